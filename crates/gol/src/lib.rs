@@ -0,0 +1,130 @@
+//! ライフゲームの更新規則と1世代分の更新処理
+//!
+//! wasm-game-of-lifeとweb-serverで同じ更新結果になるように共有する。
+//! セルは行優先で`row * width + column`の位置に並べ、上下左右の端はつながっているものとする
+
+use fixedbitset::FixedBitSet;
+
+/// Outer-Totalistic形式の更新規則
+///
+/// bit kが立っていれば、周囲の生存セルがk個のときに誕生/生存する
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Rule {
+    pub birth: u16,
+    pub survival: u16,
+}
+
+impl Rule {
+    /// B3/S23
+    pub const CONWAY: Self = Self {
+        birth: 1 << 3,
+        survival: (1 << 2) | (1 << 3),
+    };
+
+    /// 周囲の生存セル数から次の状態を決める
+    pub fn next(&self, alive: bool, live_neighbors: u8) -> bool {
+        let mask = if alive { self.survival } else { self.birth };
+        mask & (1 << live_neighbors) != 0
+    }
+
+    /// B3/S23形式で表す
+    pub fn to_rle(self) -> String {
+        let digits = |mask: u16| {
+            (0..=8)
+                .filter(|k| mask & (1 << k) != 0)
+                .map(|k| char::from(b'0' + k as u8))
+                .collect::<String>()
+        };
+        format!("B{}/S{}", digits(self.birth), digits(self.survival))
+    }
+
+    /// B3/S23形式を読み込む
+    pub fn from_rle(s: &str) -> Option<Rule> {
+        let mask = |part: &str, prefix: char| {
+            let digits = part.strip_prefix([prefix, prefix.to_ascii_lowercase()])?;
+            digits
+                .chars()
+                .try_fold(0u16, |mask, c| match c.to_digit(10) {
+                    Some(k) if k <= 8 => Some(mask | (1 << k)),
+                    _ => None,
+                })
+        };
+        let (birth, survival) = s.trim().split_once('/')?;
+        Some(Rule {
+            birth: mask(birth, 'B')?,
+            survival: mask(survival, 'S')?,
+        })
+    }
+}
+
+impl Default for Rule {
+    fn default() -> Self {
+        Self::CONWAY
+    }
+}
+
+/// 指定セルのビット位置
+#[inline]
+pub fn index(width: u32, row: u32, column: u32) -> usize {
+    (row * width + column) as usize
+}
+
+/// 指定セル周辺の生存セルの数を返す
+pub fn live_neighbor_count(
+    cells: &FixedBitSet,
+    width: u32,
+    height: u32,
+    row: u32,
+    column: u32,
+) -> u8 {
+    let mut count = 0;
+    for delta_row in [height - 1, 0, 1] {
+        for delta_col in [width - 1, 0, 1] {
+            if delta_row == 0 && delta_col == 0 {
+                continue;
+            }
+            let neighbor_row = (row + delta_row) % height;
+            let neighbor_col = (column + delta_col) % width;
+            count += cells[index(width, neighbor_row, neighbor_col)] as u8;
+        }
+    }
+    count
+}
+
+/// 1世代進めたセルを返す
+pub fn next_generation(cells: &FixedBitSet, width: u32, height: u32, rule: Rule) -> FixedBitSet {
+    let mut next = cells.clone();
+    for row in 0..height {
+        for col in 0..width {
+            let idx = index(width, row, col);
+            let live_neighbors = live_neighbor_count(cells, width, height, row, col);
+            next.set(idx, rule.next(cells[idx], live_neighbors));
+        }
+    }
+    next
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_generation_blinker() {
+        let mut cells = FixedBitSet::with_capacity(25);
+        // 横向きのブリンカーは縦向きになる
+        for col in 1..4 {
+            cells.insert(index(5, 2, col));
+        }
+        let next = next_generation(&cells, 5, 5, Rule::CONWAY);
+        assert_eq!(next.ones().collect::<Vec<_>>(), vec![7, 12, 17]);
+        assert_eq!(next_generation(&next, 5, 5, Rule::CONWAY), cells);
+    }
+
+    #[test]
+    fn test_rule_rle() {
+        assert_eq!(Rule::CONWAY.to_rle(), "B3/S23");
+        assert_eq!(Rule::from_rle("b36/s23").unwrap().to_rle(), "B36/S23");
+        assert_eq!(Rule::from_rle("B9/S23"), None);
+        assert_eq!(Rule::from_rle("B3S23"), None);
+    }
+}
@@ -0,0 +1,75 @@
+//! 離散時間LQR制御のための計算
+//!
+//! 2状態1入力の系を対象とする
+
+use nalgebra::{Matrix2, Vector2};
+
+pub mod sim;
+
+pub type Mat2 = Matrix2<f32>;
+pub type Vec2 = Vector2<f32>;
+
+/// 離散時間リカッチ方程式(DARE)を反復計算で解く
+///
+/// `P = A'PA - (A'PB)(R + B'PB)^-1(B'PA) + Q` を更新前後の差の
+/// フロベニウスノルムが`tol`未満になるまで繰り返す。
+/// `max_iter`回で収束しない場合は`None`を返す
+pub fn dare(a: &Mat2, b: &Vector2<f32>, q: &Mat2, r: f32, max_iter: u32, tol: f32) -> Option<Mat2> {
+    let at = a.transpose();
+    let mut p = *q;
+    for _ in 0..max_iter {
+        let atpb = at * p * b;
+        let s = r + (b.transpose() * p * b)[0];
+        let next = at * p * a - atpb * atpb.transpose() / s + q;
+        let diff = (next - p).norm();
+        p = next;
+        if diff < tol {
+            return Some(p);
+        }
+    }
+    None
+}
+
+/// リカッチ方程式の解から最適ゲイン`K = (R + B'PB)^-1 B'P`を求める
+///
+/// Aを含まない形で返すので、制御入力は`u = -K'Ax`で与える
+pub fn lqr_gain(p: &Mat2, b: &Vector2<f32>, r: f32) -> Vector2<f32> {
+    let s = r + (b.transpose() * p * b)[0];
+    p * b / s
+}
+
+/// 速度を積算して移動する2次元の点
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DeltaPoint2 {
+    pub position: Vec2,
+}
+
+impl DeltaPoint2 {
+    pub fn new(x: f32, y: f32) -> Self {
+        Self {
+            position: Vec2::new(x, y),
+        }
+    }
+
+    /// 移動量を加える
+    pub fn apply(&mut self, delta: &Vec2) {
+        self.position += delta;
+    }
+
+    /// 各成分を`[-bounds, bounds]`の範囲に周期的に折り返す
+    pub fn wrap_boundary(&mut self, bounds: f32) {
+        let width = bounds * 2.0;
+        for v in self.position.iter_mut() {
+            *v = (*v + bounds).rem_euclid(width) - bounds;
+        }
+    }
+
+    /// 次の移動で`[-bounds, bounds]`を超える成分の速度を反転する
+    pub fn bounce_boundary(&mut self, bounds: f32, velocity: &mut Vec2) {
+        for (p, v) in self.position.iter().zip(velocity.iter_mut()) {
+            if (p + *v).abs() > bounds {
+                *v = -*v;
+            }
+        }
+    }
+}
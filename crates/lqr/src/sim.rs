@@ -0,0 +1,47 @@
+//! 状態方程式の数値積分
+
+use crate::Vec2;
+
+/// 1ステップの積分方法
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IntegrationMethod {
+    /// 1次のオイラー法
+    Euler,
+    /// 2次のルンゲ=クッタ法(中点法)
+    Rk2,
+    /// 4次のルンゲ=クッタ法
+    Rk4,
+}
+
+/// 時間刻み`dt`で`dx/dt = f(x)`を積分する
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct DiscreteIntegrator {
+    pub dt: f32,
+    pub method: IntegrationMethod,
+}
+
+impl DiscreteIntegrator {
+    pub fn new(dt: f32, method: IntegrationMethod) -> Self {
+        Self { dt, method }
+    }
+
+    /// 状態を1ステップ進める
+    pub fn step<F: Fn(Vec2) -> Vec2>(&self, state: Vec2, f: F) -> Vec2 {
+        let dt = self.dt;
+        match self.method {
+            IntegrationMethod::Euler => state + f(state) * dt,
+            IntegrationMethod::Rk2 => {
+                let k1 = f(state);
+                let k2 = f(state + k1 * (dt / 2.0));
+                state + k2 * dt
+            }
+            IntegrationMethod::Rk4 => {
+                let k1 = f(state);
+                let k2 = f(state + k1 * (dt / 2.0));
+                let k3 = f(state + k2 * (dt / 2.0));
+                let k4 = f(state + k3 * dt);
+                state + (k1 + k2 * 2.0 + k3 * 2.0 + k4) * (dt / 6.0)
+            }
+        }
+    }
+}
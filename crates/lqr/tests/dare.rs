@@ -0,0 +1,72 @@
+use lqr::{dare, lqr_gain, Mat2};
+use nalgebra::Vector2;
+
+// 二重積分系 x = [位置, 速度], u = 加速度
+fn double_integrator(dt: f32) -> (Mat2, Vector2<f32>) {
+    let a = Mat2::new(1.0, dt, 0.0, 1.0);
+    let b = Vector2::new(0.5 * dt * dt, dt);
+    (a, b)
+}
+
+fn riccati_residual(a: &Mat2, b: &Vector2<f32>, q: &Mat2, r: f32, p: &Mat2) -> f32 {
+    let at = a.transpose();
+    let atpb = at * p * b;
+    let s = r + (b.transpose() * p * b)[0];
+    let rhs = at * p * a - atpb * atpb.transpose() / s + q;
+    (rhs - p).norm()
+}
+
+#[test]
+fn test_dare_decoupled() {
+    // 対角な系は状態毎のスカラーのリカッチ方程式になる
+    // a=1,b=1,q=1,r=1 : p^2 - p - 1 = 0 -> p = 黄金比
+    // a=0.5,b=0,q=1   : p = 0.25p + 1 -> p = 4/3
+    let a = Mat2::new(1.0, 0.0, 0.0, 0.5);
+    let b = Vector2::new(1.0, 0.0);
+    let q = Mat2::identity();
+    let p = dare(&a, &b, &q, 1.0, 1000, 1e-6).unwrap();
+    let golden = (1.0 + 5.0_f32.sqrt()) / 2.0;
+    assert!((p[(0, 0)] - golden).abs() < 1e-4, "{p}");
+    assert!((p[(1, 1)] - 4.0 / 3.0).abs() < 1e-4, "{p}");
+    assert!(p[(0, 1)].abs() < 1e-6);
+    assert!(p[(1, 0)].abs() < 1e-6);
+
+    // スカラー系の最適ゲイン k = p / (r + p)
+    let k = lqr_gain(&p, &b, 1.0);
+    assert!((k[0] - golden / (1.0 + golden)).abs() < 1e-4);
+    assert_eq!(k[1], 0.0);
+}
+
+#[test]
+fn test_dare_double_integrator() {
+    let (a, b) = double_integrator(0.1);
+    let q = Mat2::identity();
+    let r = 1.0;
+    let p = dare(&a, &b, &q, r, 10_000, 1e-5).unwrap();
+    assert!(riccati_residual(&a, &b, &q, r, &p) < 1e-3);
+    // 解は対称正定値
+    assert!((p[(0, 1)] - p[(1, 0)]).abs() < 1e-3);
+    assert!(p[(0, 0)] > 0.0 && p.determinant() > 0.0);
+
+    // 閉ループ系 (I - BK')A の固有値が単位円内に収まる
+    let k = lqr_gain(&p, &b, r);
+    let closed = (Mat2::identity() - b * k.transpose()) * a;
+    let eigen = closed.complex_eigenvalues();
+    assert!(eigen.iter().all(|e| e.norm() < 1.0), "{eigen}");
+
+    // 初期位置からの応答が原点に収束する
+    let mut x = Vector2::new(1.0, 0.0);
+    for _ in 0..1000 {
+        x = closed * x;
+    }
+    assert!(x.norm() < 1e-3);
+}
+
+#[test]
+fn test_dare_not_converged() {
+    // 不安定で可制御でない系は収束しない
+    let a = Mat2::new(2.0, 0.0, 0.0, 2.0);
+    let b = Vector2::new(0.0, 0.0);
+    let q = Mat2::identity();
+    assert!(dare(&a, &b, &q, 1.0, 100, 1e-6).is_none());
+}
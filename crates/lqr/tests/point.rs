@@ -0,0 +1,28 @@
+use lqr::{DeltaPoint2, Vec2};
+
+fn assert_near(a: &Vec2, b: &Vec2) {
+    assert!((a - b).norm() < 1e-5, "{a} != {b}");
+}
+
+#[test]
+fn test_wrap_boundary() {
+    let mut p = DeltaPoint2::new(1.5, -2.1);
+    p.wrap_boundary(1.0);
+    // 周期は範囲幅の2.0
+    assert_near(&p.position, &Vec2::new(-0.5, -0.1));
+
+    // 範囲内の点は変化しない
+    let mut p = DeltaPoint2::new(0.3, -0.7);
+    p.wrap_boundary(1.0);
+    assert_near(&p.position, &Vec2::new(0.3, -0.7));
+}
+
+#[test]
+fn test_bounce_boundary() {
+    let mut p = DeltaPoint2::new(0.9, 0.0);
+    let mut vel = Vec2::new(0.2, 0.0);
+    p.bounce_boundary(1.0, &mut vel);
+    p.apply(&vel);
+    assert_near(&vel, &Vec2::new(-0.2, 0.0));
+    assert_near(&p.position, &Vec2::new(0.7, 0.0));
+}
@@ -0,0 +1,36 @@
+use lqr::{
+    sim::{DiscreteIntegrator, IntegrationMethod},
+    Vec2,
+};
+
+const METHODS: [IntegrationMethod; 3] = [
+    IntegrationMethod::Euler,
+    IntegrationMethod::Rk2,
+    IntegrationMethod::Rk4,
+];
+
+// dx/dt = -xを1ステップ進めた時の解析解との相対誤差
+fn step_error(dt: f32, method: IntegrationMethod) -> f32 {
+    let x0 = Vec2::new(1.0, -2.0);
+    let x = DiscreteIntegrator::new(dt, method).step(x0, |x| -x);
+    (x - x0 * (-dt).exp()).norm() / x0.norm()
+}
+
+#[test]
+fn test_integrator_converges() {
+    for method in METHODS {
+        for dt in [0.1, 0.01, 0.001] {
+            // オイラー法でも1ステップの誤差はO(dt^2)
+            let err = step_error(dt, method);
+            assert!(err < dt * dt + 1e-6, "{method:?} dt={dt} err={err}");
+        }
+    }
+}
+
+#[test]
+fn test_rk4_more_accurate_than_euler() {
+    let euler = step_error(0.1, IntegrationMethod::Euler);
+    let rk2 = step_error(0.1, IntegrationMethod::Rk2);
+    let rk4 = step_error(0.1, IntegrationMethod::Rk4);
+    assert!(rk4 < rk2 && rk2 < euler, "{euler} {rk2} {rk4}");
+}
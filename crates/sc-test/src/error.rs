@@ -4,4 +4,6 @@ pub type Result<T> = std::result::Result<T, Error>;
 pub enum Error {
     #[error("Any error: {0}")]
     Any(#[from] anyhow::Error),
+    #[error("Send error: {0}")]
+    Send(String),
 }
@@ -5,6 +5,7 @@ use tokio::sync::mpsc;
 use tokio_util::sync::CancellationToken;
 
 pub mod error;
+pub mod recorder;
 
 // 独自にループ処理を含む実行フローを持つ処理の例
 // このアクターの場合は自身の速度を元に経時変化で位置を更新する
@@ -24,26 +25,36 @@ impl Actor {
         }
     }
 
+    // 位置を更新して購読者に通知する
+    // 送信に失敗した購読者があれば出力する
     pub fn update(&mut self, dt: f32) {
+        if let Err(e) = self.try_update(dt) {
+            println!("Failed to send position {}", e);
+        }
+    }
+
+    // 位置を更新して購読者に通知する
+    // 送信に失敗した場合も全員に通知してから最後のエラーを返す。閉じた購読者は取り除く
+    pub fn try_update(&mut self, dt: f32) -> crate::error::Result<()> {
         self.position += self.velocity * dt;
+        let mut result = Ok(());
         for tx in self.sender_queue.iter() {
-            if tx.is_closed() {
-                continue;
-            }
-            match tx.try_send(self.position) {
-                Ok(_) => {}
-                Err(e) => {
-                    println!("Failed to send position {}", e);
-                }
+            if let Err(e) = tx.try_send(self.position) {
+                result = Err(crate::error::Error::Send(e.to_string()));
             }
         }
         self.sender_queue.retain(|tx| !tx.is_closed());
+        result
     }
 
     pub fn get_position(&self) -> f32 {
         self.position
     }
 
+    pub fn get_velocity(&self) -> f32 {
+        self.velocity
+    }
+
     pub fn set_velocity(&mut self, velocity: f32) {
         self.velocity = velocity;
     }
@@ -71,8 +82,10 @@ impl StActor for Actor {
     ) -> Result<(), Self::Error> {
         let mut interval = tokio::time::interval(Duration::from_millis(100));
         loop {
-            self.update(0.1);
-            self.recv(rx).await?;
+            let result = self.try_update(0.1);
+            continue_on_error(self, result)?;
+            let result = self.recv(rx).await;
+            continue_on_error(self, result)?;
             tokio::select! {
                 _ = token.cancelled() => {
                     break;
@@ -83,6 +96,14 @@ impl StActor for Actor {
         println!("Actor shutdown");
         Ok(())
     }
+
+    // 購読者が居なくなっただけなら動作を続ける
+    fn on_error(&mut self, err: &Self::Error) -> ErrorPolicy {
+        match err {
+            crate::error::Error::Send(_) => ErrorPolicy::Continue,
+            _ => ErrorPolicy::Stop,
+        }
+    }
 }
 
 // 今回のアクターはイベント駆動で記述しているので、メッセージの種類を列挙しておく
@@ -91,6 +112,17 @@ pub enum ActorIn {
     PosReader(mpsc::Sender<f32>),
 }
 
+// startの中で起きたエラーの扱い
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    // エラーを出力して処理を続ける。startを抜けた場合はStWrapperがstartを呼び直す
+    Continue,
+    // 内側のループを抜け、StWrapperがstartからやり直す
+    Restart,
+    // エラーを呼び出し元に返して終了する
+    Stop,
+}
+
 // アクターのトレイト。
 // 処理の起動方法とメッセージの受信処理を定義
 // 状態を持つアクターを保持してそれを更新する
@@ -109,6 +141,29 @@ pub trait StActor {
         token: CancellationToken,
         rx: &mut mpsc::Receiver<Self::Msg>,
     ) -> impl std::future::Future<Output = Result<(), Self::Error>>;
+    // エラーが起きた時の方針を決める。既定ではエラーを返して終了する
+    fn on_error(&mut self, _err: &Self::Error) -> ErrorPolicy {
+        ErrorPolicy::Stop
+    }
+}
+
+/// on_errorの方針がContinueならエラーを出力して続行し、それ以外はエラーを返す
+///
+/// startの実装から呼び出して使う
+pub fn continue_on_error<A: StActor>(
+    actor: &mut A,
+    result: Result<(), A::Error>,
+) -> Result<(), A::Error>
+where
+    A::Error: std::fmt::Display,
+{
+    match result {
+        Err(e) if actor.on_error(&e) == ErrorPolicy::Continue => {
+            println!("Continue after error: {e}");
+            Ok(())
+        }
+        result => result,
+    }
 }
 
 // アクターに対してメッセージを送受信する口を提供するラッパー
@@ -143,9 +198,39 @@ where
         self.state.recv(&mut self.in_rx).await
     }
 
+    // Restartでやり直す最大回数
+    const MAX_RESTARTS: u32 = 3;
+    // やり直すまでの待ち時間。Restartの度に倍にする
+    const RESTART_DELAY: Duration = Duration::from_millis(50);
+
+    // エラーで終了した場合はon_errorの方針に従う
+    // Continueは最短の待ち時間でstartからやり直し、Restartは待ち時間を倍にしながら最大回数までやり直す
     pub async fn start(&mut self, token: CancellationToken) -> Result<(), T::Error> {
         println!("start_task");
-        self.state.start(token, &mut self.in_rx).await?;
+        let mut restarts = 0;
+        loop {
+            let err = match self.state.start(token.clone(), &mut self.in_rx).await {
+                Ok(()) => break,
+                Err(e) => e,
+            };
+            let delay = match self.state.on_error(&err) {
+                ErrorPolicy::Continue => {
+                    println!("Continue after error: {err}");
+                    // 失敗し続けてもCPUを占有しないように最短の待ち時間は置く
+                    Self::RESTART_DELAY
+                }
+                ErrorPolicy::Restart if restarts < Self::MAX_RESTARTS => {
+                    println!("Restart after error: {err}");
+                    restarts += 1;
+                    Self::RESTART_DELAY * 2u32.pow(restarts - 1)
+                }
+                ErrorPolicy::Restart | ErrorPolicy::Stop => return Err(err),
+            };
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = tokio::time::sleep(delay) => {}
+            }
+        }
         println!("shutdown");
         Ok(())
     }
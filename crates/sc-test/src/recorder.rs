@@ -0,0 +1,119 @@
+//! アクターの状態を時系列でCSVに記録する
+//!
+//! P制御のチューニングなどをオフラインで解析するために使う
+
+use std::{fs::File, path::Path, time::Duration};
+
+use anyhow::Context;
+use tokio::{sync::mpsc, time::Instant};
+use tokio_util::sync::CancellationToken;
+
+use crate::{continue_on_error, Actor, ActorIn, ErrorPolicy, StActor};
+
+/// `(timestamp_ms, position, velocity)` の行をCSVに書き出す
+pub struct SimulationRecorder {
+    writer: csv::Writer<File>,
+    interval_ms: u64,
+    // 次に記録する時刻
+    next_ms: u64,
+}
+
+impl SimulationRecorder {
+    pub fn new(path: impl AsRef<Path>, interval_ms: u64) -> crate::error::Result<Self> {
+        let mut writer = csv::Writer::from_path(path).context("open csv")?;
+        writer
+            .write_record(["timestamp_ms", "position", "velocity"])
+            .context("write csv header")?;
+        Ok(Self {
+            writer,
+            interval_ms,
+            next_ms: 0,
+        })
+    }
+
+    /// 前回の記録から`interval_ms`以上経過していれば1行記録する
+    pub fn record(
+        &mut self,
+        timestamp_ms: u64,
+        position: f32,
+        velocity: f32,
+    ) -> crate::error::Result<()> {
+        if timestamp_ms < self.next_ms {
+            return Ok(());
+        }
+        self.writer
+            .serialize((timestamp_ms, position, velocity))
+            .context("write csv record")?;
+        // 処理の遅れで記録間隔がずれないように予定時刻を基準に進める
+        while self.next_ms <= timestamp_ms {
+            self.next_ms += self.interval_ms.max(1);
+        }
+        Ok(())
+    }
+}
+
+impl Drop for SimulationRecorder {
+    fn drop(&mut self) {
+        if let Err(e) = self.writer.flush() {
+            println!("Failed to flush csv {}", e);
+        }
+    }
+}
+
+/// [Actor]の更新毎に状態を記録するアクター
+pub struct RecordingActor {
+    inner: Actor,
+    recorder: SimulationRecorder,
+}
+
+impl RecordingActor {
+    pub fn new(inner: Actor, recorder: SimulationRecorder) -> Self {
+        Self { inner, recorder }
+    }
+
+    pub fn into_inner(self) -> Actor {
+        self.inner
+    }
+}
+
+impl StActor for RecordingActor {
+    type Msg = ActorIn;
+    type Error = crate::error::Error;
+
+    async fn recv(&mut self, rx: &mut mpsc::Receiver<Self::Msg>) -> Result<(), Self::Error> {
+        self.inner.recv(rx).await
+    }
+
+    // Actor::startと同じ周期で更新し、更新後の状態を記録する
+    async fn start(
+        &mut self,
+        token: CancellationToken,
+        rx: &mut mpsc::Receiver<Self::Msg>,
+    ) -> Result<(), Self::Error> {
+        let started = Instant::now();
+        let mut interval = tokio::time::interval(Duration::from_millis(100));
+        loop {
+            let result = self.inner.try_update(0.1);
+            continue_on_error(self, result)?;
+            let result = self.recv(rx).await;
+            continue_on_error(self, result)?;
+            self.recorder.record(
+                started.elapsed().as_millis() as u64,
+                self.inner.get_position(),
+                self.inner.get_velocity(),
+            )?;
+            tokio::select! {
+                _ = token.cancelled() => {
+                    break;
+                }
+                _ = interval.tick() => {}
+            }
+        }
+        println!("RecordingActor shutdown");
+        Ok(())
+    }
+
+    fn on_error(&mut self, err: &Self::Error) -> ErrorPolicy {
+        self.inner.on_error(err)
+    }
+}
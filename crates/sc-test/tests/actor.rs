@@ -0,0 +1,98 @@
+use std::time::Duration;
+
+use sc_test::{error::Error, Actor, ActorIn, ErrorPolicy, StActor, StWrapper};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+
+#[tokio::test]
+async fn test_on_error_continue() {
+    let mut actor = StWrapper::new(Actor::new(0.0, 1.0));
+    assert_eq!(
+        actor.as_mut().on_error(&Error::Send("closed".into())),
+        ErrorPolicy::Continue
+    );
+    assert_eq!(
+        actor
+            .as_mut()
+            .on_error(&Error::Any(anyhow::anyhow!("other"))),
+        ErrorPolicy::Stop
+    );
+
+    // 受信側を閉じた購読者を登録して送信エラーを起こす
+    let (tx, rx) = mpsc::channel(10);
+    drop(rx);
+    actor.tx().send(ActorIn::PosReader(tx)).await.unwrap();
+
+    let token = CancellationToken::new();
+    let cancel = async {
+        tokio::time::sleep(Duration::from_millis(500)).await;
+        token.cancel();
+        Ok(())
+    };
+    tokio::try_join!(actor.start(token.clone()), cancel).unwrap();
+
+    // エラー後も更新が続いている
+    assert!(actor.as_ref().get_position() > 0.3);
+}
+
+// 毎回決まった方針のエラーでstartを抜けるアクター
+struct FailingActor {
+    policy: ErrorPolicy,
+    starts: u32,
+}
+
+impl StActor for FailingActor {
+    type Msg = ();
+    type Error = Error;
+
+    async fn recv(&mut self, _rx: &mut mpsc::Receiver<Self::Msg>) -> Result<(), Self::Error> {
+        Ok(())
+    }
+
+    async fn start(
+        &mut self,
+        _token: CancellationToken,
+        _rx: &mut mpsc::Receiver<Self::Msg>,
+    ) -> Result<(), Self::Error> {
+        self.starts += 1;
+        Err(Error::Any(anyhow::anyhow!("failed")))
+    }
+
+    fn on_error(&mut self, _err: &Self::Error) -> ErrorPolicy {
+        self.policy
+    }
+}
+
+#[tokio::test]
+async fn test_restart_limit() {
+    let mut actor = StWrapper::new(FailingActor {
+        policy: ErrorPolicy::Restart,
+        starts: 0,
+    });
+    let started = std::time::Instant::now();
+    assert!(actor.start(CancellationToken::new()).await.is_err());
+    // 最初の1回と3回のやり直し
+    assert_eq!(actor.as_ref().starts, 4);
+    // 50ms, 100ms, 200msの待ち時間を置いている
+    assert!(started.elapsed() >= Duration::from_millis(350));
+}
+
+#[tokio::test]
+async fn test_wrapper_continue() {
+    let mut actor = StWrapper::new(FailingActor {
+        policy: ErrorPolicy::Continue,
+        starts: 0,
+    });
+    let token = CancellationToken::new();
+    let cancel = async {
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        token.cancel();
+        Ok(())
+    };
+    // Continueは回数に制限なくやり直し、キャンセルで正常に終了する
+    tokio::try_join!(actor.start(token.clone()), cancel).unwrap();
+    let starts = actor.as_ref().starts;
+    assert!(starts > 4, "{starts}");
+    // 待ち時間を置いているので空回りしない
+    assert!(starts < 10, "{starts}");
+}
@@ -0,0 +1,33 @@
+use std::time::Duration;
+
+use sc_test::{
+    recorder::{RecordingActor, SimulationRecorder},
+    Actor, StWrapper,
+};
+use tokio_util::sync::CancellationToken;
+
+#[tokio::test]
+async fn test_recording_actor() {
+    let path = std::env::temp_dir().join(format!("sc-test-recorder-{}.csv", std::process::id()));
+    {
+        let recorder = SimulationRecorder::new(&path, 100).unwrap();
+        let mut actor = StWrapper::new(RecordingActor::new(Actor::new(0.0, 1.0), recorder));
+        let token = CancellationToken::new();
+        let cancel = async {
+            tokio::time::sleep(Duration::from_secs(1)).await;
+            token.cancel();
+            Ok(())
+        };
+        tokio::try_join!(actor.start(token.clone()), cancel).unwrap();
+    }
+
+    let mut reader = csv::Reader::from_path(&path).unwrap();
+    let rows = reader
+        .deserialize::<(u64, f32, f32)>()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    std::fs::remove_file(&path).unwrap();
+
+    assert!(rows.len() >= 9, "rows: {}", rows.len());
+    assert!(rows.windows(2).all(|w| w[0].0 < w[1].0));
+}
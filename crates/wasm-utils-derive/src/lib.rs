@@ -10,18 +10,26 @@ use syn::{
 };
 
 /// InputのSelectOptionを実装するためのマクロ
+///
+/// 選択肢になるのはunit variantのみ。`Custom(f32)`のように中身を持つVariantは
+/// `iter`で列挙する値を作れず、`value`も中身を文字列として借用できないため対応しない。
+/// そのようなVariantには`#[select(skip)]`を付けて選択肢から除外する
 #[proc_macro_derive(Select, attributes(select))]
 pub fn derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
     // DeriveInputを取得
     let input = parse_macro_input!(input as DeriveInput);
 
     // Enumの各Variantを取得
-    let variants = get_variants(&input);
+    let variants = match get_variants(&input) {
+        Ok(variants) => variants,
+        Err(e) => return e.to_compile_error().into(),
+    };
 
     // バリアントを元に各メソッドを生成
     let iter = gen_iter_method(&variants);
     let value = gen_value_method(&variants);
-    let from_str = gen_from_str_method(&variants);
+    let from_str = gen_from_str_method();
+    let try_from_str = gen_try_from_str_method(&variants);
     let text = gen_text_method(&variants);
 
     // impl SelectOption の実装を生成
@@ -32,6 +40,7 @@ pub fn derive(input: proc_macro::TokenStream) -> proc_macro::TokenStream {
             #iter
             #value
             #from_str
+            #try_from_str
             #text
         }
     };
@@ -58,13 +67,42 @@ impl Variant {
         &self.variant.ident
     }
 
-    fn value(&self) -> &str {
-        if let Some(attr) = self.attrs.first() {
-            match attr {
-                SelectAttr::Value(ref value) => return value,
-            }
+    // 中身を持つVariantにも一致するパターン
+    fn pattern(&self) -> TokenStream {
+        let name = &self.name;
+        let variant = self.variant();
+        match self.variant.fields {
+            syn::Fields::Unit => quote! { #name::#variant },
+            syn::Fields::Unnamed(_) => quote! { #name::#variant(..) },
+            syn::Fields::Named(_) => quote! { #name::#variant { .. } },
         }
-        &self.value
+    }
+
+    fn value(&self) -> &str {
+        self.attrs
+            .iter()
+            .find_map(|attr| match attr {
+                SelectAttr::Value(value) => Some(value.as_str()),
+                _ => None,
+            })
+            .unwrap_or(&self.value)
+    }
+
+    fn display(&self) -> &str {
+        self.attrs
+            .iter()
+            .find_map(|attr| match attr {
+                SelectAttr::Display(display) => Some(display.as_str()),
+                _ => None,
+            })
+            .unwrap_or(&self.display)
+    }
+
+    // iter及びfrom_strから除外するか
+    fn skip(&self) -> bool {
+        self.attrs
+            .iter()
+            .any(|attr| matches!(attr, SelectAttr::Skip))
     }
 }
 
@@ -73,6 +111,10 @@ impl Variant {
 enum SelectAttr {
     // valueの変更
     Value(String),
+    // 表示用の値の変更
+    Display(String),
+    // 選択肢から除外する
+    Skip,
 }
 
 impl Parse for SelectAttr {
@@ -80,24 +122,28 @@ impl Parse for SelectAttr {
         let name: Ident = input.parse()?;
         let name_str = name.to_string();
 
-        // メモ: nameによって値の有無などが変わる場合、この分岐を増やすことになる
-        let value = if input.peek(Token![=]) {
-            input.parse::<Token![=]>()?;
-            let value: syn::LitStr = input.parse()?;
-            value.value()
-        } else {
-            // 現時点ではKey=Valueの形式のみ使用しているのでそれ以外はエラーとする
-            return Err(syn::Error::new_spanned(name, "expected `=` after `name`"));
-        };
+        // 値を持たないのはskipのみ
+        if !input.peek(Token![=]) {
+            return match name_str.as_str() {
+                "skip" => Ok(SelectAttr::Skip),
+                _ => Err(syn::Error::new_spanned(name, "expected `=` after `name`")),
+            };
+        }
+        input.parse::<Token![=]>()?;
+        let value: syn::LitStr = input.parse()?;
+        let value = value.value();
         match name_str.as_str() {
             "value" => Ok(SelectAttr::Value(value)),
+            "display" => Ok(SelectAttr::Display(value)),
             _ => Err(syn::Error::new_spanned(name, "unknown attribute")),
         }
     }
 }
 
 /// enumの中身を取り出す
-fn get_variants(input: &DeriveInput) -> Vec<Variant> {
+///
+/// 中身を持つVariantはiterで列挙できないため、`#[select(skip)]`が無ければエラーとする
+fn get_variants(input: &DeriveInput) -> syn::Result<Vec<Variant>> {
     let mut variants = Vec::new();
 
     if let syn::Data::Enum(ref e) = input.data {
@@ -107,16 +153,9 @@ fn get_variants(input: &DeriveInput) -> Vec<Variant> {
             for attr in &variant.attrs {
                 // select属性があるなら中身を取り出す
                 if attr.path().is_ident("select") {
-                    if let Ok(attr) =
-                        attr.parse_args_with(Punctuated::<SelectAttr, Token![,]>::parse_terminated)
-                    {
-                        for attr in attr {
-                            println!("find attr {:?}", attr);
-                            attrs.push(attr);
-                        }
-                    }
-                } else {
-                    continue;
+                    let attr = attr
+                        .parse_args_with(Punctuated::<SelectAttr, Token![,]>::parse_terminated)?;
+                    attrs.extend(attr);
                 }
             }
 
@@ -130,16 +169,29 @@ fn get_variants(input: &DeriveInput) -> Vec<Variant> {
                 display: variant.ident.to_string(),
                 attrs,
             });
+            let v = variants.last().unwrap();
+            if !v.skip() && !matches!(variant.fields, syn::Fields::Unit) {
+                return Err(syn::Error::new_spanned(
+                    variant,
+                    "variant with fields requires `#[select(skip)]`",
+                ));
+            }
         }
     }
 
-    variants
+    if variants.iter().all(Variant::skip) {
+        return Err(syn::Error::new_spanned(
+            &input.ident,
+            "Select requires at least one selectable unit variant",
+        ));
+    }
+    Ok(variants)
 }
 
 // iterメソッドの生成
 fn gen_iter_method(variants: &[Variant]) -> TokenStream {
     let name = variants[0].name.clone();
-    let arms = variants.iter().map(|v| {
+    let arms = variants.iter().filter(|v| !v.skip()).map(|v| {
         let variant = &v.variant();
         quote! {
             #name::#variant
@@ -154,12 +206,11 @@ fn gen_iter_method(variants: &[Variant]) -> TokenStream {
 
 // valueメソッドの生成
 fn gen_value_method(variants: &[Variant]) -> TokenStream {
-    let name = variants[0].name.clone();
     let arms = variants.iter().map(|v| {
-        let variant = &v.variant();
+        let pattern = v.pattern();
         let value = v.value();
         quote! {
-            #name::#variant => #value,
+            #pattern => #value,
         }
     });
     quote! {
@@ -171,21 +222,30 @@ fn gen_value_method(variants: &[Variant]) -> TokenStream {
     }
 }
 
-// from_strメソッドの生成
-fn gen_from_str_method(variants: &[Variant]) -> TokenStream {
+// from_strメソッドの生成。未知の値はpanicする
+fn gen_from_str_method() -> TokenStream {
+    quote! {
+        fn from_str(value: &str) -> Self {
+            Self::try_from_str(value).unwrap_or_else(|| panic!("Invalid value: {}", value))
+        }
+    }
+}
+
+// try_from_strメソッドの生成
+fn gen_try_from_str_method(variants: &[Variant]) -> TokenStream {
     let name = variants[0].name.clone();
-    let arms = variants.iter().map(|v| {
+    let arms = variants.iter().filter(|v| !v.skip()).map(|v| {
         let value = v.value();
         let variant = &v.variant();
         quote! {
-            #value => #name::#variant,
+            #value => Some(#name::#variant),
         }
     });
     quote! {
-        fn from_str(value: &str) -> Self {
+        fn try_from_str(value: &str) -> Option<Self> {
             match value {
                 #(#arms)*
-                _ => panic!("Invalid value: {}", value),
+                _ => None,
             }
         }
     }
@@ -193,12 +253,11 @@ fn gen_from_str_method(variants: &[Variant]) -> TokenStream {
 
 // textメソッドの生成
 fn gen_text_method(variants: &[Variant]) -> TokenStream {
-    let name = variants[0].name.clone();
     let arms = variants.iter().map(|v| {
-        let variant = &v.variant();
-        let display = &v.display;
+        let pattern = v.pattern();
+        let display = v.display();
         quote! {
-            #name::#variant => #display,
+            #pattern => #display,
         }
     });
     quote! {
@@ -0,0 +1,48 @@
+use wasm_utils::input::SelectOption;
+use wasm_utils_derive::Select;
+
+#[derive(Debug, Clone, Copy, PartialEq, Select)]
+enum Speed {
+    #[select(skip)]
+    Custom(f32),
+    #[select(display = "ゆっくり")]
+    Slow,
+    #[select(value = "max", display = "最速")]
+    Fast,
+    #[select(skip, display = "一時停止")]
+    Paused { at: u32 },
+}
+
+#[test]
+fn test_select_skip() {
+    assert_eq!(Speed::iter(), &[Speed::Slow, Speed::Fast]);
+    assert_eq!(Speed::from_str("slow"), Speed::Slow);
+    assert_eq!(Speed::from_str("max"), Speed::Fast);
+
+    // 除外したVariantもvalueとtextは持つ
+    assert_eq!(Speed::Custom(1.5).value(), "custom");
+    assert_eq!(Speed::Custom(1.5).text(), "Custom");
+    assert_eq!(Speed::Paused { at: 3 }.text(), "一時停止");
+}
+
+#[test]
+#[should_panic(expected = "Invalid value: custom")]
+fn test_select_skip_from_str() {
+    Speed::from_str("custom");
+}
+
+#[test]
+fn test_select_display() {
+    assert_eq!(Speed::Slow.value(), "slow");
+    assert_eq!(Speed::Slow.text(), "ゆっくり");
+    assert_eq!(Speed::Fast.value(), "max");
+    assert_eq!(Speed::Fast.text(), "最速");
+}
+
+#[test]
+fn test_select_try_from_str() {
+    assert_eq!(Speed::try_from_str("slow"), Some(Speed::Slow));
+    assert_eq!(Speed::try_from_str("max"), Some(Speed::Fast));
+    assert_eq!(Speed::try_from_str("fast"), None);
+    assert_eq!(Speed::try_from_str("custom"), None);
+}
@@ -1,7 +1,7 @@
 use std::path::PathBuf;
 
 use clap::{Parser, ValueEnum};
-use image::DynamicImage;
+use image::{imageops::FilterType, DynamicImage, RgbaImage};
 
 #[derive(Debug, Clone, Copy, Default, ValueEnum)]
 #[clap(rename_all = "kebab-case")]
@@ -13,6 +13,8 @@ enum Format {
     Dxt1,
     Dxt3,
     Dxt5,
+    // 2の累乗の大きさに揃えたRGBAのミップマップ列
+    MipChain,
 }
 
 impl Format {
@@ -23,6 +25,7 @@ impl Format {
             Format::Dxt1 => "dxt1",
             Format::Dxt3 => "dxt3",
             Format::Dxt5 => "dxt5",
+            Format::MipChain => "mip",
         }
     }
 
@@ -39,9 +42,49 @@ impl Format {
             Format::Dxt1 => self.encode_dds(img, image_dds::ImageFormat::BC1RgbaUnorm),
             Format::Dxt3 => self.encode_dds(img, image_dds::ImageFormat::BC2RgbaUnorm),
             Format::Dxt5 => self.encode_dds(img, image_dds::ImageFormat::BC3RgbaUnorm),
+            Format::MipChain => self.encode_mip_chain(img),
         }
     }
 
+    // 各レベルは`[width: u16, height: u16]`(リトルエンディアン)のヘッダに続けてRGBAの画素を書く
+    // 大きさを半分にしながら1x1になるまで繰り返す
+    // ヘッダに収まらない65536以上の大きさはエラーにする
+    fn encode_mip_chain(&self, img: &DynamicImage) -> anyhow::Result<Vec<u8>> {
+        let (width, height) = (
+            img.width().next_power_of_two(),
+            img.height().next_power_of_two(),
+        );
+        let mut level =
+            image::imageops::resize(&img.to_rgba8(), width, height, FilterType::Lanczos3);
+        let mut buf = Vec::new();
+        loop {
+            let (width, height) = level.dimensions();
+            let w = u16::try_from(width)
+                .map_err(|_| anyhow::anyhow!("mip width {width} does not fit in the u16 header"))?;
+            let h = u16::try_from(height).map_err(|_| {
+                anyhow::anyhow!("mip height {height} does not fit in the u16 header")
+            })?;
+            buf.extend_from_slice(&w.to_le_bytes());
+            buf.extend_from_slice(&h.to_le_bytes());
+            buf.extend_from_slice(level.as_raw());
+            if width == 1 && height == 1 {
+                break;
+            }
+            level = Self::half(&level);
+        }
+        Ok(buf)
+    }
+
+    fn half(img: &RgbaImage) -> RgbaImage {
+        let (width, height) = img.dimensions();
+        image::imageops::resize(
+            img,
+            (width / 2).max(1),
+            (height / 2).max(1),
+            FilterType::Lanczos3,
+        )
+    }
+
     fn encode_dds(
         &self,
         img: &DynamicImage,
@@ -60,6 +103,38 @@ impl Format {
     }
 }
 
+/// 変換前に適用する大きさ。片方が0の場合は縦横比を保つ
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Resize {
+    width: u32,
+    height: u32,
+}
+
+impl Resize {
+    // `WIDTHxHEIGHT`の形式で受け取る
+    fn parse(s: &str) -> Result<Self, String> {
+        let (w, h) = s
+            .split_once('x')
+            .ok_or_else(|| format!("expected WIDTHxHEIGHT: {s}"))?;
+        let width = w.parse().map_err(|e| format!("invalid width {w}: {e}"))?;
+        let height = h.parse().map_err(|e| format!("invalid height {h}: {e}"))?;
+        if width == 0 && height == 0 {
+            return Err("either width or height must be non-zero".to_string());
+        }
+        Ok(Self { width, height })
+    }
+
+    fn apply(&self, img: &DynamicImage) -> DynamicImage {
+        let (w, h) = (img.width() as u64, img.height() as u64);
+        let (width, height) = match (self.width, self.height) {
+            (0, height) => (((w * height as u64 + h / 2) / h) as u32, height),
+            (width, 0) => (width, ((h * width as u64 + w / 2) / w) as u32),
+            size => size,
+        };
+        img.resize_exact(width.max(1), height.max(1), FilterType::Lanczos3)
+    }
+}
+
 #[derive(Debug, Parser)]
 #[command(version, about, long_about = None)]
 struct Cli {
@@ -75,6 +150,10 @@ struct Cli {
         value_delimiter = ','
     )]
     format: Vec<Format>,
+
+    /// 変換前に`WIDTHxHEIGHT`の大きさにする。片方を0にすると縦横比を保つ
+    #[clap(long, value_parser = Resize::parse)]
+    resize: Option<Resize>,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -82,6 +161,10 @@ fn main() -> anyhow::Result<()> {
     println!("{:?}", args);
 
     let img = image::open(&args.input).unwrap();
+    let img = match args.resize {
+        Some(resize) => resize.apply(&img),
+        None => img,
+    };
 
     for f in &args.format {
         let output = match args.output {
@@ -100,3 +183,40 @@ fn main() -> anyhow::Result<()> {
 
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resize_keep_aspect() {
+        let resize = Resize::parse("0x128").unwrap();
+        let img = resize.apply(&DynamicImage::new_rgba8(200, 400));
+        assert_eq!((img.width(), img.height()), (64, 128));
+
+        assert!(Resize::parse("0x0").is_err());
+        assert!(Resize::parse("64").is_err());
+    }
+
+    #[test]
+    fn test_mip_chain() {
+        let img = DynamicImage::new_rgba8(100, 80);
+        let buf = Format::MipChain.encode(&img).unwrap();
+        assert_eq!(buf[..4], [128, 0, 128, 0]);
+
+        // ヘッダを辿って各レベルの大きさを確認する
+        let mut sizes = vec![];
+        let mut rest = buf.as_slice();
+        while !rest.is_empty() {
+            let width = u16::from_le_bytes([rest[0], rest[1]]) as usize;
+            let height = u16::from_le_bytes([rest[2], rest[3]]) as usize;
+            sizes.push(width);
+            rest = &rest[4 + width * height * 4..];
+        }
+        assert_eq!(sizes, vec![128, 64, 32, 16, 8, 4, 2, 1]);
+
+        // 2の累乗に揃えると65536になりヘッダに収まらない
+        let img = DynamicImage::new_rgba8(40000, 1);
+        assert!(Format::MipChain.encode(&img).is_err());
+    }
+}
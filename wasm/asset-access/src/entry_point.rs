@@ -6,16 +6,18 @@ use std::rc::Rc;
 use nalgebra::Vector2;
 use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::spawn_local;
-use wasm_utils::{animation::AnimationLoop, error::*, info};
+use wasm_utils::{animation::AnimationLoop, error, error::*, info};
 use web_sys::HtmlCanvasElement;
 use webgl2::{
     context::{gl_clear_color, COLOR_BLACK},
     gl,
-    loader::{load_texture, ImageLoader},
+    loader::load_texture,
     shader::texture::{TextureShader, TextureVd},
     texture::Texture,
 };
 
+use crate::loader::preload_all;
+
 #[wasm_bindgen(start)]
 pub fn init() -> Result<()> {
     wasm_utils::panic::set_panic_hook();
@@ -37,9 +39,10 @@ pub fn start(canvas: HtmlCanvasElement) -> std::result::Result<(), JsValue> {
     };
 
     let metrics = glctx.metrics().clone();
-    let mut textures = vec![];
+    let mut shaders = vec![];
+    let mut urls = vec![];
 
-    // テクスチャインタンスの生成と配置
+    // シェーダーインタンスの生成と配置
     let length = 100;
     for i in 0..length {
         let x = (i as f32 / length as f32 * f32::consts::PI * 2.0).sin();
@@ -51,22 +54,12 @@ pub fn start(canvas: HtmlCanvasElement) -> std::result::Result<(), JsValue> {
                 .append_translation(&Vector2::new(x / vp.aspect(), y)),
         );
         let v = s.create_vao(&webgl2::vertex::UNIT_RECT)?;
-        let texture = glctx.create_blank_texture()?;
+        shaders.push((s, v));
 
         let color_front = rgba_to_hexcode(i as u8, 0, 0, 255);
-        // 同期処理から非同期にタスクを生成
-        // ロードの完了を待たない
-        spawn_load_texture(create_img_src(i, color_front.as_str()), texture.clone());
-        textures.push(texture.clone());
-        ctx.objects.push(Drawable {
-            shader: s,
-            vao: v,
-            texture,
-        });
+        urls.push(create_img_src(i, color_front.as_str()));
     }
 
-    check_memory_usage("after spawn");
-
     // console.logにメモリの使用量などを出す
     spawn_local(async move {
         use futures_util::{future::ready, stream::StreamExt};
@@ -80,45 +73,37 @@ pub fn start(canvas: HtmlCanvasElement) -> std::result::Result<(), JsValue> {
             .await;
     });
 
-    // animation loop
-    let mut a = AnimationLoop::new(move |_time| {
-        ctx.draw();
-        Ok(())
-    });
-    a.start();
-    a.forget();
-
-    // メモリリークの有無を確認するためにテクスチャを定期的に読み出す
-    // 実際にforgetではメモリ使用量が増える付けることが確認できた
+    // 最初のフレームまでにすべてのテクスチャを揃えてから描画を始める
     spawn_local(async move {
-        use futures_util::stream::StreamExt;
-        let interval = std::time::Duration::from_secs(5);
-        let mut counter = 0;
-
-        // 画像読み出しの同時実行数
-        let par_count = 8;
-        let requests = textures.iter().enumerate().collect::<Vec<_>>();
-        loop {
-            let timeout = gloo_timers::future::TimeoutFuture::new(interval.as_millis() as u32);
-
-            // ループごとに画像の色を変える
-            let f = match counter % 3 {
-                0 => |i| rgba_to_hexcode(i as u8, 0, 128, 255),
-                1 => |i| rgba_to_hexcode(128, i as u8, 0, 255),
-                _ => |i| rgba_to_hexcode(0, 128, i as u8, 255),
+        let urls = urls.iter().map(String::as_str).collect::<Vec<_>>();
+        let results = preload_all(&glctx, &urls).await;
+        let mut textures = vec![];
+        for ((shader, vao), texture) in shaders.into_iter().zip(results) {
+            let texture = match texture {
+                Ok(texture) => texture,
+                Err(e) => {
+                    error!("failed to preload texture: {e}");
+                    return;
+                }
             };
-
-            // 直接に読むと遅いので一定数の画像を同時に読み出す
-            futures::stream::iter(requests.iter())
-                .for_each_concurrent(par_count, |(i, texture)| async {
-                    let color_front = f(*i);
-                    let src = create_img_src(*i, color_front.as_str());
-                    load_texture(src, texture).await.unwrap();
-                })
-                .await;
-            timeout.await;
-            counter += 1;
+            textures.push(texture.clone());
+            ctx.objects.push(Drawable {
+                shader,
+                vao,
+                texture,
+            });
         }
+        check_memory_usage("after preload");
+
+        // animation loop
+        let mut a = AnimationLoop::new(move |_time| {
+            ctx.draw();
+            Ok(())
+        });
+        a.start();
+        a.forget();
+
+        reload_textures_forever(textures).await;
     });
 
     Ok(())
@@ -145,13 +130,37 @@ impl DrawContext {
     }
 }
 
-// テクスチャを先に確保しておき、後から画像を読み込む
-fn spawn_load_texture(src: impl AsRef<str>, texture: Texture) {
-    let loader = ImageLoader::new(src).unwrap();
-    spawn_local(async move {
-        let img = loader.await.unwrap();
-        texture.update_texture_image_element(&img);
-    });
+// メモリリークの有無を確認するためにテクスチャを定期的に読み出す
+// 実際にforgetではメモリ使用量が増える付けることが確認できた
+async fn reload_textures_forever(textures: Vec<Texture>) {
+    use futures_util::stream::StreamExt;
+    let interval = std::time::Duration::from_secs(5);
+    let mut counter = 0;
+
+    // 画像読み出しの同時実行数
+    let par_count = 8;
+    let requests = textures.iter().enumerate().collect::<Vec<_>>();
+    loop {
+        let timeout = gloo_timers::future::TimeoutFuture::new(interval.as_millis() as u32);
+
+        // ループごとに画像の色を変える
+        let f = match counter % 3 {
+            0 => |i| rgba_to_hexcode(i as u8, 0, 128, 255),
+            1 => |i| rgba_to_hexcode(128, i as u8, 0, 255),
+            _ => |i| rgba_to_hexcode(0, 128, i as u8, 255),
+        };
+
+        // 直接に読むと遅いので一定数の画像を同時に読み出す
+        futures::stream::iter(requests.iter())
+            .for_each_concurrent(par_count, |(i, texture)| async {
+                let color_front = f(*i);
+                let src = create_img_src(*i, color_front.as_str());
+                load_texture(src, texture).await.unwrap();
+            })
+            .await;
+        timeout.await;
+        counter += 1;
+    }
 }
 
 // WebAssembly.Memoryの使用量をログ出力
@@ -1 +1,2 @@
 mod entry_point;
+pub mod loader;
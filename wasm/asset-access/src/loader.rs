@@ -0,0 +1,312 @@
+//! アセットの読み込み補助
+
+use std::{cell::RefCell, future::Future, rc::Rc};
+
+use futures_channel::oneshot;
+use futures_util::{Stream, StreamExt};
+use fxhash::FxHashMap;
+use wasm_bindgen::{prelude::*, JsCast};
+use wasm_bindgen_futures::JsFuture;
+use wasm_utils::error::*;
+use web_sys::HtmlImageElement;
+use webgl2::{
+    context::Context,
+    loader::ImageLoader,
+    texture::{Texture, TextureFilter},
+};
+
+/// キャッシュの状態
+pub enum ImageCacheEntry {
+    /// 読み込み中。読み込みを待っている要求者へ結果を通知するための送信側を保持する
+    Pending(Vec<oneshot::Sender<Result<Texture>>>),
+    /// 読み込み済み
+    Ready(Texture),
+}
+
+// get_or_loadの要求がどの状態で受け付けられたか
+enum ImageRequest<F> {
+    // 読み込み済み
+    Ready(Texture),
+    // 他の要求が読み込み中なので結果を待つ
+    Wait(oneshot::Receiver<Result<Texture>>),
+    // この要求で読み込む
+    Load(Texture, F),
+}
+
+/// URL単位で読み込み中、読み込み済みのテクスチャをまとめる
+///
+/// 内部で参照共有をしているためCloneが可能
+#[derive(Default, Clone)]
+pub struct ImageCache {
+    entries: Rc<RefCell<FxHashMap<String, ImageCacheEntry>>>,
+    // 実際に画像の読み込みを行った回数
+    fetch_count: Rc<RefCell<u32>>,
+}
+
+impl ImageCache {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// テクスチャを取得する。読み込み済みならそのテクスチャを返す
+    ///
+    /// 読み込み中のURLが要求された場合は新たな読み込みは行わず、
+    /// 最初の要求の読み込み結果を待って同じテクスチャかエラーを返す
+    pub fn get_or_load(&self, url: &str, ctx: &Context) -> impl Future<Output = Result<Texture>> {
+        self.get_or_load_with(url, ctx, |url| ImageLoader::new(url))
+    }
+
+    /// 読み込み処理を指定してテクスチャを取得する
+    ///
+    /// loadは読み込みが必要な場合にだけ呼ばれる。読み込み失敗時の振る舞いを確認するために使う
+    pub fn get_or_load_with<L, F>(
+        &self,
+        url: &str,
+        ctx: &Context,
+        load: L,
+    ) -> impl Future<Output = Result<Texture>>
+    where
+        L: FnOnce(&str) -> std::result::Result<F, JsError>,
+        F: Future<Output = std::result::Result<HtmlImageElement, JsError>>,
+    {
+        // 同時に呼ばれた要求を判別するため、Futureを返す前に状態を登録する
+        let request = self.register(url, ctx, load);
+        // 読み込み完了前にFutureが破棄された場合でもエントリを片付けられるようにする
+        let guard = matches!(request, Ok(ImageRequest::Load(..)))
+            .then(|| PendingGuard::new(self.entries.clone(), url));
+        async move {
+            let (texture, loader) = match request? {
+                ImageRequest::Ready(texture) => return Ok(texture),
+                ImageRequest::Wait(rx) => {
+                    return rx
+                        .await
+                        .unwrap_or_else(|_| Err(Error::js("Image loading was cancelled")))
+                }
+                ImageRequest::Load(texture, loader) => (texture, loader),
+            };
+            let result = match loader.await {
+                Ok(img) => {
+                    texture.update_texture_image_element(&img);
+                    Ok(texture)
+                }
+                Err(e) => Err(Error::from(e)),
+            };
+            if let Some(guard) = guard {
+                guard.complete(&result);
+            }
+            result
+        }
+    }
+
+    // キャッシュの状態を確認し、読み込みが必要ならローダーを作成する
+    fn register<L, F>(&self, url: &str, ctx: &Context, load: L) -> Result<ImageRequest<F>>
+    where
+        L: FnOnce(&str) -> std::result::Result<F, JsError>,
+    {
+        let mut entries = self.entries.borrow_mut();
+        match entries.get_mut(url) {
+            Some(ImageCacheEntry::Ready(texture)) => Ok(ImageRequest::Ready(texture.clone())),
+            Some(ImageCacheEntry::Pending(waiters)) => {
+                let (tx, rx) = oneshot::channel();
+                waiters.push(tx);
+                Ok(ImageRequest::Wait(rx))
+            }
+            None => {
+                let texture = ctx.create_blank_texture()?;
+                let loader = load(url)?;
+                *self.fetch_count.borrow_mut() += 1;
+                entries.insert(url.to_string(), ImageCacheEntry::Pending(vec![]));
+                Ok(ImageRequest::Load(texture, loader))
+            }
+        }
+    }
+
+    /// 画像の読み込みを行った回数
+    pub fn fetch_count(&self) -> u32 {
+        *self.fetch_count.borrow()
+    }
+}
+
+// 読み込み中のエントリを、完了時またはFutureの破棄時に片付ける
+struct PendingGuard {
+    entries: Rc<RefCell<FxHashMap<String, ImageCacheEntry>>>,
+    // 片付けが済んだらNoneになる
+    url: Option<String>,
+}
+
+impl PendingGuard {
+    fn new(entries: Rc<RefCell<FxHashMap<String, ImageCacheEntry>>>, url: &str) -> Self {
+        Self {
+            entries,
+            url: Some(url.to_string()),
+        }
+    }
+
+    // 読み込み結果を記録し、待っている要求者へ通知する
+    fn complete(mut self, result: &Result<Texture>) {
+        if let Some(url) = self.url.take() {
+            self.finish(url, result);
+        }
+    }
+
+    fn finish(&self, url: String, result: &Result<Texture>) {
+        let mut entries = self.entries.borrow_mut();
+        // 失敗した場合はエントリを残さず、次の要求で再度読み込めるようにする
+        let pending = entries.remove(&url);
+        if let Ok(texture) = result {
+            entries.insert(url, ImageCacheEntry::Ready(texture.clone()));
+        }
+        drop(entries);
+        if let Some(ImageCacheEntry::Pending(waiters)) = pending {
+            for tx in waiters {
+                let _ = tx.send(result.clone());
+            }
+        }
+    }
+}
+
+impl Drop for PendingGuard {
+    fn drop(&mut self) {
+        if let Some(url) = self.url.take() {
+            self.finish(url, &Err(Error::js("Image loading was cancelled")));
+        }
+    }
+}
+
+/// 複数の画像を同時に読み込み、すべて揃ってからテクスチャを返す
+///
+/// 結果はurlsと同じ順に並ぶのでzipで対応付けられる。
+/// 最初のフレームまでにテクスチャを用意しておきたい場合に使う
+pub async fn preload_all(ctx: &Context, urls: &[&str]) -> Vec<Result<Texture>> {
+    let loads = urls.iter().map(|url| async move {
+        let img = ImageLoader::new(url)?.await?;
+        let texture = ctx.create_texture_image_element(&TextureFilter::default(), &img)?;
+        Ok(texture)
+    });
+    futures::future::join_all(loads).await
+}
+
+/// 読み込みの進捗を通知しながらバイト列を取得する
+///
+/// 進捗は0.0-1.0の割合で通知する。Content-Lengthが無い場合は全体量が不明なので-1.0を通知する
+pub async fn load_with_progress(url: &str, on_progress: impl Fn(f64) + 'static) -> Result<Vec<u8>> {
+    let window = web_sys::window().ok_or(Error::js("Failed to get Window"))?;
+    let response = JsFuture::from(window.fetch_with_str(url))
+        .await
+        .map_err(|_| Error::network(format!("Failed to fetch: {url}")))?
+        .dyn_into::<web_sys::Response>()
+        .map_err(|_| Error::js("Failed to cast to Response"))?;
+    if !response.ok() {
+        return Err(Error::network(format!(
+            "Failed to fetch: {url} status={}",
+            response.status()
+        )));
+    }
+
+    let total = response
+        .headers()
+        .get("Content-Length")
+        .ok()
+        .flatten()
+        .and_then(|v| v.parse::<u64>().ok());
+    let body = response.body().ok_or(Error::js("Response has no body"))?;
+    let reader = body
+        .get_reader()
+        .dyn_into::<web_sys::ReadableStreamDefaultReader>()
+        .map_err(|_| Error::js("Failed to cast to ReadableStreamDefaultReader"))?;
+
+    read_with_progress(chunk_stream(reader), total, on_progress).await
+}
+
+// ReadableStreamの読み出しを1チャンクずつ返すStreamに変換する
+fn chunk_stream(
+    reader: web_sys::ReadableStreamDefaultReader,
+) -> impl Stream<Item = Result<Vec<u8>>> + Unpin {
+    Box::pin(futures_util::stream::unfold(
+        Some(reader),
+        |reader| async move {
+            let reader = reader?;
+            let chunk = match JsFuture::from(reader.read()).await {
+                Ok(chunk) => chunk,
+                Err(_) => return Some((Err(Error::network("Failed to read stream")), None)),
+            };
+            let done = js_sys::Reflect::get(&chunk, &JsValue::from_str("done"))
+                .map(|v| v.is_truthy())
+                .unwrap_or(true);
+            if done {
+                return None;
+            }
+            let value = js_sys::Reflect::get(&chunk, &JsValue::from_str("value"))
+                .map(|v| js_sys::Uint8Array::new(&v).to_vec())
+                .map_err(|_| Error::js("Failed to get chunk value"));
+            Some((value, Some(reader)))
+        },
+    ))
+}
+
+// Content-Lengthを信用して事前に確保するバイト数の上限
+const MAX_PREALLOC: u64 = 16 * 1024 * 1024;
+
+// チャンクを受け取るたびに進捗を通知してバイト列を結合する
+async fn read_with_progress(
+    mut stream: impl Stream<Item = Result<Vec<u8>>> + Unpin,
+    total: Option<u64>,
+    on_progress: impl Fn(f64),
+) -> Result<Vec<u8>> {
+    let mut buf = match total {
+        // 不正に大きなContent-Lengthで確保に失敗しないよう上限を設け、以降は必要に応じて伸ばす
+        Some(total) => Vec::with_capacity(total.min(MAX_PREALLOC) as usize),
+        None => Vec::new(),
+    };
+    while let Some(chunk) = stream.next().await {
+        buf.extend_from_slice(&chunk?);
+        match total {
+            Some(total) if total > 0 => on_progress(buf.len() as f64 / total as f64),
+            _ => on_progress(-1.0),
+        }
+    }
+    Ok(buf)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{cell::RefCell, rc::Rc};
+
+    use super::*;
+
+    #[test]
+    fn test_read_with_progress() {
+        let chunks = (0..3).map(|_| Ok(vec![0u8; 100])).collect::<Vec<_>>();
+        let progress = Rc::new(RefCell::new(vec![]));
+        let p = progress.clone();
+        let buf = futures::executor::block_on(read_with_progress(
+            futures_util::stream::iter(chunks),
+            Some(300),
+            move |v| p.borrow_mut().push(v),
+        ))
+        .unwrap();
+
+        assert_eq!(buf.len(), 300);
+        let progress = progress.borrow();
+        assert_eq!(progress.len(), 3);
+        for (v, expect) in progress.iter().zip([0.33, 0.67, 1.0]) {
+            assert!((v - expect).abs() < 0.01, "{v} != {expect}");
+        }
+    }
+
+    #[test]
+    fn test_read_with_progress_unknown_length() {
+        let chunks = (0..2).map(|_| Ok(vec![0u8; 10])).collect::<Vec<_>>();
+        let progress = Rc::new(RefCell::new(vec![]));
+        let p = progress.clone();
+        let buf = futures::executor::block_on(read_with_progress(
+            futures_util::stream::iter(chunks),
+            None,
+            move |v| p.borrow_mut().push(v),
+        ))
+        .unwrap();
+
+        assert_eq!(buf.len(), 20);
+        assert_eq!(*progress.borrow(), vec![-1.0, -1.0]);
+    }
+}
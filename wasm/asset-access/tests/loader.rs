@@ -0,0 +1,124 @@
+#![cfg(target_arch = "wasm32")]
+
+extern crate wasm_bindgen_test;
+
+use asset_access::loader::{preload_all, ImageCache};
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_test::*;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+// 1x1の透明PNG
+const IMAGE_SRC: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
+// 64x128のグレースケールPNG
+const IMAGE_SRC_64X128: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAEAAAACACAAAAACe6jC2AAAAH0lEQVR42u3BMQEAAADCoPVPbQhfoAAAAAAAAAAAPgMggAABImXeGgAAAABJRU5ErkJggg==";
+
+/// 同じURLを同時に要求しても読み込みは1回だけ
+#[wasm_bindgen_test]
+async fn test_image_cache_dedupe() -> std::result::Result<(), JsValue> {
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+
+    let cache = ImageCache::new();
+    let a = cache.get_or_load(IMAGE_SRC, &ctx);
+    let b = cache.get_or_load(IMAGE_SRC, &ctx);
+    let (a, b) = futures::join!(a, b);
+    a?;
+    b?;
+    assert_eq!(cache.fetch_count(), 1);
+
+    // 読み込み済みなら再度の読み込みは行わない
+    cache.get_or_load(IMAGE_SRC, &ctx).await?;
+    assert_eq!(cache.fetch_count(), 1);
+    Ok(())
+}
+
+/// 読み込みに失敗した場合は、読み込みを待っていた要求にもエラーを返す
+#[wasm_bindgen_test]
+async fn test_image_cache_failure() -> std::result::Result<(), JsValue> {
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+
+    let cache = ImageCache::new();
+    let fail =
+        |_: &str| Ok(async { Err::<web_sys::HtmlImageElement, _>(JsError::new("failed to load")) });
+    let a = cache.get_or_load_with(IMAGE_SRC, &ctx, fail);
+    let b = cache.get_or_load_with(IMAGE_SRC, &ctx, fail);
+    let (a, b) = futures::join!(a, b);
+    assert!(a.is_err());
+    assert!(b.is_err());
+    assert_eq!(cache.fetch_count(), 1);
+
+    // 失敗したURLは次の要求で再度読み込む
+    cache.get_or_load(IMAGE_SRC, &ctx).await?;
+    assert_eq!(cache.fetch_count(), 2);
+    Ok(())
+}
+
+/// 読み込み中のFutureを破棄しても、後続の要求は読み込みを完了できる
+#[wasm_bindgen_test]
+async fn test_image_cache_dropped() -> std::result::Result<(), JsValue> {
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+
+    let cache = ImageCache::new();
+    let never = |_: &str| Ok(futures::future::pending());
+    let a = cache.get_or_load_with(IMAGE_SRC, &ctx, never);
+    let b = cache.get_or_load_with(IMAGE_SRC, &ctx, never);
+    drop(a);
+    // 読み込みを担当していた要求が破棄されたので待っていた要求はエラーになる
+    assert!(b.await.is_err());
+
+    let texture = cache.get_or_load(IMAGE_SRC, &ctx).await?;
+    assert!(ctx.gl().is_texture(Some(texture.texture().as_ref())));
+    assert_eq!(cache.fetch_count(), 2);
+    Ok(())
+}
+
+/// 複数の画像を同時に読み込み、URLの順に結果を返す
+#[wasm_bindgen_test]
+async fn test_preload_all() -> std::result::Result<(), JsValue> {
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+
+    let urls = [IMAGE_SRC, IMAGE_SRC_64X128, IMAGE_SRC];
+    let textures = preload_all(&ctx, &urls).await;
+    assert_eq!(textures.len(), urls.len());
+    for texture in textures {
+        let texture = texture?;
+        assert!(ctx.gl().is_texture(Some(texture.texture().as_ref())));
+    }
+    Ok(())
+}
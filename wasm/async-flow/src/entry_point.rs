@@ -1,4 +1,4 @@
-use std::{rc::Rc, time::Duration};
+use std::{cell::RefCell, rc::Rc, time::Duration};
 
 use futures::StreamExt;
 use wasm_bindgen::prelude::*;
@@ -10,10 +10,11 @@ use wasm_utils::{
     time::{sleep, Interval},
     util::get_performance,
 };
-use web_sys::HtmlCanvasElement;
+use web_sys::{js_sys, HtmlCanvasElement};
 use webgl2::{
     context::{Context, COLOR_BLACK},
     font::{Align, TextShader},
+    shader::pointing::PointingShader,
 };
 
 use crate::{
@@ -28,7 +29,7 @@ pub fn init() -> Result<()> {
 }
 
 #[wasm_bindgen]
-pub fn start(canvas: HtmlCanvasElement) -> std::result::Result<(), JsValue> {
+pub fn start(canvas: HtmlCanvasElement) -> std::result::Result<PointingShaderController, JsValue> {
     canvas.set_width(1024);
     canvas.set_height(768);
 
@@ -42,6 +43,7 @@ pub fn start(canvas: HtmlCanvasElement) -> std::result::Result<(), JsValue> {
     let viewport = ctx.viewport();
     let ts = TextShader::new(&ctx)?;
     let mut ms = MouseShader::new(&ctx)?;
+    let pointing = ms.shader();
     let font = webgl2::font::embed::load(&ctx)?;
     let mut text = font.text_by_capacity(60, Align::left_bottom());
     let mat = viewport.font_mat(0, 128, 16.0);
@@ -159,5 +161,29 @@ pub fn start(canvas: HtmlCanvasElement) -> std::result::Result<(), JsValue> {
 
     info!("start() done");
 
-    Ok(())
+    Ok(PointingShaderController { shader: pointing })
+}
+
+/// startで表示したポインタの見た目をJSから変更する
+#[wasm_bindgen]
+pub struct PointingShaderController {
+    shader: Rc<RefCell<PointingShader>>,
+}
+
+#[wasm_bindgen]
+impl PointingShaderController {
+    /// タッチ位置を`[x0, y0, x1, y1, ...]`の形式で設定する
+    pub fn set_touch_positions(&self, positions: js_sys::Float32Array) {
+        self.shader.borrow_mut().set_touch_positions(positions);
+    }
+
+    /// タッチ位置の円の半径をGL空間の単位で設定する。0以下の場合は表示しない
+    pub fn set_radius(&self, radius: f32) {
+        self.shader.borrow().set_radius(radius);
+    }
+
+    /// ポインタとタッチ位置の円の色を設定する
+    pub fn set_color_rgba(&self, r: f32, g: f32, b: f32, a: f32) {
+        self.shader.borrow().set_color([r, g, b, a]);
+    }
 }
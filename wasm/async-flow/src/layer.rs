@@ -1,3 +1,5 @@
+use std::{cell::RefCell, rc::Rc};
+
 use webgl2::{
     context::Context,
     shader::pointing::{PointingRequest, PointingShader},
@@ -7,14 +9,21 @@ use webgl2::{
 use wasm_utils::{error::*, mouse::MouseEventMessage};
 
 pub struct MouseShader {
-    s: PointingShader,
+    s: Rc<RefCell<PointingShader>>,
 }
 
 impl MouseShader {
     pub fn new(ctx: &Context) -> Result<Self> {
         let mut s = PointingShader::new(ctx)?;
         s.enable(true);
-        Ok(Self { s })
+        Ok(Self {
+            s: Rc::new(RefCell::new(s)),
+        })
+    }
+
+    // JSから見た目を変更するために共有する
+    pub fn shader(&self) -> Rc<RefCell<PointingShader>> {
+        self.s.clone()
     }
 
     // mouseイベントを適用
@@ -24,15 +33,15 @@ impl MouseShader {
                 x: pos.x,
                 y: pos.y,
             });
-            self.s.apply_requests(&[pos]);
+            self.s.borrow_mut().apply_requests(&[pos]);
         }
     }
 
     pub fn update(&mut self, ts: f64) {
-        self.s.update(ts as f32);
+        self.s.borrow_mut().update(ts as f32);
     }
 
     pub fn draw(&self) {
-        self.s.draw();
+        self.s.borrow().draw();
     }
 }
@@ -1,7 +1,6 @@
 //! 1つ目のUI操作グループ
 
 use futures::channel::mpsc::Receiver;
-use wasm_bindgen::prelude::*;
 
 use wasm_utils::{
     error::*,
@@ -41,13 +40,13 @@ impl InputBool for Event {
     fn value(&self) -> Result<bool> {
         match self {
             Event::Toggle(b) => Ok(*b),
-            _ => Err(JsError::new("not bool")),
+            _ => Err(Error::js("not bool")),
         }
     }
     fn with_value(&self, value: bool) -> Result<Self> {
         match self {
             Event::Toggle(_) => Ok(Event::Toggle(value)),
-            _ => Err(JsError::new("not bool")),
+            _ => Err(Error::js("not bool")),
         }
     }
 }
@@ -57,14 +56,14 @@ impl InputNumber<f32> for Event {
         match self {
             Event::Slider1(f) => Ok(*f),
             Event::Slider3(f) => Ok(*f),
-            _ => Err(JsError::new("not f32")),
+            _ => Err(Error::js("not f32")),
         }
     }
     fn with_value(&self, value: f32) -> Result<Self> {
         match self {
             Event::Slider1(_) => Ok(Event::Slider1(value)),
             Event::Slider3(_) => Ok(Event::Slider3(value)),
-            _ => Err(JsError::new("not f32")),
+            _ => Err(Error::js("not f32")),
         }
     }
 }
@@ -73,13 +72,13 @@ impl InputNumber<u16> for Event {
     fn value(&self) -> Result<u16> {
         match self {
             Event::Slider2(u) => Ok(*u),
-            _ => Err(JsError::new("not u16")),
+            _ => Err(Error::js("not u16")),
         }
     }
     fn with_value(&self, value: u16) -> Result<Self> {
         match self {
             Event::Slider2(_) => Ok(Event::Slider2(value)),
-            _ => Err(JsError::new("not u16")),
+            _ => Err(Error::js("not u16")),
         }
     }
 }
@@ -1,6 +1,5 @@
 //! HTTPリクエストをトリガーするUIの実験
 
-use wasm_bindgen::prelude::*;
 use wasm_utils::{
     error::*,
     input::{
@@ -40,7 +39,7 @@ impl InputNumber<u32> for Event {
             Event::Duration(v) => Ok(*v),
             Event::Times(v) => Ok(*v),
             Event::Parallel(v) => Ok(*v),
-            _ => Err(JsError::new("not u32")),
+            _ => Err(Error::js("not u32")),
         }
     }
     fn with_value(&self, value: u32) -> Result<Self> {
@@ -48,7 +47,7 @@ impl InputNumber<u32> for Event {
             Event::Duration(_) => Ok(Event::Duration(value)),
             Event::Times(_) => Ok(Event::Times(value)),
             Event::Parallel(_) => Ok(Event::Parallel(value)),
-            _ => Err(JsError::new("not u32")),
+            _ => Err(Error::js("not u32")),
         }
     }
 }
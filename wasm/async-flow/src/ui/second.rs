@@ -1,7 +1,6 @@
 //! 2つめのUI操作グループ
 
 use futures::channel::mpsc::Receiver;
-use wasm_bindgen::prelude::*;
 use wasm_utils::{
     error::*,
     input::{
@@ -50,15 +49,15 @@ impl InputOption<OptionMode> for Event {
     fn value(&self) -> Result<OptionMode> {
         match self {
             Event::Select1(v) => Ok(*v),
-            Event::Select2(_) => Err(JsError::new("not OptionMode")),
-            _ => Err(JsError::new("not OptionMode")),
+            Event::Select2(_) => Err(Error::js("not OptionMode")),
+            _ => Err(Error::js("not OptionMode")),
         }
     }
     fn with_value(&self, value: OptionMode) -> Result<Self> {
         match self {
             Event::Select1(_) => Ok(Event::Select1(value)),
-            Event::Select2(_) => Err(JsError::new("not OptionMode")),
-            _ => Err(JsError::new("not OptionMode")),
+            Event::Select2(_) => Err(Error::js("not OptionMode")),
+            _ => Err(Error::js("not OptionMode")),
         }
     }
 }
@@ -66,16 +65,16 @@ impl InputOption<OptionMode> for Event {
 impl InputOption<OptionStrength> for Event {
     fn value(&self) -> Result<OptionStrength> {
         match self {
-            Event::Select1(_) => Err(JsError::new("not OptionStrength")),
+            Event::Select1(_) => Err(Error::js("not OptionStrength")),
             Event::Select2(v) => Ok(*v),
-            _ => Err(JsError::new("not OptionStrength")),
+            _ => Err(Error::js("not OptionStrength")),
         }
     }
     fn with_value(&self, value: OptionStrength) -> Result<Self> {
         match self {
-            Event::Select1(_) => Err(JsError::new("not OptionStrength")),
+            Event::Select1(_) => Err(Error::js("not OptionStrength")),
             Event::Select2(_) => Ok(Event::Select2(value)),
-            _ => Err(JsError::new("not OptionStrength")),
+            _ => Err(Error::js("not OptionStrength")),
         }
     }
 }
@@ -84,13 +83,13 @@ impl InputString for Event {
     fn value(&self) -> Result<String> {
         match self {
             Event::Text(v) => Ok(v.clone()),
-            _ => Err(JsError::new("not String")),
+            _ => Err(Error::js("not String")),
         }
     }
     fn with_value(&self, value: String) -> Result<Self> {
         match self {
             Event::Text(_) => Ok(Event::Text(value)),
-            _ => Err(JsError::new("not String")),
+            _ => Err(Error::js("not String")),
         }
     }
 }
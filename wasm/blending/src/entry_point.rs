@@ -7,6 +7,7 @@ use web_sys::{HtmlCanvasElement, WebGlBuffer, WebGlProgram};
 use webgl2::{
     blend::BlendMode, context::gl_clear_color, gl, program::compile_program,
     shader::texture::TextureShader, texture::color_texture, vertex::buffer_data_f32,
+    viewport::Viewport,
 };
 
 use crate::shader::SingleColorShaderGl1;
@@ -61,18 +62,18 @@ impl GlBlendMode {
 pub fn create_blendmode_option(select_element: web_sys::HtmlSelectElement) -> Result<()> {
     for mode in GlBlendMode::VARIABLES {
         let option = web_sys::window()
-            .ok_or(JsError::new("Failed to get window"))?
+            .ok_or(Error::js("Failed to get window"))?
             .document()
-            .ok_or(JsError::new("Failed to get document"))?
+            .ok_or(Error::js("Failed to get document"))?
             .create_element("option")
-            .map_err(|_| JsError::new("Failed to create option element"))?;
+            .map_err(|_| Error::js("Failed to create option element"))?;
         option.set_text_content(Some(&format!("{:?}", mode)));
         option
             .set_attribute("value", &format!("{:?}", mode.into_abi()))
-            .map_err(|_| JsError::new("Failed to set value attribute to option element"))?;
+            .map_err(|_| Error::js("Failed to set value attribute to option element"))?;
         select_element
             .append_child(&option)
-            .map_err(|_| JsError::new("Failed to append option element"))?;
+            .map_err(|_| Error::js("Failed to append option element"))?;
     }
 
     Ok(())
@@ -102,19 +103,17 @@ impl GlContext {
 /// ローカル座標変換行列
 pub struct LocalMat {
     // windowによる形状の変化はローカルの時点で考慮する
-    window_scaling: Vector2<f32>,
+    window_scaling: Matrix3<f32>,
 }
 
 impl LocalMat {
-    fn new(aspect: f32) -> Self {
-        let window_scaling = Vector2::new(1.0 / aspect, 1.0);
+    fn new(vp: &Viewport) -> Self {
+        let window_scaling = vp.normalized_unit_mat();
         Self { window_scaling }
     }
 
     fn with_translation(&self, x: f32, y: f32) -> Matrix3<f32> {
-        Matrix3::identity()
-            .append_translation(&Vector2::new(x, y))
-            .append_nonuniform_scaling(&self.window_scaling)
+        self.window_scaling.prepend_translation(&Vector2::new(x, y))
     }
 }
 
@@ -124,9 +123,9 @@ pub fn start(canvas: HtmlCanvasElement) -> std::result::Result<GlContext, JsValu
     let height = 300;
     canvas.set_width(width);
     canvas.set_height(height);
-    let local_mat = LocalMat::new(width as f32 / height as f32);
 
     let ctx = webgl2::context::Context::new(canvas, BG_COLOR)?;
+    let local_mat = LocalMat::new(&ctx.viewport());
     let gl = ctx.gl().clone();
     let s = SingleColorShaderGl1::new(&ctx)?;
     let u = s.uniform();
@@ -193,9 +192,9 @@ pub fn start_webgl2_texture(canvas: HtmlCanvasElement) -> std::result::Result<Gl
     let height = 300;
     canvas.set_width(width);
     canvas.set_height(height);
-    let local_mat = LocalMat::new(width as f32 / height as f32);
 
     let ctx = webgl2::context::Context::new(canvas, BG_COLOR)?;
+    let local_mat = LocalMat::new(&ctx.viewport());
     let gl = ctx.gl().clone();
     let s = TextureShader::new(&ctx)?;
 
@@ -247,16 +246,20 @@ pub fn start_webgl2_texture(canvas: HtmlCanvasElement) -> std::result::Result<Gl
 pub fn get_context_rs(canvas: HtmlCanvasElement) -> Result<gl> {
     let gl = canvas
         .get_context("experimental-webgl")
-        .map_err(|_| JsError::new("Failed to get_context(webgl)"))?
-        .ok_or(JsError::new("Failed to get WebGlRenderingContext Object"))?
+        .map_err(|_| Error::js("Failed to get_context(webgl)"))?
+        .ok_or(Error::js("Failed to get WebGlRenderingContext Object"))?
         .dyn_into::<gl>()
-        .map_err(|_| JsError::new("Failed to cast to WebGlRenderingContext"))?;
+        .map_err(|_| Error::js("Failed to cast to WebGlRenderingContext"))?;
     Ok(gl)
 }
 
 #[wasm_bindgen]
 pub fn create_program_rs(gl: gl) -> Result<WebGlProgram> {
-    compile_program(&gl, SingleColorShaderGl1::VERT, SingleColorShaderGl1::FRAG)
+    Ok(compile_program(
+        &gl,
+        SingleColorShaderGl1::VERT,
+        SingleColorShaderGl1::FRAG,
+    )?)
 }
 
 #[wasm_bindgen]
@@ -85,6 +85,8 @@ void main(void){
         let gl = &self.program.gl();
         gl.bind_buffer(gl::ARRAY_BUFFER, Some(vbo));
         gl.draw_arrays(gl::TRIANGLE_STRIP, 0, 4);
+        #[cfg(debug_assertions)]
+        self.program.check_errors_assert();
     }
 }
 
@@ -1,7 +1,7 @@
 use crate::unit::Vec3f;
 
 /// 1つのボイドを表す構造体
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Boid {
     pos: Vec3f,
     vel: Vec3f,
@@ -76,6 +76,16 @@ impl Boid {
             + (center - self.pos) * self.param.center_factor
             + avoid * self.param.avoid_factor
             + align * self.param.alignment_factor;
+        self.clamp_speed(v)
+    }
+
+    /// 外部からの力を加える。速度に`force * dt`を足し、速度の制限内に収める
+    pub fn apply_force(&mut self, force: Vec3f, dt: f32) {
+        self.vel = self.clamp_speed(self.vel + force * dt);
+    }
+
+    // 速度の大きさをspeed_limitの範囲に収める
+    fn clamp_speed(&self, v: Vec3f) -> Vec3f {
         let norm = v.norm();
         if norm < self.param.speed_limit.0 {
             v * self.param.speed_limit.0 / norm
@@ -92,7 +102,7 @@ impl Boid {
 }
 
 /// ボイドの制御パラメータ
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct BoidsParameter {
     // 速度の制限min, max
     speed_limit: (f32, f32),
@@ -150,6 +160,8 @@ pub struct Boids {
     pub boids: Vec<Boid>,
     vel_cache: Vec<Vec3f>,
     bounds: CubeBounds,
+    #[cfg(feature = "spatial-hash")]
+    grid: SpatialGrid,
 }
 
 impl Boids {
@@ -159,6 +171,8 @@ impl Boids {
             boids,
             bounds: CubeBounds::default(),
             vel_cache,
+            #[cfg(feature = "spatial-hash")]
+            grid: SpatialGrid::default(),
         }
     }
 
@@ -174,10 +188,71 @@ impl Boids {
         Self::new(boids)
     }
 
-    pub fn update(&mut self) {
-        for (b, v) in self.boids.iter().zip(self.vel_cache.iter_mut()) {
-            *v = b.next_velocity(&self.boids);
+    /// `[-bounds, bounds]`の立方体内にランダムに配置する
+    ///
+    /// 同じseedからは同じ配置が得られる。初速は大きさ0.01未満
+    #[allow(dead_code)]
+    pub fn new_random(n: u32, bounds: f32, seed: u64) -> Self {
+        // 各軸の初速を[-0.005, 0.005]にすれば大きさは0.005*sqrt(3) < 0.01に収まる
+        const VEL_RANGE: f32 = 0.005;
+        let mut rng = Lcg::new(seed);
+        let mut boids = Vec::with_capacity(n as usize);
+        for _ in 0..n {
+            let pos = Vec3f::new(
+                rng.next_signed() * bounds,
+                rng.next_signed() * bounds,
+                rng.next_signed() * bounds,
+            );
+            let vel = Vec3f::new(
+                rng.next_signed() * VEL_RANGE,
+                rng.next_signed() * VEL_RANGE,
+                rng.next_signed() * VEL_RANGE,
+            );
+            boids.push(Boid::new(pos, vel, BoidsParameter::default()));
+        }
+
+        Self::new(boids)
+    }
+
+    // 1個体あたりのシリアライズ要素数。pos, velの順にxyzを並べる
+    const STATE_STRIDE: usize = 6;
+
+    /// 全ボイドの位置と速度を`[pos.x, pos.y, pos.z, vel.x, vel.y, vel.z]`の順に並べて返す
+    pub fn serialize_state(&self) -> js_sys::Float32Array {
+        let mut data = Vec::with_capacity(self.boids.len() * Self::STATE_STRIDE);
+        for b in &self.boids {
+            data.extend_from_slice(b.pos.as_slice());
+            data.extend_from_slice(b.vel.as_slice());
+        }
+        js_sys::Float32Array::from(data.as_slice())
+    }
+
+    /// [`Self::serialize_state`]で保存した位置と速度を復元する
+    pub fn deserialize_state(&mut self, data: &[f32]) -> Result<(), String> {
+        let expected = self.boids.len() * Self::STATE_STRIDE;
+        if data.len() != expected {
+            return Err(format!(
+                "invalid state length: expected {expected}, got {}",
+                data.len()
+            ));
+        }
+        for (b, chunk) in self
+            .boids
+            .iter_mut()
+            .zip(data.chunks_exact(Self::STATE_STRIDE))
+        {
+            b.pos = Vec3f::from_column_slice(&chunk[0..3]);
+            b.vel = Vec3f::from_column_slice(&chunk[3..6]);
         }
+        Ok(())
+    }
+
+    pub fn update(&mut self) {
+        #[cfg(feature = "spatial-hash")]
+        self.update_velocity_grid();
+        #[cfg(not(feature = "spatial-hash"))]
+        self.update_velocity_brute_force();
+
         for (boid, v) in self.boids.iter_mut().zip(self.vel_cache.iter()) {
             boid.vel = *v;
             self.bounds.keep_within(boid);
@@ -187,6 +262,113 @@ impl Boids {
             boid.pos += boid.vel;
         }
     }
+
+    // 全個体同士の距離を調べて次の速度を求める
+    #[cfg_attr(feature = "spatial-hash", allow(dead_code))]
+    fn update_velocity_brute_force(&mut self) {
+        for (b, v) in self.boids.iter().zip(self.vel_cache.iter_mut()) {
+            *v = b.next_velocity(&self.boids);
+        }
+    }
+
+    // 空間ハッシュで近傍の個体だけを調べて次の速度を求める
+    //
+    // 候補を元の並び順で渡すので、総当りと同じ順序で足し合わされ結果は一致する
+    #[cfg(feature = "spatial-hash")]
+    fn update_velocity_grid(&mut self) {
+        let cell_size = self
+            .boids
+            .iter()
+            .map(|b| b.param.visual_range.max(b.param.avoid_distance))
+            .fold(f32::EPSILON, f32::max);
+        self.grid.rebuild(&self.boids, cell_size);
+
+        let mut indices = Vec::new();
+        let mut neighbors = Vec::new();
+        for (b, v) in self.boids.iter().zip(self.vel_cache.iter_mut()) {
+            self.grid.neighbors(b.pos, &mut indices);
+            neighbors.clear();
+            neighbors.extend(indices.iter().map(|&i| self.boids[i]));
+            *v = b.next_velocity(&neighbors);
+        }
+    }
+}
+
+/// 近傍探索用の空間ハッシュ
+///
+/// 可視範囲以上の大きさのセルに分割し、周囲27セルにいる個体だけを候補にする
+#[cfg(feature = "spatial-hash")]
+#[derive(Debug, Default)]
+pub struct SpatialGrid {
+    cell_size: f32,
+    cells: std::collections::HashMap<(i32, i32, i32), Vec<usize>>,
+}
+
+#[cfg(feature = "spatial-hash")]
+impl SpatialGrid {
+    /// 全個体をセルに振り分け直す
+    pub fn rebuild(&mut self, boids: &[Boid], cell_size: f32) {
+        self.cell_size = cell_size;
+        // セルのVecは使い回してアロケーションを減らす
+        for cell in self.cells.values_mut() {
+            cell.clear();
+        }
+        for (i, b) in boids.iter().enumerate() {
+            self.cells.entry(self.key(b.pos)).or_default().push(i);
+        }
+    }
+
+    /// posの近傍にいる可能性がある個体のインデックスを昇順でoutに格納する
+    pub fn neighbors(&self, pos: Vec3f, out: &mut Vec<usize>) {
+        out.clear();
+        let (x, y, z) = self.key(pos);
+        for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    if let Some(cell) = self.cells.get(&(x + dx, y + dy, z + dz)) {
+                        out.extend_from_slice(cell);
+                    }
+                }
+            }
+        }
+        out.sort_unstable();
+    }
+
+    fn key(&self, pos: Vec3f) -> (i32, i32, i32) {
+        (
+            (pos.x / self.cell_size).floor() as i32,
+            (pos.y / self.cell_size).floor() as i32,
+            (pos.z / self.cell_size).floor() as i32,
+        )
+    }
+}
+
+// 初期配置用の64bit線形合同法
+struct Lcg(u64);
+
+impl Lcg {
+    // Knuth MMIXの定数
+    const MUL: u64 = 6364136223846793005;
+    const INC: u64 = 1442695040888963407;
+
+    fn new(seed: u64) -> Self {
+        Self(seed)
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.0 = self.0.wrapping_mul(Self::MUL).wrapping_add(Self::INC);
+        self.0
+    }
+
+    // [0, 1)の一様乱数。下位ビットは周期が短いので上位24bitを使う
+    fn next_f32(&mut self) -> f32 {
+        (self.next_u64() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    // [-1, 1)の一様乱数
+    fn next_signed(&mut self) -> f32 {
+        self.next_f32() * 2.0 - 1.0
+    }
 }
 
 /// キューブ上の空間境界を表す構造体
@@ -229,3 +411,79 @@ impl Default for CubeBounds {
         }
     }
 }
+
+#[cfg(all(test, feature = "spatial-hash"))]
+mod spatial_hash_tests {
+    use super::*;
+
+    #[test]
+    fn test_grid_matches_brute_force() {
+        let mut grid = Boids::new_random(10, 0.3, 7);
+        let mut brute = Boids::new_random(10, 0.3, 7);
+        for _ in 0..10 {
+            grid.update_velocity_grid();
+            brute.update_velocity_brute_force();
+            for (a, b) in grid.vel_cache.iter().zip(brute.vel_cache.iter()) {
+                for i in 0..3 {
+                    assert_eq!(a[i].to_bits(), b[i].to_bits());
+                }
+            }
+            grid.update();
+            brute.update();
+        }
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_serialize_state_roundtrip() {
+        let mut src = Boids::new_circle(7, 0.5, 0.01);
+        src.update();
+        let data = src.serialize_state().to_vec();
+        assert_eq!(data.len(), 7 * 6);
+
+        let mut dst = Boids::new_circle(7, 0.1, 0.0);
+        dst.deserialize_state(&data).unwrap();
+        for (a, b) in src.boids.iter().zip(dst.boids.iter()) {
+            for i in 0..3 {
+                assert_eq!(a.pos[i].to_bits(), b.pos[i].to_bits());
+                assert_eq!(a.vel[i].to_bits(), b.vel[i].to_bits());
+            }
+        }
+
+        assert!(dst.deserialize_state(&data[1..]).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_apply_force() {
+        let vel = Vec3f::new(0.0075, 0.0, 0.0);
+        let mut boid = Boid::new(Vec3f::zeros(), vel, BoidsParameter::default());
+        let force = Vec3f::new(0.0, -0.001, 0.0);
+        let dt = 0.5;
+        boid.apply_force(force, dt);
+        // 速度の制限内なのでそのまま加算される
+        assert_eq!(boid.vel, vel + force * dt);
+
+        // 制限を超える場合は最大速度に収める
+        boid.apply_force(Vec3f::new(1.0, 0.0, 0.0), 1.0);
+        assert!((boid.vel.norm() - 0.01).abs() < 1e-6);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_new_random_deterministic() {
+        let a = Boids::new_random(5, 1.0, 42);
+        let b = Boids::new_random(5, 1.0, 42);
+        assert_eq!(a.boids, b.boids);
+        for boid in &a.boids {
+            assert!(boid.pos.iter().all(|v| (-1.0..=1.0).contains(v)));
+            assert!(boid.vel.norm() < 0.01);
+        }
+    }
+}
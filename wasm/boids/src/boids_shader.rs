@@ -1,3 +1,5 @@
+use std::cell::Cell;
+
 use wasm_bindgen::JsError;
 use wasm_utils::{error::*, info};
 use web_sys::{js_sys, WebGlBuffer, WebGlUniformLocation};
@@ -6,7 +8,7 @@ use webgl2::{
     gl,
     program::{uniform_block_binding, Program},
     vertex::{Vao, VaoDefine},
-    GlPoint3d,
+    GlPoint1d, GlPoint3d,
 };
 
 use crate::{
@@ -25,6 +27,10 @@ pub struct BoidsShaderBuilder {
     pub history_size: f32,
     /// ボイドの履歴を残す数
     pub history_len: usize,
+    /// ボイド履歴を更新毎に薄くする係数
+    pub history_decay: f32,
+    /// ボイドをインスタンス描画する
+    pub instanced: bool,
 }
 
 impl BoidsShaderBuilder {
@@ -35,6 +41,8 @@ impl BoidsShaderBuilder {
             history_color: [0.0, 0.5, 0.4, 1.0],
             history_size: 1.0,
             history_len: 200,
+            history_decay: BoidHistoryShader::DEFAULT_DECAY_FACTOR,
+            instanced: false,
         }
     }
 
@@ -49,7 +57,7 @@ impl BoidsShaderBuilder {
         let camera_ubo = CameraUbo::new(gl, camera, view)?;
         let mut boids_shaders: Vec<BoidShader> = vec![];
         for b in boids {
-            let bi = BoidShader::new(ctx, b, self.boid_size, self.history_len, &camera_ubo)?;
+            let mut bi = BoidShader::new(ctx, b, self.boid_size, self.history_len, &camera_ubo)?;
             bi.use_program();
             bi.set_ambient(self.color);
             bi.draw();
@@ -58,10 +66,20 @@ impl BoidsShaderBuilder {
             hist.set_ambient(self.history_color);
             hist.set_point_size(self.history_size);
             hist.draw();
+            bi.history_mut().set_decay_factor(self.history_decay);
             boids_shaders.push(bi);
         }
+        let instanced = if self.instanced {
+            let s = BoidInstancedShader::new(ctx, boids, self.boid_size, &camera_ubo)?;
+            s.use_program();
+            s.set_ambient(self.color);
+            Some(s)
+        } else {
+            None
+        };
         Ok(BoidsShader {
             boids: boids_shaders,
+            instanced,
             camera: camera_ubo,
         })
     }
@@ -69,11 +87,15 @@ impl BoidsShaderBuilder {
 
 pub struct BoidsShader {
     pub boids: Vec<BoidShader>,
+    /// Someの場合はボイド本体をインスタンス描画する
+    pub instanced: Option<BoidInstancedShader>,
     pub camera: CameraUbo,
 }
 
 pub struct CameraUbo {
     ubo: WebGlBuffer,
+    // 補間のためにGPUへ送ったMVPを保持する
+    mvp: Cell<[f32; 16]>,
 }
 
 impl CameraUbo {
@@ -90,26 +112,48 @@ impl CameraUbo {
             gl.buffer_data_with_array_buffer_view(gl::UNIFORM_BUFFER, &view, gl::DYNAMIC_DRAW);
         }
         gl.bind_buffer(gl::UNIFORM_BUFFER, None);
-        Ok(Self { ubo })
+        Ok(Self {
+            ubo,
+            mvp: Cell::new(mvp),
+        })
     }
 
-    fn gen_matrix(camera: &Camera, view: &ViewMatrix) -> Vec<f32> {
+    fn gen_matrix(camera: &Camera, view: &ViewMatrix) -> [f32; 16] {
         let mvp = camera.perspective().as_matrix() * view.look_at();
         info!("perspective: {:?}", camera.perspective());
         info!("lookat: {:?}", view.look_at());
         let mvp_arrays: [[f32; 4]; 4] = mvp.into();
-        mvp_arrays.iter().flat_map(|a| *a).collect::<Vec<_>>()
+        let mut m = [0.0; 16];
+        for (i, v) in mvp_arrays.iter().flatten().enumerate() {
+            m[i] = *v;
+        }
+        m
     }
 
-    pub fn update_mvp(&self, gl: &gl, camera: &Camera, view: &ViewMatrix) {
-        let mvp = Self::gen_matrix(camera, view);
-
+    fn upload(&self, gl: &gl, mvp: [f32; 16]) {
         gl.bind_buffer(gl::UNIFORM_BUFFER, Some(&self.ubo));
         unsafe {
             let view = js_sys::Float32Array::view(&mvp);
             gl.buffer_sub_data_with_i32_and_array_buffer_view(gl::UNIFORM_BUFFER, 0, &view);
         }
         gl.bind_buffer(gl::UNIFORM_BUFFER, None);
+        self.mvp.set(mvp);
+    }
+
+    pub fn update_mvp(&self, gl: &gl, camera: &Camera, view: &ViewMatrix) {
+        self.upload(gl, Self::gen_matrix(camera, view));
+    }
+
+    /// 現在のMVPから目標のカメラ位置へtの割合で近づける
+    ///
+    /// 毎フレーム呼び出すことで滑らかにカメラを移動できる
+    pub fn lerp_to(&mut self, gl: &gl, target_camera: &Camera, target_view: &ViewMatrix, t: f32) {
+        let mut mvp = self.mvp.get();
+        let target = Self::gen_matrix(target_camera, target_view);
+        for (m, target) in mvp.iter_mut().zip(target.iter()) {
+            *m = *m * (1.0 - t) + target * t;
+        }
+        self.upload(gl, mvp);
     }
 }
 
@@ -136,6 +180,33 @@ impl VaoDefine for BoidVd {
     }
 }
 
+/// 履歴の頂点定義。古い点ほど小さくなる透明度を持つ
+#[derive(Debug, PartialEq)]
+pub enum BoidHistoryVd {
+    Position,
+    Age,
+}
+
+impl VaoDefine for BoidHistoryVd {
+    fn iter() -> std::slice::Iter<'static, Self> {
+        [BoidHistoryVd::Position, BoidHistoryVd::Age].iter()
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            BoidHistoryVd::Position => "position",
+            BoidHistoryVd::Age => "age_alpha",
+        }
+    }
+
+    fn size_of(&self) -> i32 {
+        match self {
+            BoidHistoryVd::Position => 3,
+            BoidHistoryVd::Age => 1,
+        }
+    }
+}
+
 pub struct BoidShader {
     program: Program,
     ambient: WebGlUniformLocation,
@@ -235,6 +306,8 @@ void main() {
         self.program
             .gl()
             .draw_arrays(gl::TRIANGLE_STRIP, 0, self.vertex_len);
+        #[cfg(debug_assertions)]
+        self.program.check_errors_assert();
     }
 
     pub fn history(&self) -> &BoidHistoryShader {
@@ -246,31 +319,183 @@ void main() {
     }
 }
 
+#[derive(Debug, PartialEq)]
+pub enum BoidInstanceVd {
+    Position,
+    InstancePos,
+}
+
+impl VaoDefine for BoidInstanceVd {
+    fn iter() -> std::slice::Iter<'static, Self> {
+        [BoidInstanceVd::Position, BoidInstanceVd::InstancePos].iter()
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            BoidInstanceVd::Position => "position",
+            BoidInstanceVd::InstancePos => "instance_pos",
+        }
+    }
+
+    fn size_of(&self) -> i32 {
+        match self {
+            BoidInstanceVd::Position => 3,
+            BoidInstanceVd::InstancePos => 3,
+        }
+    }
+
+    fn is_instanced(&self) -> bool {
+        matches!(self, BoidInstanceVd::InstancePos)
+    }
+}
+
+/// 全ボイドをインスタンス描画で1回のdraw callで描画するシェーダー
+///
+/// [BoidShader]はボイド毎にdraw callが発生するので、数が多い場合はこちらを使う
+pub struct BoidInstancedShader {
+    program: Program,
+    ambient: WebGlUniformLocation,
+    vao: Vao<BoidInstanceVd>,
+    // 毎フレーム書き換えるインスタンス位置
+    instances: Vec<GlPoint3d>,
+}
+
+impl BoidInstancedShader {
+    const VERT: &'static str = r#"#version 300 es
+layout(location = 0) in vec3 position;
+layout(location = 1) in vec3 instance_pos;
+layout (std140) uniform matrix {
+    mat4 mvp;
+} mat;
+uniform float size;
+
+void main() {
+    gl_Position = mat.mvp * vec4(instance_pos + position * size, 1.0);
+}
+"#;
+
+    const FRAG: &'static str = r#"#version 300 es
+precision mediump float;
+
+uniform vec4 ambient;
+out vec4 fragmentColor;
+
+void main() {
+    fragmentColor = ambient;
+}
+"#;
+
+    const MVP_UBI: u32 = 0;
+
+    // for TRIANGLE STRIP
+    // 大きさ1の正方形をインスタンス位置に移動して描画する
+    const UNIT_RECT: [GlPoint3d; 4] = [
+        GlPoint3d::new(-1.0, -1.0, 0.0),
+        GlPoint3d::new(1.0, -1.0, 0.0),
+        GlPoint3d::new(-1.0, 1.0, 0.0),
+        GlPoint3d::new(1.0, 1.0, 0.0),
+    ];
+
+    pub fn new(ctx: &Context, boids: &[Boid], size: f32, camera: &CameraUbo) -> Result<Self> {
+        let program = ctx.program(Self::VERT, Self::FRAG)?;
+        let gl = ctx.gl();
+        uniform_block_binding(gl, program.program(), "matrix", Self::MVP_UBI);
+        gl.bind_buffer_base(gl::UNIFORM_BUFFER, Self::MVP_UBI, Some(&camera.ubo));
+
+        let ambient = program.uniform_location("ambient")?;
+        program.use_program();
+        gl.uniform1f(Some(&program.uniform_location("size")?), size);
+
+        let mut vao = program.create_vao()?;
+        vao.buffer_data(BoidInstanceVd::Position, &Self::UNIT_RECT, gl::STATIC_DRAW);
+        let instances = boids.iter().map(Self::instance).collect::<Vec<_>>();
+        vao.buffer_data(BoidInstanceVd::InstancePos, &instances, gl::DYNAMIC_DRAW);
+
+        Ok(Self {
+            program,
+            ambient,
+            vao,
+            instances,
+        })
+    }
+
+    fn instance(b: &Boid) -> GlPoint3d {
+        let p = b.pos();
+        GlPoint3d::new(p.x, p.y, p.z)
+    }
+
+    pub fn use_program(&self) {
+        self.program.use_program();
+    }
+
+    pub fn update(&mut self, boids: &[Boid]) {
+        self.instances.clear();
+        self.instances.extend(boids.iter().map(Self::instance));
+        self.vao
+            .buffer_sub_data(BoidInstanceVd::InstancePos, &self.instances, 0);
+    }
+
+    pub fn set_ambient(&self, ambient: [f32; 4]) {
+        self.program.gl().uniform4f(
+            Some(&self.ambient),
+            ambient[0],
+            ambient[1],
+            ambient[2],
+            ambient[3],
+        );
+    }
+
+    pub fn draw(&self) {
+        self.vao.bind();
+        self.program.gl().draw_arrays_instanced(
+            gl::TRIANGLE_STRIP,
+            0,
+            Self::UNIT_RECT.len() as i32,
+            self.instances.len() as i32,
+        );
+        self.vao.unbind();
+        #[cfg(debug_assertions)]
+        self.program.check_errors_assert();
+    }
+}
+
 /// posの記録を行うシェーダー
 pub struct BoidHistoryShader {
     program: Program,
     ambient: WebGlUniformLocation,
     point_size: WebGlUniformLocation,
-    vao: Vao<BoidVd>,
+    vao: Vao<BoidHistoryVd>,
     vertex_len: i32,
 
     // 書き込む頂点位置の調整
     current_index: i32,
     vbo_len: i32,
+
+    // 各点の透明度の重み。実際の透明度は重みにalpha_scaleを掛けた値
+    // 更新毎にalpha_scaleへdecay_factorを掛けることで、全点を送り直さずに古い点を薄くする
+    alphas: Vec<GlPoint1d>,
+    alpha_scale: f32,
+    alpha_scale_location: WebGlUniformLocation,
+    decay_factor: f32,
 }
 
 impl BoidHistoryShader {
     // TODO: mvpはUniformBufferObjectにする
     const VERT: &'static str = r#"#version 300 es
 layout(location = 0) in vec3 position;
+layout(location = 1) in float age_alpha;
 layout (std140) uniform matrix {
     mat4 mvp;
 } mat;
 uniform float pointSize;
+uniform float alphaScale;
+
+out float v_alpha;
 
 void main() {
     gl_Position = mat.mvp * vec4(position, 1.0);
     gl_PointSize = pointSize;
+    v_alpha = age_alpha * alphaScale;
 }
 "#;
 
@@ -278,13 +503,18 @@ void main() {
 precision mediump float;
 
 uniform vec4 ambient;
+in float v_alpha;
 out vec4 fragmentColor;
 
 void main() {
-    fragmentColor = ambient;
+    fragmentColor = vec4(ambient.rgb, ambient.a * v_alpha);
 }
 "#;
 
+    pub const DEFAULT_DECAY_FACTOR: f32 = 0.99;
+    // alpha_scaleがこれより小さくなったら重みを正規化する
+    const MIN_ALPHA_SCALE: f32 = 1e-18;
+
     // uniform blockのn番目のindexを指定
     const MVP_UBI: u32 = 0;
 
@@ -296,6 +526,9 @@ void main() {
 
         let ambient = program.uniform_location("ambient")?;
         let point_size = program.uniform_location("pointSize")?;
+        let alpha_scale_location = program.uniform_location("alphaScale")?;
+        program.use_program();
+        gl.uniform1f(Some(&alpha_scale_location), 1.0);
 
         let mut vao = program.create_vao()?;
 
@@ -303,7 +536,10 @@ void main() {
         let pos = b.pos();
         let pos = GlPoint3d::new(pos.x, pos.y, pos.z);
         let v = vec![pos; vbo_len];
-        vao.buffer_data(BoidVd::Position, &v, gl::DYNAMIC_DRAW);
+        vao.buffer_data(BoidHistoryVd::Position, &v, gl::DYNAMIC_DRAW);
+        // まだ記録していない点は表示しない
+        let alphas = vec![GlPoint1d::new(0.0); vbo_len];
+        vao.buffer_data(BoidHistoryVd::Age, &alphas, gl::DYNAMIC_DRAW);
 
         Ok(Self {
             program,
@@ -313,6 +549,10 @@ void main() {
             vertex_len: v.len() as i32,
             current_index: 0,
             vbo_len: vbo_len as i32,
+            alphas,
+            alpha_scale: 1.0,
+            alpha_scale_location,
+            decay_factor: Self::DEFAULT_DECAY_FACTOR,
         })
     }
 
@@ -328,8 +568,35 @@ void main() {
     pub fn update(&mut self, b: &Boid) {
         let next = self.index(self.current_index + 1);
         let pos = GlPoint3d::new(b.pos().x, b.pos().y, b.pos().z);
-        self.vao.buffer_sub_data(BoidVd::Position, &[pos], next);
+        self.vao
+            .buffer_sub_data(BoidHistoryVd::Position, &[pos], next);
         self.current_index = next;
+
+        self.alpha_scale *= self.decay_factor;
+        let i = next as usize;
+        if self.alpha_scale < Self::MIN_ALPHA_SCALE {
+            // 新しい点の重みが大きくなりすぎる前に正規化して全体を送り直す
+            for a in self.alphas.iter_mut() {
+                a.x *= self.alpha_scale;
+            }
+            self.alpha_scale = 1.0;
+            self.alphas[i] = GlPoint1d::new(1.0);
+            self.vao
+                .buffer_sub_data(BoidHistoryVd::Age, &self.alphas, 0);
+        } else {
+            // 書き込んだ点だけを送る
+            self.alphas[i] = GlPoint1d::new(1.0 / self.alpha_scale);
+            self.vao
+                .buffer_sub_data(BoidHistoryVd::Age, &self.alphas[i..=i], next);
+        }
+        self.program
+            .gl()
+            .uniform1f(Some(&self.alpha_scale_location), self.alpha_scale);
+    }
+
+    /// 1回の更新で古い点の透明度に掛ける係数を設定する。0.0から1.0の範囲に制限する
+    pub fn set_decay_factor(&mut self, decay_factor: f32) {
+        self.decay_factor = decay_factor.clamp(0.0, 1.0);
     }
 
     pub fn set_ambient(&self, ambient: [f32; 4]) {
@@ -351,5 +618,144 @@ void main() {
         self.program
             .gl()
             .draw_arrays(gl::POINTS, 0, self.vertex_len);
+        #[cfg(debug_assertions)]
+        self.program.check_errors_assert();
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_test::*;
+
+    use super::*;
+    use crate::boids::Boids;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    // 個別描画とインスタンス描画の1フレームあたりの時間をconsole.timeで比較する
+    #[wasm_bindgen_test]
+    fn test_instanced_draw_time() {
+        let canvas = web_sys::window()
+            .unwrap()
+            .document()
+            .unwrap()
+            .create_element("canvas")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .unwrap();
+        let ctx = Context::new(canvas, [0.0, 0.0, 0.0, 1.0]).unwrap();
+        let boids = Boids::new_circle(1000, 0.5, 0.01);
+        let camera = Camera::default();
+        let view = ViewMatrix::default();
+        let camera_ubo = CameraUbo::new(ctx.gl(), &camera, &view).unwrap();
+
+        let mut shaders = boids
+            .boids
+            .iter()
+            .map(|b| BoidShader::new(&ctx, b, 0.01, 1, &camera_ubo).unwrap())
+            .collect::<Vec<_>>();
+        web_sys::console::time_with_label("boids: per boid");
+        for (b, s) in boids.boids.iter().zip(shaders.iter_mut()) {
+            s.use_program();
+            s.update(b);
+            s.draw();
+        }
+        ctx.gl().finish();
+        web_sys::console::time_end_with_label("boids: per boid");
+
+        let mut instanced =
+            BoidInstancedShader::new(&ctx, &boids.boids, 0.01, &camera_ubo).unwrap();
+        web_sys::console::time_with_label("boids: instanced");
+        instanced.use_program();
+        instanced.update(&boids.boids);
+        instanced.draw();
+        ctx.gl().finish();
+        web_sys::console::time_end_with_label("boids: instanced");
+
+        assert_eq!(ctx.gl().get_error(), gl::NO_ERROR);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_history_age_alpha() {
+        let canvas = web_sys::window()
+            .unwrap()
+            .document()
+            .unwrap()
+            .create_element("canvas")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .unwrap();
+        let ctx = Context::new(canvas, [0.0, 0.0, 0.0, 1.0]).unwrap();
+        let camera_ubo =
+            CameraUbo::new(ctx.gl(), &Camera::default(), &ViewMatrix::default()).unwrap();
+        let boids = Boids::new_circle(1, 0.5, 0.01);
+        let mut history = BoidHistoryShader::new(&ctx, &boids.boids[0], 128, &camera_ubo).unwrap();
+        history.use_program();
+        history.set_decay_factor(0.99);
+        assert!((0..128).all(|i| alpha(&history, i) == 0.0));
+
+        for _ in 0..100 {
+            history.update(&boids.boids[0]);
+        }
+        // 最初の更新で記録した点はその後の99回の更新で0.99^99 ≈ 0.37まで減衰する
+        let oldest = alpha(&history, 1);
+        assert!((oldest - 0.99f32.powi(99)).abs() < 1e-4, "{oldest}");
+        assert!((alpha(&history, current(&history)) - 1.0).abs() < 1e-4);
+        // 未記録の点は透明のまま
+        assert_eq!(alpha(&history, 0), 0.0);
+        assert_eq!(alpha(&history, 101), 0.0);
+
+        // 減衰が速くても正規化されて最新の点は1.0のまま
+        history.set_decay_factor(0.5);
+        for _ in 0..100 {
+            history.update(&boids.boids[0]);
+        }
+        assert!((alpha(&history, current(&history)) - 1.0).abs() < 1e-4);
+        let previous = alpha(&history, current(&history) - 1);
+        assert!((previous - 0.5).abs() < 1e-4, "{previous}");
+        assert_eq!(ctx.gl().get_error(), gl::NO_ERROR);
+    }
+
+    // 最後に記録した点の位置
+    fn current(history: &BoidHistoryShader) -> usize {
+        history.current_index as usize
+    }
+
+    // index番目の点の実際の透明度
+    fn alpha(history: &BoidHistoryShader, index: usize) -> f32 {
+        history.alphas[index].x * history.alpha_scale
+    }
+
+    #[wasm_bindgen_test]
+    fn test_camera_lerp_to() {
+        let canvas = web_sys::window()
+            .unwrap()
+            .document()
+            .unwrap()
+            .create_element("canvas")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .unwrap();
+        let ctx = Context::new(canvas, [0.0, 0.0, 0.0, 1.0]).unwrap();
+        let camera = Camera::default();
+        let view = ViewMatrix::default();
+        let mut camera_ubo = CameraUbo::new(ctx.gl(), &camera, &view).unwrap();
+        let initial = camera_ubo.mvp.get();
+
+        let target_view = ViewMatrix {
+            eye: crate::unit::Point3f::new(1.0, 2.0, 3.0),
+            ..ViewMatrix::default()
+        };
+        camera_ubo.lerp_to(ctx.gl(), &camera, &target_view, 0.0);
+        assert_eq!(camera_ubo.mvp.get(), initial);
+
+        camera_ubo.lerp_to(ctx.gl(), &camera, &target_view, 1.0);
+        let target = CameraUbo::gen_matrix(&camera, &target_view);
+        assert_eq!(camera_ubo.mvp.get(), target);
+
+        // update_mvpは補間せずに切り替える
+        camera_ubo.update_mvp(ctx.gl(), &camera, &view);
+        assert_eq!(camera_ubo.mvp.get(), initial);
     }
 }
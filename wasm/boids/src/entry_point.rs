@@ -1,3 +1,5 @@
+use std::{cell::RefCell, rc::Rc};
+
 use tokio::sync::mpsc;
 use wasm_bindgen::prelude::*;
 use wasm_utils::info;
@@ -12,6 +14,9 @@ use crate::{
 };
 
 const COLOR_BLACK: [f32; 4] = [0.0, 0.0, 0.0, 1.0];
+// カメラ移動を補間するフレーム数と1フレームあたりの補間率
+const CAMERA_TRANSITION_FRAMES: u32 = 30;
+const CAMERA_LERP_RATE: f32 = 0.2;
 
 #[wasm_bindgen(start)]
 pub fn init() -> Result<(), JsValue> {
@@ -27,6 +32,7 @@ pub struct BoidsInitializeParam {
     pub history_len: usize,
     pub history_size: f32,
     pub history_alpha: f32,
+    pub instanced: bool,
 }
 
 #[wasm_bindgen]
@@ -38,7 +44,43 @@ impl BoidsInitializeParam {
             history_len: 200,
             history_size: 2.0,
             history_alpha: 0.75,
+            instanced: false,
+        }
+    }
+
+    /// `window.location.search`のクエリ文字列から初期化パラメータを作る
+    pub fn from_window_query() -> Result<BoidsInitializeParam, JsValue> {
+        let search = wasm_utils::util::get_window()?.location().search()?;
+        Ok(Self::from_url_query(&search))
+    }
+}
+
+impl BoidsInitializeParam {
+    /// `boid_num=200&boid_size=0.02`形式のクエリ文字列から初期化パラメータを作る
+    ///
+    /// 指定のないキーや値が解釈できないキーは既定値のまま
+    pub fn from_url_query(query: &str) -> Self {
+        let mut p = Self::init();
+        let query = query.strip_prefix('?').unwrap_or(query);
+        for (key, value) in query.split('&').filter_map(|kv| kv.split_once('=')) {
+            match key {
+                "boid_num" => set_parsed(&mut p.boid_num, value),
+                "boid_size" => set_parsed(&mut p.boid_size, value),
+                "history_len" => set_parsed(&mut p.history_len, value),
+                "history_size" => set_parsed(&mut p.history_size, value),
+                "history_alpha" => set_parsed(&mut p.history_alpha, value),
+                "instanced" => set_parsed(&mut p.instanced, value),
+                _ => {}
+            }
         }
+        p
+    }
+}
+
+// 解釈できた場合のみ値を上書きする
+fn set_parsed<T: std::str::FromStr>(dst: &mut T, value: &str) {
+    if let Ok(v) = value.parse() {
+        *dst = v;
     }
 }
 
@@ -51,7 +93,7 @@ pub fn start_boids(
     canvas.set_width(768);
     canvas.set_height(768);
 
-    let mut boids = crate::boids::Boids::new_circle(ip.boid_num, 0.5, 0.01);
+    let boids = crate::boids::Boids::new_circle(ip.boid_num, 0.5, 0.01);
     let mut buillder = BoidsShaderBuilder::new();
 
     let ctx = Context::new(canvas, COLOR_BLACK)?;
@@ -63,14 +105,18 @@ pub fn start_boids(
     buillder.history_size = ip.history_size;
     buillder.history_len = ip.history_len;
     buillder.history_color = [0.0, 0.5, 0.4, ip.history_alpha];
+    buillder.instanced = ip.instanced;
 
     let mut boids_shader = buillder.build(&ctx, &boids.boids, &camera, &view)?;
+    let boids = Rc::new(RefCell::new(boids));
 
     let (tx, mut rx) = mpsc::unbounded_channel();
     let (c_tx, mut c_rx) = mpsc::unbounded_channel();
-    let ctrl = BoidController::new(tx, c_tx);
+    let ctrl = BoidController::new(tx, c_tx, boids.clone());
 
+    let mut camera_transition = 0;
     let mut a = wasm_utils::animation::AnimationLoop::new(move |_| {
+        let mut boids = boids.borrow_mut();
         if let Some(event) = merge_events(&mut rx) {
             for b in boids.boids.iter_mut() {
                 event.apply(b);
@@ -80,14 +126,32 @@ pub fn start_boids(
             view.eye.x = event.x;
             view.eye.y = event.y;
             view.eye.z = event.z;
-            boids_shader.camera.update_mvp(&gl, &camera, &view);
+            camera_transition = CAMERA_TRANSITION_FRAMES;
+        }
+        if camera_transition > 0 {
+            // 最後のフレームで目標の位置に揃える
+            camera_transition -= 1;
+            if camera_transition == 0 {
+                boids_shader.camera.update_mvp(&gl, &camera, &view);
+            } else {
+                boids_shader
+                    .camera
+                    .lerp_to(&gl, &camera, &view, CAMERA_LERP_RATE);
+            }
         }
 
         gl_clear_color(&gl, COLOR_BLACK);
-        for (b, s) in boids.boids.iter().zip(boids_shader.boids.iter_mut()) {
+        if let Some(s) = boids_shader.instanced.as_mut() {
             s.use_program();
-            s.update(b);
+            s.update(&boids.boids);
             s.draw();
+        }
+        for (b, s) in boids.boids.iter().zip(boids_shader.boids.iter_mut()) {
+            if boids_shader.instanced.is_none() {
+                s.use_program();
+                s.update(b);
+                s.draw();
+            }
             let hist = s.history_mut();
             hist.use_program();
             hist.update(b);
@@ -188,18 +252,21 @@ pub struct BoidController {
     last: BoidParamSetter,
     camera_ch: mpsc::UnboundedSender<CameraParamSetter>,
     camera_last: CameraParamSetter,
+    boids: Rc<RefCell<crate::boids::Boids>>,
 }
 
 impl BoidController {
-    pub fn new(
+    pub(crate) fn new(
         tx: mpsc::UnboundedSender<BoidParamSetter>,
         c_tx: mpsc::UnboundedSender<CameraParamSetter>,
+        boids: Rc<RefCell<crate::boids::Boids>>,
     ) -> Self {
         Self {
             param_ch: tx,
             last: BoidParamSetter::default(),
             camera_ch: c_tx,
             camera_last: CameraParamSetter::DEFAULT,
+            boids,
         }
     }
 }
@@ -278,6 +345,27 @@ impl BoidController {
     pub fn reset_camera_position(&mut self) {
         self.camera_ch.send(CameraParamSetter::DEFAULT).unwrap();
     }
+
+    /// 全ボイドに重力や風などの外力を1フレーム分加える
+    pub fn apply_global_force(&self, fx: f32, fy: f32, fz: f32) {
+        let force = crate::unit::Vec3f::new(fx, fy, fz);
+        for b in self.boids.borrow_mut().boids.iter_mut() {
+            b.apply_force(force, 1.0);
+        }
+    }
+
+    /// 全ボイドの位置と速度を保存する
+    pub fn serialize_state(&self) -> js_sys::Float32Array {
+        self.boids.borrow().serialize_state()
+    }
+
+    /// serialize_stateで保存した位置と速度を復元する
+    pub fn deserialize_state(&self, data: &[f32]) -> Result<(), JsValue> {
+        self.boids
+            .borrow_mut()
+            .deserialize_state(data)
+            .map_err(|e| JsValue::from_str(&e))
+    }
 }
 
 #[wasm_bindgen(inspectable)]
@@ -303,3 +391,25 @@ impl Mergeable for CameraParamSetter {
         self.z = other.z;
     }
 }
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_from_url_query() {
+        let p = BoidsInitializeParam::from_url_query("boid_num=50&history_len=100");
+        let d = BoidsInitializeParam::init();
+        assert_eq!(p.boid_num, 50);
+        assert_eq!(p.history_len, 100);
+        assert_eq!(p.boid_size, d.boid_size);
+
+        let p = BoidsInitializeParam::from_url_query("?boid_size=0.02&boid_num=x");
+        assert_eq!(p.boid_size, 0.02);
+        assert_eq!(p.boid_num, d.boid_num);
+    }
+}
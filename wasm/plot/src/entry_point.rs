@@ -1,10 +1,14 @@
 use std::{cell::RefCell, rc::Rc, sync::atomic::AtomicBool, time::Duration};
 
+use nalgebra::{Matrix3, Vector2};
 use rand::Rng;
 use tokio::sync::mpsc::{unbounded_channel, UnboundedReceiver};
 use wasm_bindgen::prelude::*;
 use wasm_utils::{
-    animation::ctrl::{AnimationCtrl, PlayStopButton},
+    animation::{
+        ctrl::{AnimationCtrl, PlayStopButton},
+        AnimationLoop,
+    },
     error::*,
 };
 use web_sys::HtmlCanvasElement;
@@ -15,8 +19,8 @@ use webgl2::{
 };
 
 use crate::{
-    plot::Chart,
-    shader::{PlaneShader, PlotParams},
+    plot::{Chart, SeriesRenderer},
+    shader::{AxisParams, AxisShader, PlaneShader, PlotParams},
 };
 
 #[wasm_bindgen(start)]
@@ -96,6 +100,20 @@ pub fn start(canvas: HtmlCanvasElement) -> std::result::Result<(), JsValue> {
     ts.local_mat(&mat);
     let tv = ts.create_vbo(&text)?;
 
+    // c2の時間窓と値の範囲を示す軸
+    let mut axis = AxisShader::new(
+        &ctx,
+        &font,
+        AxisParams::new(0.0, 10.0, 2.0),
+        AxisParams::new(-5.0, 5.0, 2.5),
+    )?;
+    axis.set_local_mat(viewport.local(0, 128, 512, 128).local_mat());
+    axis.set_plot_mat(
+        Matrix3::identity()
+            .append_translation(&Vector2::new(-5.0, 0.0))
+            .append_nonuniform_scaling(&Vector2::new(1.0 / 5.0, 1.0 / 5.0)),
+    );
+
     // ViewPort確認
     let lp = viewport.local(512, 256, 512, 128);
     let plane = PlaneShader::new(&ctx, [0.5, 0.5, 0.5, 1.0])?;
@@ -120,6 +138,7 @@ pub fn start(canvas: HtmlCanvasElement) -> std::result::Result<(), JsValue> {
         // Scissorを解除して全体に描画
         viewport.scissor(&gl);
         plane.draw();
+        axis.draw();
 
         // Chart.Seriese0の最後のデータを取得してテキストに反映
         if let Some(s) = chart.series(0) {
@@ -147,6 +166,67 @@ pub fn start(canvas: HtmlCanvasElement) -> std::result::Result<(), JsValue> {
     Ok(())
 }
 
+/// 1系列のプロットを開始し、JSからデータを流し込むためのコントローラを返す
+///
+/// params_jsは`PlotParams`のフィールドを持つオブジェクト。省略したフィールドはデフォルト値
+#[wasm_bindgen]
+pub fn start_plot(
+    canvas: HtmlCanvasElement,
+    params_js: JsValue,
+) -> std::result::Result<PlotController, JsValue> {
+    let params: PlotParams = if params_js.is_undefined() || params_js.is_null() {
+        PlotParams::default()
+    } else {
+        serde_wasm_bindgen::from_value(params_js)?
+    };
+    params.validate()?;
+
+    let ctx = Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let gl = ctx.gl().clone();
+    let series = Rc::new(RefCell::new(SeriesRenderer::new(&ctx, params)?));
+
+    let s = series.clone();
+    let mut animation = AnimationLoop::new(move |time| {
+        let current_time = time as f32 / 1000.0;
+        webgl2::context::gl_clear_color(&gl, webgl2::context::COLOR_BLACK);
+        let mut s = s.borrow_mut();
+        s.update_window(current_time);
+        s.draw();
+        Ok(())
+    });
+    animation.start();
+
+    Ok(PlotController { series, animation })
+}
+
+/// start_plotで開始したプロットをJSから操作する
+#[wasm_bindgen]
+pub struct PlotController {
+    series: Rc<RefCell<SeriesRenderer>>,
+    animation: AnimationLoop,
+}
+
+#[wasm_bindgen]
+impl PlotController {
+    /// データを追加する。timestamp_msはrequestAnimationFrameと同じ時間軸のミリ秒
+    pub fn push_value(&self, timestamp_ms: f64, value: f32) {
+        self.series
+            .borrow_mut()
+            .add_data((timestamp_ms / 1000.0) as f32, value);
+    }
+
+    /// Y軸の表示範囲を変更する
+    pub fn set_y_range(&self, min: f32, max: f32) {
+        self.series.borrow_mut().set_y_range(min, max);
+    }
+}
+
+impl Drop for PlotController {
+    fn drop(&mut self) {
+        let _ = self.animation.cancel();
+    }
+}
+
 // 大量のデータを描画するテスト
 fn random_walk_chart(
     ctx: &Context,
@@ -297,3 +377,61 @@ fn check_bounds(hue: f64, saturation: f64, value: f64) {
         panic_bad_params("value", "0.0", "1.0", value)
     }
 }
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use wasm_bindgen_futures::JsFuture;
+    use wasm_bindgen_test::*;
+    use webgl2::{gl, GlPoint2d};
+
+    use crate::shader::DotVertexDefine;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    async fn next_animation_frame() {
+        let promise = js_sys::Promise::new(&mut |resolve, _| {
+            web_sys::window()
+                .unwrap()
+                .request_animation_frame(&resolve)
+                .unwrap();
+        });
+        JsFuture::from(promise).await.unwrap();
+    }
+
+    fn create_canvas() -> HtmlCanvasElement {
+        let doc = web_sys::window().unwrap().document().unwrap();
+        doc.create_element("canvas")
+            .unwrap()
+            .dyn_into::<HtmlCanvasElement>()
+            .unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_push_value() {
+        let controller = start_plot(create_canvas(), JsValue::UNDEFINED).unwrap();
+        controller.push_value(0.0, 1.0);
+        next_animation_frame().await;
+
+        let series = controller.series.borrow();
+        let vao = series.dot_shader().vao();
+        let gl = series.dot_shader().gl();
+        let out = js_sys::Float32Array::new_with_length(2);
+        gl.bind_buffer(gl::ARRAY_BUFFER, Some(vao.vbo(DotVertexDefine::Position)));
+        gl.get_buffer_sub_data_with_i32_and_array_buffer_view(gl::ARRAY_BUFFER, 0, &out);
+        let expected = GlPoint2d::new(0.0, 1.0);
+        assert_eq!(out.to_vec(), [expected.x, expected.y]);
+    }
+
+    // 点数0はpush_value時に0除算になるので開始時に弾く
+    #[wasm_bindgen_test]
+    fn test_start_plot_zero_point_count() {
+        let params = js_sys::Object::new();
+        js_sys::Reflect::set(&params, &"point_count".into(), &0.into()).unwrap();
+        assert!(start_plot(create_canvas(), params.into()).is_err());
+
+        let params = js_sys::Object::new();
+        js_sys::Reflect::set(&params, &"time_window_ms".into(), &0.5.into()).unwrap();
+        assert!(start_plot(create_canvas(), params.into()).is_err());
+    }
+}
@@ -96,6 +96,11 @@ impl SeriesRenderer {
     }
 
     pub fn add_data(&mut self, time: f32, value: f32) {
+        // 直前の点と線で結んで時系列のつながりを表示する
+        if let Some((t, v)) = self.last() {
+            self.dot_shader
+                .add_line_segment(GlPoint2d::new(t, v), GlPoint2d::new(time, value));
+        }
         if self.buffer.time.len() >= self.buffer.max_len {
             self.buffer.time.pop_front();
             self.buffer.value.pop_front();
@@ -122,6 +127,16 @@ impl SeriesRenderer {
         self.dot_shader.uniform().plot_mat(mat);
     }
 
+    /// Y軸の表示範囲を変更する。次のupdate_windowから反映される
+    pub fn set_y_range(&mut self, min: f32, max: f32) {
+        self.params.y_range = (min, max);
+    }
+
+    #[cfg(all(test, target_arch = "wasm32"))]
+    pub fn dot_shader(&self) -> &crate::shader::DotShader {
+        &self.dot_shader
+    }
+
     pub fn draw(&self) {
         self.dot_shader.draw();
         self.plane_shader.draw();
@@ -1,17 +1,21 @@
 use std::{rc::Rc, time::Duration};
 
-use nalgebra::Matrix3;
+use nalgebra::{Matrix3, Vector2, Vector3};
+use serde::{de::Error as _, Deserialize, Deserializer};
 use wasm_utils::error::*;
 use web_sys::WebGlUniformLocation;
 use webgl2::{
     context::Context,
+    font::{Align, Font, TextAlign, TextShader, TextVao, VerticalAlign},
     gl,
     program::Program,
     vertex::{Vao, VaoDefine},
     GlPoint1d, GlPoint2d, GlPoint4d,
 };
 
-#[derive(Clone)]
+/// JSから渡す場合は省略したフィールドにデフォルト値を使う
+#[derive(Clone, Deserialize)]
+#[serde(default)]
 pub struct PlotParams {
     /// 点の色
     pub color: [f32; 4],
@@ -19,7 +23,8 @@ pub struct PlotParams {
     pub point_size: f32,
     /// プロットする点の数
     pub point_count: usize,
-    /// plotのX軸の表示範囲
+    /// plotのX軸の表示範囲。JSからはミリ秒で`time_window_ms`に指定する
+    #[serde(rename = "time_window_ms", deserialize_with = "duration_from_ms")]
     pub time_window: Duration,
     /// plotのY軸の表示範囲
     pub y_range: (f32, f32),
@@ -41,7 +46,18 @@ impl PlotParams {
     }
 
     pub fn point_per_seconds(&self) -> f32 {
-        (self.point_count as f32) / self.time_window.as_secs() as f32
+        (self.point_count as f32) / self.time_window.as_secs_f32()
+    }
+
+    /// プロットできるパラメータか確認する。JSから受け取った値に使う
+    pub fn validate(&self) -> Result<()> {
+        if self.point_count == 0 {
+            return Err(Error::parse("point_count must be greater than 0"));
+        }
+        if self.time_window < Duration::from_millis(1) {
+            return Err(Error::parse("time_window_ms must be at least 1"));
+        }
+        Ok(())
     }
 }
 
@@ -57,6 +73,13 @@ impl Default for PlotParams {
     }
 }
 
+fn duration_from_ms<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> std::result::Result<Duration, D::Error> {
+    let ms = f64::deserialize(deserializer)?;
+    Duration::try_from_secs_f64(ms / 1000.0).map_err(D::Error::custom)
+}
+
 struct PlotState {
     len: usize,
     current_index: usize,
@@ -85,6 +108,9 @@ pub struct DotShader {
     vertex_len: i32,
     default_color: GlPoint4d,
     state: PlotState,
+    // 点同士を結ぶ線分。1線分につき2頂点を持つ
+    line_vao: Vao<DotVertexDefine>,
+    line_state: PlotState,
 }
 
 impl DotShader {
@@ -135,6 +161,24 @@ void main() {
             gl::DYNAMIC_DRAW,
         );
 
+        let mut line_vao = program.create_vao()?;
+        let line_len = param.point_count * 2;
+        line_vao.buffer_data(
+            DotVertexDefine::Position,
+            &vec![GlPoint2d::new(0.0, 0.0); line_len],
+            gl::DYNAMIC_DRAW,
+        );
+        line_vao.buffer_data(
+            DotVertexDefine::Color,
+            &vec![GlPoint4d::new(0.0, 0.0, 0.0, 0.0); line_len],
+            gl::DYNAMIC_DRAW,
+        );
+        line_vao.buffer_data(
+            DotVertexDefine::PointSize,
+            &vec![GlPoint1d::new(param.point_size); line_len],
+            gl::DYNAMIC_DRAW,
+        );
+
         let uniform = DotUniform::new(&program)?;
         uniform.init();
 
@@ -145,6 +189,8 @@ void main() {
             vertex_len: param.point_count as i32,
             default_color: GlPoint4d::from(param.color),
             state: PlotState::new(param.point_count),
+            line_vao,
+            line_state: PlotState::new(param.point_count),
         })
     }
 
@@ -156,6 +202,16 @@ void main() {
         &self.uniform
     }
 
+    #[cfg(all(test, target_arch = "wasm32"))]
+    pub fn gl(&self) -> &Rc<gl> {
+        self.program.gl()
+    }
+
+    #[cfg(all(test, target_arch = "wasm32"))]
+    pub fn vao(&self) -> &Vao<DotVertexDefine> {
+        &self.vao
+    }
+
     pub fn add_data(&mut self, p: GlPoint2d) {
         let i = self.state.next();
         self.vao
@@ -164,12 +220,29 @@ void main() {
             .buffer_sub_data(DotVertexDefine::Color, &[self.default_color], i as i32);
     }
 
+    /// 2点を結ぶ線分を追加する。点と同じ数だけ保持し、古いものから上書きする
+    pub fn add_line_segment(&mut self, p0: GlPoint2d, p1: GlPoint2d) {
+        let i = self.line_state.next() as i32 * 2;
+        self.line_vao
+            .buffer_sub_data(DotVertexDefine::Position, &[p0, p1], i);
+        self.line_vao.buffer_sub_data(
+            DotVertexDefine::Color,
+            &[self.default_color, self.default_color],
+            i,
+        );
+    }
+
     pub fn draw(&self) {
+        let gl = self.program.gl();
+        self.line_vao.bind();
+        gl.draw_arrays(gl::LINES, 0, self.vertex_len * 2);
+        self.line_vao.unbind();
+
         self.vao.bind();
-        self.program
-            .gl()
-            .draw_arrays(gl::POINTS, 0, self.vertex_len);
+        gl.draw_arrays(gl::POINTS, 0, self.vertex_len);
         self.vao.unbind();
+        #[cfg(debug_assertions)]
+        self.program.check_errors_assert();
     }
 }
 
@@ -340,6 +413,8 @@ void main() {
             .gl()
             .draw_arrays(gl::LINE_LOOP, 0, self.vertex_len);
         self.vao.unbind();
+        #[cfg(debug_assertions)]
+        self.program.check_errors_assert();
     }
 }
 
@@ -374,3 +449,279 @@ impl PlaneUniform {
             .uniform_matrix3fv_with_f32_array(Some(&self.local_mat), false, mat.as_slice());
     }
 }
+
+/// 軸の表示範囲と目盛りの設定
+#[derive(Clone)]
+pub struct AxisParams {
+    pub min: f32,
+    pub max: f32,
+    /// 目盛りの間隔
+    pub tick_interval: f32,
+    /// 目盛りの値からラベル文字列を作る
+    pub label_format: fn(f32) -> String,
+}
+
+impl AxisParams {
+    pub fn new(min: f32, max: f32, tick_interval: f32) -> Self {
+        Self {
+            min,
+            max,
+            tick_interval,
+            label_format: |v| format!("{v}"),
+        }
+    }
+
+    /// 目盛りを置く値の一覧。軸同士の交点になるminは除き、maxは含む
+    pub fn ticks(&self) -> Vec<f32> {
+        if self.tick_interval <= 0.0 || self.max <= self.min {
+            return Vec::new();
+        }
+        // 浮動小数点の誤差でmaxの目盛りが落ちないように少し余裕を持たせる
+        let count = ((self.max - self.min) / self.tick_interval + 1e-4).floor() as usize;
+        (1..=count)
+            .map(|i| self.min + i as f32 * self.tick_interval)
+            .collect()
+    }
+
+    // 目盛り線の長さ。軸の範囲に対する割合で決める
+    fn tick_len(&self) -> f32 {
+        (self.max - self.min) * 0.02
+    }
+}
+
+// 目盛りのラベル。位置はプロットと同じデータ空間で持つ
+struct AxisLabel {
+    pos: GlPoint2d,
+    vao: TextVao,
+}
+
+/// X軸とY軸を目盛りとラベル付きで描画するシェーダ
+///
+/// 軸の線はデータ空間で持つので、DotShaderと同じlocal_mat/plot_matを設定して使う
+pub struct AxisShader {
+    program: Program,
+    uniform: DotUniform,
+    vao: Vao<PlaneVertexDefine>,
+    vertex_len: i32,
+    text_shader: TextShader,
+    labels: Vec<AxisLabel>,
+    label_scale: f32,
+    local_mat: Matrix3<f32>,
+    plot_mat: Matrix3<f32>,
+}
+
+impl AxisShader {
+    const VERT: &'static str = r#"#version 300 es
+layout(location = 0) in vec2 position;
+
+uniform mat3 local_mat;
+uniform mat3 plot_mat;
+
+void main() {
+    mat3 m = local_mat * plot_mat;
+    gl_Position = vec4((m * vec3(position.xy, 1.0)).xy, 0.0, 1.0);
+}
+"#;
+
+    const FRAG: &'static str = r#"#version 300 es
+precision mediump float;
+
+uniform vec4 u_color;
+out vec4 fragmentColor;
+
+void main() {
+    fragmentColor = u_color;
+}
+"#;
+
+    pub const DEFAULT_COLOR: [f32; 4] = [0.7, 0.7, 0.7, 1.0];
+    pub const DEFAULT_LABEL_SCALE: f32 = 0.05;
+
+    pub fn new(
+        ctx: &Context,
+        font: &Font,
+        x_params: AxisParams,
+        y_params: AxisParams,
+    ) -> Result<Self> {
+        let program = ctx.program(Self::VERT, Self::FRAG)?;
+
+        let x_ticks = x_params.ticks();
+        let y_ticks = y_params.ticks();
+        let (x_tick_len, y_tick_len) = (y_params.tick_len(), x_params.tick_len());
+
+        // 軸はX軸とY軸の最小値の位置で交差させる
+        let mut vertex = vec![
+            GlPoint2d::new(x_params.min, y_params.min),
+            GlPoint2d::new(x_params.max, y_params.min),
+            GlPoint2d::new(x_params.min, y_params.min),
+            GlPoint2d::new(x_params.min, y_params.max),
+        ];
+        let mut labels = Vec::with_capacity(x_ticks.len() + y_ticks.len());
+        let text_shader = TextShader::new(ctx)?;
+
+        let x_align = Align {
+            text: TextAlign::Center,
+            vertical: VerticalAlign::Top,
+        };
+        for &x in &x_ticks {
+            let end = GlPoint2d::new(x, y_params.min - x_tick_len);
+            vertex.push(GlPoint2d::new(x, y_params.min));
+            vertex.push(end);
+            let text = font.text(&(x_params.label_format)(x), x_align);
+            labels.push(AxisLabel {
+                pos: end,
+                vao: text_shader.create_vbo(&text)?,
+            });
+        }
+
+        let y_align = Align {
+            text: TextAlign::Right,
+            vertical: VerticalAlign::Middle,
+        };
+        for &y in &y_ticks {
+            let end = GlPoint2d::new(x_params.min - y_tick_len, y);
+            vertex.push(GlPoint2d::new(x_params.min, y));
+            vertex.push(end);
+            let text = font.text(&(y_params.label_format)(y), y_align);
+            labels.push(AxisLabel {
+                pos: end,
+                vao: text_shader.create_vbo(&text)?,
+            });
+        }
+
+        // ラベル作成でTextShaderに切り替わっているので戻す
+        program.use_program();
+        let mut vao = program.create_vao()?;
+        vao.buffer_data(PlaneVertexDefine::Position, &vertex, gl::STATIC_DRAW);
+
+        let uniform = DotUniform::new(&program)?;
+        uniform.init();
+        let color = program.uniform_location("u_color")?;
+        program
+            .gl()
+            .uniform4fv_with_f32_array(Some(&color), &Self::DEFAULT_COLOR);
+
+        Ok(Self {
+            program,
+            uniform,
+            vao,
+            vertex_len: vertex.len() as i32,
+            text_shader,
+            labels,
+            label_scale: Self::DEFAULT_LABEL_SCALE,
+            local_mat: Matrix3::identity(),
+            plot_mat: Matrix3::identity(),
+        })
+    }
+
+    /// 表示位置を調整する行列を設定する。DotShaderと同じものを渡す
+    pub fn set_local_mat(&mut self, mat: Matrix3<f32>) {
+        self.program.use_program();
+        self.uniform.local_mat(mat);
+        self.local_mat = mat;
+    }
+
+    /// データ空間を表示範囲に合わせる行列を設定する。DotShaderと同じものを渡す
+    pub fn set_plot_mat(&mut self, mat: Matrix3<f32>) {
+        self.program.use_program();
+        self.uniform.plot_mat(mat);
+        self.plot_mat = mat;
+    }
+
+    pub fn draw(&self) {
+        self.program.use_program();
+        self.vao.bind();
+        self.program.gl().draw_arrays(gl::LINES, 0, self.vertex_len);
+        self.vao.unbind();
+        #[cfg(debug_assertions)]
+        self.program.check_errors_assert();
+
+        // ラベルは文字が歪まないように、位置だけをプロットの行列で変換する
+        let m = self.local_mat * self.plot_mat;
+        for label in &self.labels {
+            let p = m * Vector3::new(label.pos.x, label.pos.y, 1.0);
+            let mat = Matrix3::identity()
+                .append_scaling(self.label_scale)
+                .append_translation(&Vector2::new(p.x, p.y));
+            self.text_shader.local_mat(&mat);
+            self.text_shader.draw(&label.vao);
+        }
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn create_context() -> Context {
+        let canvas = web_sys::window()
+            .unwrap()
+            .document()
+            .unwrap()
+            .create_element("canvas")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .unwrap();
+        Context::new(canvas, webgl2::context::COLOR_BLACK).unwrap()
+    }
+
+    // 線分バッファのPositionを読み出す
+    fn read_line_positions(shader: &DotShader, len: u32) -> Vec<f32> {
+        let gl = shader.gl();
+        let out = js_sys::Float32Array::new_with_length(len * 2);
+        gl.bind_buffer(
+            gl::ARRAY_BUFFER,
+            Some(shader.line_vao.vbo(DotVertexDefine::Position)),
+        );
+        gl.get_buffer_sub_data_with_i32_and_array_buffer_view(gl::ARRAY_BUFFER, 0, &out);
+        out.to_vec()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_add_line_segment() {
+        let ctx = create_context();
+        let mut param = PlotParams::default();
+        param.point_count = 5;
+        let mut shader = DotShader::new(&ctx, &param).unwrap();
+
+        for i in 0..5 {
+            let x = i as f32;
+            shader.add_line_segment(GlPoint2d::new(x, 0.0), GlPoint2d::new(x + 1.0, 1.0));
+        }
+        let expected: Vec<f32> = (0..5)
+            .flat_map(|i| {
+                let x = i as f32;
+                [x, 0.0, x + 1.0, 1.0]
+            })
+            .collect();
+        assert_eq!(read_line_positions(&shader, 10), expected);
+
+        // 6本目は先頭の線分を上書きする
+        shader.add_line_segment(GlPoint2d::new(9.0, 9.0), GlPoint2d::new(8.0, 8.0));
+        let positions = read_line_positions(&shader, 10);
+        assert_eq!(positions[..4], [9.0, 9.0, 8.0, 8.0]);
+        assert_eq!(positions[4..], expected[4..]);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_axis_ticks_and_labels() {
+        let x_params = AxisParams::new(0.0, 10.0, 2.0);
+        assert_eq!(x_params.ticks(), vec![2.0, 4.0, 6.0, 8.0, 10.0]);
+        let y_params = AxisParams::new(-1.0, 1.0, 0.5);
+
+        let ctx = create_context();
+        let font = webgl2::font::embed::load(&ctx).unwrap();
+        let axis = AxisShader::new(&ctx, &font, x_params, y_params).unwrap();
+
+        let x_ticks = axis.labels.iter().filter(|l| l.pos.y < -1.0).count();
+        assert_eq!(x_ticks, 5);
+        assert_eq!(axis.labels.len(), 5 + 4);
+        // 軸2本と目盛り毎に2頂点
+        assert_eq!(axis.vertex_len, 4 + 2 * 9);
+        axis.draw();
+    }
+}
@@ -1,13 +1,17 @@
 use std::{
     borrow::Borrow,
-    cell::RefCell,
+    cell::{Cell, RefCell},
+    collections::VecDeque,
     rc::{Rc, Weak},
     sync::atomic::{AtomicU64, Ordering},
 };
 
 use wasm_bindgen::prelude::*;
 
-use crate::{error::Result, util::get_window};
+use crate::{
+    error::{Error, Result},
+    util::get_window,
+};
 
 // アニメーションフレームのコールバック
 // タイムスタンプが渡され、次のアニメーションフレームのIDを返す
@@ -31,23 +35,159 @@ fn cancel_animation_frame(handle: i32) {
         .expect("Failed to cancel animation frame");
 }
 
+// アニメーション中のエラーを受け取るコールバック
+type ErrorCallback = Box<dyn Fn(JsValue)>;
+
+// アニメーション中のエラーを受け取るハンドラ。Debugを実装するためにラップする
+#[derive(Clone, Default)]
+struct ErrorHandler(Rc<RefCell<Option<ErrorCallback>>>);
+
+impl std::fmt::Debug for ErrorHandler {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ErrorHandler")
+            .field("registered", &RefCell::borrow(&self.0).is_some())
+            .finish()
+    }
+}
+
+// パーセンタイル計算に使うフレーム数
+const FRAME_STATS_CAPACITY: usize = 300;
+
+#[derive(Debug, Default)]
+struct FrameStatsInner {
+    durations: VecDeque<f64>,
+    last_timestamp: Option<f64>,
+}
+
+/// 直近のフレーム時間を保持し、パーセンタイルを計算する
+///
+/// 内部はRcで共有されるため、cloneしたものも同じバッファを参照する
+#[wasm_bindgen]
+#[derive(Debug, Clone, Default)]
+pub struct FrameStats {
+    inner: Rc<RefCell<FrameStatsInner>>,
+}
+
+#[wasm_bindgen]
+impl FrameStats {
+    #[wasm_bindgen(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// フレームのタイムスタンプを記録し、前回との差分をフレーム時間として追加する
+    pub fn record_timestamp(&self, timestamp_msec: f64) {
+        let last = self
+            .inner
+            .borrow_mut()
+            .last_timestamp
+            .replace(timestamp_msec);
+        if let Some(last) = last {
+            self.record(timestamp_msec - last);
+        }
+    }
+
+    /// フレーム時間を追加する。容量を超えた場合は古いものから捨てる
+    pub fn record(&self, duration_msec: f64) {
+        let mut inner = self.inner.borrow_mut();
+        if inner.durations.len() == FRAME_STATS_CAPACITY {
+            inner.durations.pop_front();
+        }
+        inner.durations.push_back(duration_msec);
+    }
+
+    pub fn clear(&self) {
+        *self.inner.borrow_mut() = FrameStatsInner::default();
+    }
+
+    /// 記録済みのフレーム数
+    pub fn len(&self) -> usize {
+        RefCell::borrow(&self.inner).durations.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// フレーム時間のパーセンタイル。pは0.0..=1.0で、記録が無い場合は0を返す
+    pub fn percentile(&self, p: f64) -> f64 {
+        let mut sorted = Vec::from(RefCell::borrow(&self.inner).durations.clone());
+        if sorted.is_empty() {
+            return 0.0;
+        }
+        sorted.sort_by(f64::total_cmp);
+        // 隣接する2点を線形補間する
+        let rank = p.clamp(0.0, 1.0) * (sorted.len() - 1) as f64;
+        let lo = rank.floor() as usize;
+        let hi = rank.ceil() as usize;
+        sorted[lo] + (sorted[hi] - sorted[lo]) * (rank - lo as f64)
+    }
+
+    pub fn p50_ms(&self) -> f64 {
+        self.percentile(0.50)
+    }
+
+    pub fn p95_ms(&self) -> f64 {
+        self.percentile(0.95)
+    }
+
+    pub fn p99_ms(&self) -> f64 {
+        self.percentile(0.99)
+    }
+}
+
 #[derive(Debug, Clone)]
 pub struct AnimationLoop {
     animation_ctx: Rc<RefCell<Option<i32>>>,
     closure_ctx: Rc<Closure<dyn FnMut(f64) -> Result<i32>>>,
+    frame_count: Rc<Cell<u64>>,
+    step_debug: Rc<Cell<bool>>,
+    error_handler: ErrorHandler,
+    last_error: Rc<RefCell<Option<JsValue>>>,
     document_timeline: f64,
     performance_start: f64,
+    performance_stats: Rc<RefCell<Option<FrameStats>>>,
 }
 
 impl AnimationLoop {
     pub fn new(mut callback: impl FnMut(f64) -> Result<()> + 'static) -> Self {
         let a_ctx = Rc::new(RefCell::new(None));
         let a_ctx_clone = a_ctx.clone();
+        let frame_count = Rc::new(Cell::new(0));
+        let frame_count_clone = frame_count.clone();
+        let step_debug = Rc::new(Cell::new(false));
+        let step_debug_clone = step_debug.clone();
+        let error_handler = ErrorHandler::default();
+        let error_handler_clone = error_handler.clone();
+        let last_error = Rc::new(RefCell::new(None));
+        let last_error_clone = last_error.clone();
+        let performance_stats: Rc<RefCell<Option<FrameStats>>> = Rc::new(RefCell::new(None));
+        let performance_stats_clone = performance_stats.clone();
         let closure = Rc::new_cyclic(|this: &Weak<_>| {
             // &Weak -> Weak
             let this = this.clone();
             RequestAnimationFrameClosure::new(move |timestamp_msec| {
-                callback(timestamp_msec)?;
+                // エラー時は次のフレームを予約せずに停止し、ハンドラかコンソールへ通知する
+                if let Err(e) = callback(timestamp_msec) {
+                    let e = JsValue::from(e);
+                    *a_ctx_clone.borrow_mut() = None;
+                    *last_error_clone.borrow_mut() = Some(e.clone());
+                    match RefCell::borrow(&error_handler_clone.0).as_ref() {
+                        Some(handler) => handler(e),
+                        None => web_sys::console::error_1(&e),
+                    }
+                    return Ok(0);
+                }
+                frame_count_clone.set(frame_count_clone.get() + 1);
+                if let Some(stats) = RefCell::borrow(&performance_stats_clone).as_ref() {
+                    stats.record_timestamp(timestamp_msec);
+                }
+
+                // ステップ実行中は次のフレームを予約せずにstep_onceを待つ
+                if step_debug_clone.get() {
+                    *a_ctx_clone.borrow_mut() = None;
+                    return Ok(0);
+                }
 
                 // set next frame
                 let this = this.upgrade().unwrap();
@@ -60,11 +200,91 @@ impl AnimationLoop {
         Self {
             animation_ctx: a_ctx,
             closure_ctx: closure,
+            frame_count,
+            step_debug,
+            error_handler,
+            last_error,
             document_timeline: 0.0,
             performance_start: 0.0,
+            performance_stats,
+        }
+    }
+
+    /// 直近300フレームのフレーム時間を記録する
+    ///
+    /// 記録結果は[`Self::performance_stats`]で取得でき、JSにもそのまま渡せる
+    pub fn with_performance_stats(self) -> Self {
+        self.performance_stats
+            .borrow_mut()
+            .get_or_insert_with(FrameStats::default);
+        self
+    }
+
+    /// フレーム時間の統計。[`Self::with_performance_stats`]を呼んでいない場合はNone
+    ///
+    /// AnimationLoopはクロージャを持つためJSへ公開できない。JSからはここで得た[`FrameStats`]を使う
+    pub fn performance_stats(&self) -> Option<FrameStats> {
+        RefCell::borrow(&self.performance_stats).clone()
+    }
+
+    /// 以下のパーセンタイルは統計を取っていない場合はNone
+    pub fn p50_ms(&self) -> Option<f64> {
+        self.performance_stats().map(|s| s.p50_ms())
+    }
+
+    pub fn p95_ms(&self) -> Option<f64> {
+        self.performance_stats().map(|s| s.p95_ms())
+    }
+
+    pub fn p99_ms(&self) -> Option<f64> {
+        self.performance_stats().map(|s| s.p99_ms())
+    }
+
+    /// 1フレーム描画するごとに停止し、[`Self::step_once`]の呼び出しを待つモードにする
+    ///
+    /// シェーダー開発中にフレーム単位でGPUの状態を確認するためのもの。リリースビルドでは何もしない
+    pub fn with_step_debugger(self) -> Self {
+        #[cfg(debug_assertions)]
+        self.step_debug.set(true);
+        self
+    }
+
+    /// ステップ実行中に1フレームだけ描画して再び停止する
+    ///
+    /// ステップ実行モードでない場合は何もしない
+    pub fn step_once(&mut self) {
+        self.step_debugger().step_once();
+    }
+
+    /// JSからステップ実行を操作するためのハンドル
+    ///
+    /// AnimationLoopはJSへ公開できないため、JSからは[`StepDebugger::step_once`]を呼ぶ
+    pub fn step_debugger(&self) -> StepDebugger {
+        StepDebugger {
+            animation_ctx: self.animation_ctx.clone(),
+            closure_ctx: self.closure_ctx.clone(),
+            step_debug: self.step_debug.clone(),
         }
     }
 
+    /// コールバックがエラーを返した時に呼び出すハンドラを登録する
+    ///
+    /// 登録しない場合はコンソールに出力する。いずれの場合もアニメーションは停止する
+    pub fn on_error(self, handler: impl Fn(JsValue) + 'static) -> Self {
+        *self.error_handler.0.borrow_mut() = Some(Box::new(handler));
+        self
+    }
+
+    /// 最後に発生したエラー
+    pub fn last_error(&self) -> Option<JsValue> {
+        RefCell::borrow(&self.last_error).clone()
+    }
+
+    /// これまでに描画したフレーム数
+    pub fn frame_count(&self) -> u64 {
+        self.frame_count.get()
+    }
+
     pub fn start(&mut self) {
         self.document_timeline = web_sys::window()
             .expect("Failed to get window")
@@ -81,12 +301,32 @@ impl AnimationLoop {
         *self.animation_ctx.borrow_mut() = Some(request_animation_frame(self.closure_ctx.borrow()));
     }
 
+    /// 次のフレームの予約を取り消して停止する
+    pub fn pause(&self) {
+        if let Some(handle) = self.animation_ctx.borrow_mut().take() {
+            cancel_animation_frame(handle);
+        }
+    }
+
+    /// 停止中であれば次のフレームを予約して再開する
+    pub fn resume(&self) {
+        let mut ctx = self.animation_ctx.borrow_mut();
+        if ctx.is_none() {
+            *ctx = Some(request_animation_frame(self.closure_ctx.borrow()));
+        }
+    }
+
+    /// 次のフレームが予約されているか
+    pub fn is_running(&self) -> bool {
+        RefCell::borrow(&self.animation_ctx).is_some()
+    }
+
     pub fn cancel(&self) -> Result<()> {
         if let Some(handle) = self.animation_ctx.borrow_mut().take() {
             cancel_animation_frame(handle);
             Ok(())
         } else {
-            Err(JsError::new("Animation Frame is not started"))
+            Err(Error::js("Animation Frame is not started"))
         }
     }
 
@@ -96,19 +336,48 @@ impl AnimationLoop {
     }
 }
 
+/// [`AnimationLoop`]のステップ実行をJSから操作するハンドル
+#[wasm_bindgen]
+#[derive(Debug, Clone)]
+pub struct StepDebugger {
+    animation_ctx: Rc<RefCell<Option<i32>>>,
+    closure_ctx: Rc<Closure<dyn FnMut(f64) -> Result<i32>>>,
+    step_debug: Rc<Cell<bool>>,
+}
+
+#[wasm_bindgen]
+impl StepDebugger {
+    /// ステップ実行中に1フレームだけ描画して再び停止する
+    ///
+    /// ステップ実行モードでない場合や、前のステップの描画待ちの場合は何もしない
+    pub fn step_once(&self) {
+        if !self.step_debug.get() || RefCell::borrow(&self.animation_ctx).is_some() {
+            return;
+        }
+        *self.animation_ctx.borrow_mut() = Some(request_animation_frame(self.closure_ctx.borrow()));
+    }
+
+    /// ステップ実行モードか。リリースビルドでは常にfalse
+    pub fn is_enabled(&self) -> bool {
+        self.step_debug.get()
+    }
+}
+
 #[cfg(feature = "input")]
 pub mod ctrl {
     use futures_channel::mpsc;
     use futures_util::StreamExt;
     use std::{
-        cell::RefCell,
+        cell::{Cell, RefCell},
         rc::Rc,
         sync::atomic::{AtomicBool, Ordering},
     };
+    use wasm_bindgen::prelude::*;
 
     use crate::{
         error::*,
         input::{button::SubmitBtn, InputIdent},
+        util::{add_event_listener, get_window, remove_event_listener},
     };
 
     use super::AnimationLoop;
@@ -131,6 +400,7 @@ pub mod ctrl {
         btn: SubmitBtn<AnimationCtrl>,
         animation_loop: AnimationLoop,
         playing: Rc<RefCell<AtomicBool>>,
+        visibility_pause: Option<PageVisibilityPause>,
     }
 
     impl PlayStopButton {
@@ -141,6 +411,7 @@ pub mod ctrl {
                 btn,
                 animation_loop,
                 playing,
+                visibility_pause: None,
             };
             s.set_text();
             Ok(s)
@@ -152,6 +423,14 @@ pub mod ctrl {
                 .set_text(Some(if current { "Stop" } else { "Play" }));
         }
 
+        /// タブが非表示の間はアニメーションを停止する
+        ///
+        /// 非表示になる前に停止していた場合は、再表示されても再開しない
+        pub fn with_page_visibility_pause(mut self) -> Result<Self> {
+            self.visibility_pause = Some(PageVisibilityPause::new(self.animation_loop.clone())?);
+            Ok(self)
+        }
+
         pub fn set_play(&mut self, play: bool) {
             let current = self.playing.borrow().swap(play, Ordering::Relaxed);
             if current != play {
@@ -176,6 +455,90 @@ pub mod ctrl {
             Ok(())
         }
     }
+
+    /// タブが非表示の間はAnimationLoopを停止する`visibilitychange`リスナー
+    ///
+    /// 破棄するとリスナーを解除する
+    pub struct PageVisibilityPause {
+        document: web_sys::Document,
+        listener: Closure<dyn FnMut()>,
+    }
+
+    impl PageVisibilityPause {
+        pub fn new(animation_loop: AnimationLoop) -> Result<Self> {
+            let document = get_window()?
+                .document()
+                .ok_or(Error::js("Failed to get document"))?;
+            // タブが非表示になったことで停止している
+            let hidden_paused = Cell::new(false);
+            let doc = document.clone();
+            let listener = Closure::<dyn FnMut()>::new(move || {
+                on_visibility_change(&animation_loop, &hidden_paused, doc.hidden());
+            });
+            add_event_listener(&document, "visibilitychange", listener.as_ref())?;
+            Ok(Self { document, listener })
+        }
+    }
+
+    impl Drop for PageVisibilityPause {
+        fn drop(&mut self) {
+            if let Err(e) =
+                remove_event_listener(&self.document, "visibilitychange", self.listener.as_ref())
+            {
+                web_sys::console::warn_1(&e.into());
+            }
+        }
+    }
+
+    fn on_visibility_change(
+        animation_loop: &AnimationLoop,
+        hidden_paused: &Cell<bool>,
+        hidden: bool,
+    ) {
+        if hidden {
+            if animation_loop.is_running() {
+                animation_loop.pause();
+                hidden_paused.set(true);
+            }
+        } else if hidden_paused.replace(false) {
+            animation_loop.resume();
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use std::cell::Cell;
+
+        use wasm_bindgen_test::*;
+
+        use super::{on_visibility_change, PageVisibilityPause};
+        use crate::animation::AnimationLoop;
+
+        wasm_bindgen_test_configure!(run_in_browser);
+
+        #[wasm_bindgen_test]
+        fn test_page_visibility_pause() {
+            let mut a = AnimationLoop::new(|_| Ok(()));
+            let pause = PageVisibilityPause::new(a.clone()).unwrap();
+            a.start();
+            assert!(a.is_running());
+
+            let hidden_paused = Cell::new(false);
+            on_visibility_change(&a, &hidden_paused, true);
+            assert!(!a.is_running());
+            on_visibility_change(&a, &hidden_paused, false);
+            assert!(a.is_running());
+
+            // 表示前に止めていたものは再表示で再開しない
+            a.pause();
+            on_visibility_change(&a, &hidden_paused, true);
+            on_visibility_change(&a, &hidden_paused, false);
+            assert!(!a.is_running());
+
+            // 破棄時にリスナーを解除する
+            drop(pause);
+        }
+    }
 }
 
 /// 非同期の中でrequest animation frameの周期を待つTicker
@@ -264,5 +627,50 @@ impl Drop for AnimationInstant {
 fn request_animation_frame_inner(closure: &Closure<dyn FnMut(f64)>) -> Result<i32> {
     get_window()?
         .request_animation_frame(closure.as_ref().unchecked_ref())
-        .map_err(|_| JsError::new("Failed request animation frame"))
+        .map_err(|_| Error::js("Failed request animation frame"))
+}
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::{AnimationLoop, FrameStats, FRAME_STATS_CAPACITY};
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_frame_stats_percentile() {
+        let stats = FrameStats::new();
+        assert_eq!(stats.p50_ms(), 0.0);
+
+        // 逆順に入れてもソートされる
+        for i in (1..=30).rev() {
+            stats.record(i as f64 * 10.0);
+        }
+        assert!((stats.p50_ms() - 150.0).abs() <= 5.0);
+        assert!((stats.p99_ms() - 297.0).abs() <= 1.0);
+        assert!(stats.p95_ms() >= stats.p50_ms());
+
+        // 容量を超えると古いものから捨てる
+        for _ in 0..FRAME_STATS_CAPACITY {
+            stats.record(16.0);
+        }
+        assert_eq!(stats.len(), FRAME_STATS_CAPACITY);
+        assert_eq!(stats.p99_ms(), 16.0);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_performance_stats() {
+        let a = AnimationLoop::new(|_| Ok(()));
+        assert!(a.performance_stats().is_none());
+        assert_eq!(a.p50_ms(), None);
+
+        let a = a.with_performance_stats();
+        let stats = a.performance_stats().unwrap();
+        stats.record_timestamp(0.0);
+        stats.record_timestamp(16.0);
+        stats.record_timestamp(48.0);
+        assert_eq!(stats.len(), 2);
+        assert_eq!(a.p99_ms().map(|v| v.round()), Some(32.0));
+    }
 }
@@ -1,3 +1,114 @@
-use wasm_bindgen::JsError;
+use wasm_bindgen::{JsCast, JsError, JsValue};
+use web_sys::js_sys;
 
-pub type Result<T> = std::result::Result<T, JsError>;
+pub type Result<T> = std::result::Result<T, Error>;
+
+/// wasm-utilsを使うアプリケーション共通のエラー
+///
+/// JSへ返す際は種類毎にnameを設定したErrorオブジェクトに変換する
+#[derive(Debug, Clone)]
+pub enum Error {
+    /// JS側で発生したエラー
+    Js(JsError),
+    /// WebGLの操作に関するエラー
+    Gl(String),
+    /// 通信に関するエラー
+    Network(String),
+    /// データの解釈に関するエラー
+    Parse(String),
+}
+
+impl Error {
+    /// メッセージからJSのErrorを作る
+    pub fn js(msg: &str) -> Self {
+        Self::Js(JsError::new(msg))
+    }
+
+    /// WebGLのエラーを作る
+    pub fn gl(msg: impl Into<String>) -> Self {
+        Self::Gl(msg.into())
+    }
+
+    /// 通信のエラーを作る
+    pub fn network(msg: impl Into<String>) -> Self {
+        Self::Network(msg.into())
+    }
+
+    /// データ解釈のエラーを作る
+    pub fn parse(msg: impl Into<String>) -> Self {
+        Self::Parse(msg.into())
+    }
+
+    // JSのErrorオブジェクトのnameに使う
+    fn name(&self) -> &'static str {
+        match self {
+            Error::Js(_) => "Error",
+            Error::Gl(_) => "GlError",
+            Error::Network(_) => "NetworkError",
+            Error::Parse(_) => "ParseError",
+        }
+    }
+}
+
+impl std::fmt::Display for Error {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Error::Js(e) => {
+                let value = JsValue::from(e.clone());
+                match value.dyn_ref::<js_sys::Error>() {
+                    Some(e) => write!(f, "{}", String::from(e.message())),
+                    None => write!(f, "{:?}", value),
+                }
+            }
+            Error::Gl(msg) | Error::Network(msg) | Error::Parse(msg) => {
+                write!(f, "{}: {}", self.name(), msg)
+            }
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+impl From<JsError> for Error {
+    fn from(e: JsError) -> Self {
+        Self::Js(e)
+    }
+}
+
+#[cfg(feature = "net")]
+impl From<gloo_net::Error> for Error {
+    fn from(e: gloo_net::Error) -> Self {
+        Self::Network(e.to_string())
+    }
+}
+
+impl From<Error> for JsValue {
+    fn from(e: Error) -> Self {
+        match e {
+            Error::Js(e) => e.into(),
+            Error::Gl(ref msg) | Error::Network(ref msg) | Error::Parse(ref msg) => {
+                let err = js_sys::Error::new(msg);
+                err.set_name(e.name());
+                err.into()
+            }
+        }
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use wasm_bindgen_test::*;
+    use web_sys::js_sys::Reflect;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_error_into_js_value() {
+        let value = JsValue::from(Error::Gl("foo".into()));
+        let get = |key: &str| Reflect::get(&value, &key.into()).unwrap();
+        assert!(get("message").as_string().unwrap().contains("foo"));
+        assert_eq!(get("name").as_string().unwrap(), "GlError");
+    }
+}
@@ -7,7 +7,7 @@ use std::{
 use futures_channel::mpsc;
 use wasm_bindgen::prelude::*;
 
-use super::{util::*, InputBool, InputIdent};
+use super::{util::*, InputBool, InputIdent, InputOption, SelectOption};
 use crate::error::*;
 
 /// Submitボタンの実装
@@ -31,7 +31,7 @@ where
     pub fn start(&self, mut tx: mpsc::Sender<I>) -> Result<()> {
         // check closure
         if contains(self.ident.id()) {
-            return Err(JsError::new(&format!(
+            return Err(Error::js(&format!(
                 "Closure already exists: {}",
                 self.ident.id()
             )));
@@ -103,7 +103,7 @@ where
     pub fn start(&self, mut tx: mpsc::Sender<I>) -> Result<()> {
         // check closure
         if contains(self.ident.id()) {
-            return Err(JsError::new(&format!(
+            return Err(Error::js(&format!(
                 "Closure already exists: {}",
                 self.ident.id()
             )));
@@ -135,3 +135,212 @@ where
         remove_closure(self.ident.id());
     }
 }
+
+/// ラジオボタンの実装
+///
+/// `name`属性がidと一致するinput要素をまとめて1つの選択肢として扱う。
+/// 要素の並び順に`O::iter()`の値を割り当てる
+pub struct RadioGroup<I, O>
+where
+    I: InputIdent,
+    O: SelectOption,
+{
+    ident: I,
+    elements: Vec<web_sys::HtmlInputElement>,
+    state: Rc<RefCell<O>>,
+}
+
+impl<I, O> RadioGroup<I, O>
+where
+    I: InputIdent + InputOption<O>,
+    O: SelectOption,
+{
+    pub fn new(ident: I) -> Result<Self> {
+        let id = ident.id();
+        let nodes = web_sys::window()
+            .ok_or(Error::js("Failed to get window"))?
+            .document()
+            .ok_or(Error::js("Failed to get document"))?
+            .query_selector_all(&format!("input[name='{id}']"))
+            .map_err(|e| Error::js(&format!("failed to querySelectorAll {e:?}")))?;
+        let elements = (0..nodes.length())
+            .filter_map(|i| nodes.item(i))
+            .map(|n| {
+                n.dyn_into::<web_sys::HtmlInputElement>()
+                    .map_err(|_| Error::js(&format!("Failed to convert Element: {id}")))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if elements.len() != O::iter().len() {
+            return Err(Error::js(&format!(
+                "{id}: radio count {} does not match options {}",
+                elements.len(),
+                O::iter().len()
+            )));
+        }
+        let state = Rc::new(RefCell::new(ident.value()?));
+
+        let s = Self {
+            ident,
+            elements,
+            state,
+        };
+        s.init();
+
+        Ok(s)
+    }
+
+    // 各要素に選択肢の値を設定し、現在の状態を選択する
+    fn init(&self) {
+        for (element, v) in self.elements.iter().zip(O::iter()) {
+            element.set_value(v.value());
+        }
+        self.sync_checked();
+    }
+
+    fn sync_checked(&self) {
+        let value = self.state.borrow();
+        for element in &self.elements {
+            element.set_checked(element.value() == value.value());
+        }
+    }
+
+    /// イベントリスナーを登録する
+    pub fn start(&self, mut tx: mpsc::Sender<I>) -> Result<()> {
+        // check closure
+        if contains(self.ident.id()) {
+            return Err(Error::js(&format!(
+                "Closure already exists: {}",
+                self.ident.id()
+            )));
+        }
+        let elements = self.elements.clone();
+        let state = self.state.clone();
+        let ident = self.ident.to_owned();
+        let closure = Closure::wrap(Box::new(move || {
+            let Some(checked) = elements.iter().find(|e| e.checked()) else {
+                return;
+            };
+            // 古い値が残っていても停止しないように警告だけ出す
+            let Some(value) = O::try_from_str(&checked.value()) else {
+                web_sys::console::warn_1(&format!("unknown option: {}", checked.value()).into());
+                return;
+            };
+            *state.borrow_mut() = value;
+            // send message with sync
+            tx.try_send(ident.with_value(value).unwrap()).unwrap();
+        }) as Box<dyn FnMut()>);
+        // どの要素が選択されても同じクロージャで選択中の値を読む
+        for element in &self.elements {
+            element.set_oninput(Some(closure.as_ref().unchecked_ref()));
+        }
+        insert(self.ident.id(), closure);
+        Ok(())
+    }
+
+    /// 値を設定してエレメントに反映する
+    pub fn apply(&self, value: O) {
+        *self.state.borrow_mut() = value;
+        self.sync_checked();
+    }
+
+    pub fn remove(&self) {
+        for element in &self.elements {
+            element.set_oninput(None);
+        }
+        remove_closure(self.ident.id());
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+    use crate::util;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Mode {
+        A,
+        B,
+        C,
+    }
+
+    impl SelectOption for Mode {
+        fn iter() -> &'static [Self] {
+            &[Mode::A, Mode::B, Mode::C]
+        }
+        fn value(&self) -> &str {
+            match self {
+                Mode::A => "a",
+                Mode::B => "b",
+                Mode::C => "c",
+            }
+        }
+        fn text(&self) -> &str {
+            self.value()
+        }
+        fn from_str(value: &str) -> Self {
+            match value {
+                "b" => Mode::B,
+                "c" => Mode::C,
+                _ => Mode::A,
+            }
+        }
+    }
+
+    #[derive(Debug, Clone)]
+    struct Ident(Mode);
+
+    impl InputIdent for Ident {
+        fn id(&self) -> &'static str {
+            "test-radio-group"
+        }
+    }
+
+    impl InputOption<Mode> for Ident {
+        fn value(&self) -> Result<Mode> {
+            Ok(self.0)
+        }
+        fn with_value(&self, value: Mode) -> Result<Self> {
+            Ok(Ident(value))
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_radio_group_select() {
+        let body = util::get_body().unwrap();
+        let radios = (0..3)
+            .map(|_| {
+                let input = util::create_element::<web_sys::HtmlInputElement>("input").unwrap();
+                input.set_type("radio");
+                input.set_name("test-radio-group");
+                body.append_child(&input).unwrap();
+                input
+            })
+            .collect::<Vec<_>>();
+
+        let group = RadioGroup::new(Ident(Mode::A)).unwrap();
+        let values = radios.iter().map(|r| r.value()).collect::<Vec<_>>();
+        assert_eq!(values, ["a", "b", "c"]);
+        assert!(radios[0].checked());
+
+        let (tx, mut rx) = mpsc::channel(1);
+        group.start(tx).unwrap();
+        radios[1].click();
+        let Ident(mode) = rx.try_recv().unwrap();
+        assert_eq!(mode, Mode::B);
+        // 1回のクリックで通知は1回だけ
+        assert!(rx.try_recv().is_err());
+        assert!(!radios[0].checked());
+
+        group.apply(Mode::C);
+        assert!(radios[2].checked());
+
+        group.remove();
+        for r in radios {
+            r.remove();
+        }
+    }
+}
@@ -37,6 +37,11 @@ pub trait SelectOption: Copy + Sized + 'static {
     fn value(&self) -> &str;
     fn text(&self) -> &str;
     fn from_str(value: &str) -> Self;
+
+    /// 文字列から値を得る。選択肢に無い場合はNoneを返す
+    fn try_from_str(value: &str) -> Option<Self> {
+        Self::iter().iter().find(|v| v.value() == value).copied()
+    }
 }
 
 pub trait InputString: Sized {
@@ -46,7 +46,7 @@ where
             option.set_text(v.text());
             self.element
                 .append_child(option.as_ref())
-                .map_err(|e| JsError::new(&format!("failed to append_child {e:?}")))?;
+                .map_err(|e| Error::js(&format!("failed to append_child {e:?}")))?;
         }
         self.element.set_value(self.state.borrow().value());
         Ok(())
@@ -56,7 +56,7 @@ where
     pub fn start(&self, mut tx: mpsc::Sender<I>) -> Result<()> {
         // check closure
         if contains(self.ident.id()) {
-            return Err(JsError::new(&format!(
+            return Err(Error::js(&format!(
                 "Closure already exists: {}",
                 self.ident.id()
             )));
@@ -65,7 +65,11 @@ where
         let state = self.state.clone();
         let ident = self.ident.to_owned();
         let closure = Closure::wrap(Box::new(move || {
-            let value = O::from_str(&ele.value());
+            // 古い値が残っていても停止しないように警告だけ出す
+            let Some(value) = O::try_from_str(&ele.value()) else {
+                web_sys::console::warn_1(&format!("unknown option: {}", ele.value()).into());
+                return;
+            };
             *state.borrow_mut() = value;
             // send message with sync
             tx.try_send(ident.with_value(value).unwrap()).unwrap();
@@ -76,12 +80,135 @@ where
         Ok(())
     }
 
+    /// 値を設定してエレメントに反映する
+    ///
+    /// 選択肢に存在しない値の場合は警告を出して何もしない
     pub fn apply(&self, value: O) {
+        if !O::iter().iter().any(|v| v.value() == value.value()) {
+            web_sys::console::warn_1(
+                &format!("{}: unknown option {}", self.ident.id(), value.value()).into(),
+            );
+            return;
+        }
         *self.state.borrow_mut() = value;
         self.element.set_value(value.value());
     }
 
+    /// valueが一致するoptionの選択可否を切り替える
+    pub fn set_option_disabled(&self, value: &str, disabled: bool) -> Result<()> {
+        let option = (0..self.element.length())
+            .filter_map(|i| self.element.item(i))
+            .filter_map(|e| e.dyn_into::<web_sys::HtmlOptionElement>().ok())
+            .find(|o| o.value() == value)
+            .ok_or(Error::js(&format!("option not found: {value}")))?;
+        option.set_disabled(disabled);
+        Ok(())
+    }
+
     pub fn remove(&self) {
         remove_closure(self.ident.id());
     }
 }
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+    use crate::util;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[derive(Debug, Clone, Copy, PartialEq)]
+    enum Mode {
+        A,
+        B,
+        C,
+    }
+
+    impl SelectOption for Mode {
+        fn iter() -> &'static [Self] {
+            &[Mode::A, Mode::B, Mode::C]
+        }
+        fn value(&self) -> &str {
+            match self {
+                Mode::A => "a",
+                Mode::B => "b",
+                Mode::C => "c",
+            }
+        }
+        fn text(&self) -> &str {
+            self.value()
+        }
+        fn from_str(value: &str) -> Self {
+            match value {
+                "b" => Mode::B,
+                "c" => Mode::C,
+                _ => Mode::A,
+            }
+        }
+    }
+
+    // テスト毎に別の要素を使うためにidを持たせる
+    #[derive(Debug, Clone)]
+    struct Ident(&'static str, Mode);
+
+    impl InputIdent for Ident {
+        fn id(&self) -> &'static str {
+            self.0
+        }
+    }
+
+    impl InputOption<Mode> for Ident {
+        fn value(&self) -> Result<Mode> {
+            Ok(self.1)
+        }
+        fn with_value(&self, value: Mode) -> Result<Self> {
+            Ok(Ident(self.0, value))
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_set_option_disabled() {
+        let select = util::create_element::<web_sys::HtmlSelectElement>("select").unwrap();
+        select.set_id("test-select-disabled");
+        util::get_body().unwrap().append_child(&select).unwrap();
+
+        let input = SelectInput::new(Ident("test-select-disabled", Mode::A)).unwrap();
+        input.set_option_disabled("b", true).unwrap();
+        let option = |i| {
+            select
+                .item(i)
+                .unwrap()
+                .dyn_into::<web_sys::HtmlOptionElement>()
+                .unwrap()
+        };
+        assert!(!option(0).disabled());
+        assert!(option(1).disabled());
+        assert!(!option(2).disabled());
+        assert!(input.set_option_disabled("d", true).is_err());
+
+        select.remove();
+    }
+
+    #[wasm_bindgen_test]
+    fn test_try_from_str() {
+        // 手動実装でもiterから探すデフォルト実装が使える
+        assert_eq!(Mode::try_from_str("b"), Some(Mode::B));
+        assert_eq!(Mode::try_from_str("d"), None);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_apply() {
+        let select = util::create_element::<web_sys::HtmlSelectElement>("select").unwrap();
+        select.set_id("test-select-apply");
+        util::get_body().unwrap().append_child(&select).unwrap();
+
+        let input = SelectInput::new(Ident("test-select-apply", Mode::A)).unwrap();
+        input.apply(Mode::B);
+        assert_eq!(input.state.borrow().value(), Mode::B.value());
+        assert_eq!(select.value(), Mode::B.value());
+
+        select.remove();
+    }
+}
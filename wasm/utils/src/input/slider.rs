@@ -1,4 +1,9 @@
-use std::{cell::RefCell, fmt::Debug, rc::Rc, str::FromStr};
+use std::{
+    cell::RefCell,
+    fmt::{Debug, Display},
+    rc::Rc,
+    str::FromStr,
+};
 
 use futures_channel::mpsc;
 use wasm_bindgen::prelude::*;
@@ -14,6 +19,8 @@ pub struct SliderConfig<T> {
     pub max: T,
     pub step: T,
     pub default: T,
+    /// 現在値を表示するエレメントのid
+    pub label_element_id: Option<&'static str>,
 }
 
 impl<T> SliderConfig<T>
@@ -26,9 +33,16 @@ where
             max,
             step,
             default,
+            label_element_id: None,
         }
     }
 
+    /// 現在値を表示するエレメントを設定する
+    pub fn with_label(mut self, id: &'static str) -> Self {
+        self.label_element_id = Some(id);
+        self
+    }
+
     fn apply(&self, ele: &web_sys::HtmlInputElement) {
         ele.set_min(&self.min.to_string());
         ele.set_max(&self.max.to_string());
@@ -98,12 +112,13 @@ where
     element: web_sys::HtmlInputElement,
     state: Rc<RefCell<T>>,
     ident: I,
+    label: Option<(&'static str, web_sys::HtmlElement)>,
 }
 
 impl<I, T> SliderInput<I, T>
 where
     I: InputIdent + InputNumber<T>,
-    T: Copy + FromStr + ToString + 'static,
+    T: Copy + FromStr + ToString + Display + 'static,
 {
     pub fn new(ident: I, mut config: SliderConfig<T>) -> Result<Self> {
         let id = ident.id();
@@ -112,11 +127,16 @@ where
         config.default = default;
         config.apply(&element);
         let state = Rc::new(RefCell::new(config.default));
+        let label = match config.label_element_id {
+            Some(id) => Some((id, get_element::<web_sys::HtmlElement>(id)?)),
+            None => None,
+        };
 
         let s = Self {
             element,
             state,
             ident,
+            label,
         };
         s.init();
 
@@ -127,13 +147,25 @@ where
     pub fn init(&self) {
         let value = self.state.borrow().to_string();
         self.element.set_value(&value);
+        self.update_label(*self.state.borrow());
+    }
+
+    fn update_label(&self, value: T) {
+        if let Some((_, label)) = &self.label {
+            label.set_inner_text(&format!("{:.2}", value));
+        }
+    }
+
+    /// 現在値を表示するエレメントのid
+    pub fn get_label_element_id(&self) -> Option<&'static str> {
+        self.label.as_ref().map(|(id, _)| *id)
     }
 
     /// イベントリスナーを登録する
     pub fn start(&self, mut tx: mpsc::Sender<I>) -> Result<()> {
         // check closure
         if contains(self.ident.id()) {
-            return Err(JsError::new(&format!(
+            return Err(Error::js(&format!(
                 "Closure already exists: {}",
                 self.ident.id()
             )));
@@ -141,12 +173,16 @@ where
         let ele = self.element.clone();
         let state = self.state.clone();
         let ident = self.ident.to_owned();
+        let label = self.label.as_ref().map(|(_, e)| e.clone());
         let closure = Closure::wrap(Box::new(move || {
             let value = match ele.value().parse::<T>() {
                 Ok(v) => v,
                 Err(_) => return,
             };
             *state.borrow_mut() = value;
+            if let Some(label) = &label {
+                label.set_inner_text(&format!("{:.2}", value));
+            }
             // send message with sync
             match tx.try_send(ident.with_value(value).unwrap()) {
                 Ok(_) => (),
@@ -165,6 +201,7 @@ where
     pub fn apply(&self, value: T) {
         self.element.set_value(&value.to_string());
         *self.state.borrow_mut() = value;
+        self.update_label(value);
     }
 
     pub fn remove(&self) {
@@ -226,7 +263,7 @@ where
     pub fn start(&self, mut tx: mpsc::Sender<I>) -> Result<()> {
         // check closure
         if contains(self.ident.id()) {
-            return Err(JsError::new(&format!(
+            return Err(Error::js(&format!(
                 "Closure already exists: {}",
                 self.ident.id()
             )));
@@ -270,3 +307,62 @@ where
         *self.state.borrow()
     }
 }
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+    use crate::util;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[derive(Debug, Clone)]
+    struct Ident(f32);
+
+    impl InputIdent for Ident {
+        fn id(&self) -> &'static str {
+            "test-slider-label"
+        }
+    }
+
+    impl InputNumber<f32> for Ident {
+        fn value(&self) -> Result<f32> {
+            Ok(self.0)
+        }
+        fn with_value(&self, value: f32) -> Result<Self> {
+            Ok(Ident(value))
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_display_label() {
+        let body = util::get_body().unwrap();
+        let slider = util::create_element::<web_sys::HtmlInputElement>("input").unwrap();
+        slider.set_type("range");
+        slider.set_id("test-slider-label");
+        body.append_child(&slider).unwrap();
+        let label = util::create_element::<web_sys::HtmlElement>("span").unwrap();
+        label.set_id("test-slider-label-value");
+        body.append_child(&label).unwrap();
+
+        let config = SliderConfig::new(0.0, 1.0, 0.25, 0.5).with_label("test-slider-label-value");
+        let input = SliderInput::new(Ident(0.5), config).unwrap();
+        assert_eq!(
+            input.get_label_element_id(),
+            Some("test-slider-label-value")
+        );
+        assert_eq!(label.inner_text(), "0.50");
+
+        let (tx, _rx) = mpsc::channel(4);
+        input.start(tx).unwrap();
+        slider.set_value("0.75");
+        slider.oninput().unwrap().call0(&JsValue::NULL).unwrap();
+        assert_eq!(input.value(), 0.75);
+        assert_eq!(label.inner_text(), "0.75");
+
+        input.remove();
+        slider.remove();
+        label.remove();
+    }
+}
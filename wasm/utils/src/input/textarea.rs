@@ -29,7 +29,7 @@ where
     pub fn start(&self, mut tx: mpsc::Sender<I>) -> Result<()> {
         // check closure
         if contains(self.ident.id()) {
-            return Err(JsError::new(&format!(
+            return Err(Error::js(&format!(
                 "Closure already exists: {}",
                 self.ident.id()
             )));
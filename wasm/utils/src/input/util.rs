@@ -30,13 +30,13 @@ where
     T: wasm_bindgen::JsCast,
 {
     web_sys::window()
-        .ok_or(JsError::new("Failed to get window"))?
+        .ok_or(Error::js("Failed to get window"))?
         .document()
-        .ok_or(JsError::new("Failed to get document"))?
+        .ok_or(Error::js("Failed to get document"))?
         .get_element_by_id(id)
-        .ok_or(JsError::new(&format!("Failed to get element: {id}")))?
+        .ok_or(Error::js(&format!("Failed to get element: {id}")))?
         .dyn_into::<T>()
-        .map_err(|_| JsError::new(&format!("Failed to convert Element: {id}")))
+        .map_err(|_| Error::js(&format!("Failed to convert Element: {id}")))
 }
 
 /// エレメントを作成のラッパー
@@ -45,13 +45,13 @@ where
     T: wasm_bindgen::JsCast,
 {
     web_sys::window()
-        .ok_or(JsError::new("window is None"))?
+        .ok_or(Error::js("window is None"))?
         .document()
-        .ok_or(JsError::new("document is None"))?
+        .ok_or(Error::js("document is None"))?
         .create_element(tag)
-        .map_err(|_| JsError::new("cannot create element"))?
+        .map_err(|_| Error::js("cannot create element"))?
         .dyn_into::<T>()
-        .map_err(|_| JsError::new("cannot convert to HtmlElement"))
+        .map_err(|_| Error::js("cannot convert to HtmlElement"))
 }
 
 /// イベントリスナーを登録する
@@ -62,7 +62,7 @@ pub(super) fn add_event_listener(
 ) -> Result<()> {
     element
         .add_event_listener_with_callback(event, callback.unchecked_ref())
-        .map_err(|_| JsError::new("Failed to add event listener"))?;
+        .map_err(|_| Error::js("Failed to add event listener"))?;
     Ok(())
 }
 
@@ -5,6 +5,13 @@ macro_rules! info {
     }
 }
 
+#[macro_export]
+macro_rules! warn {
+    ( $( $t:tt )* ) => {
+        $crate::__reexport::console::warn_1(&format!( $( $t )* ).into());
+    }
+}
+
 #[macro_export]
 macro_rules! error {
     ( $( $t:tt )* ) => {
@@ -21,3 +28,18 @@ macro_rules! alert {
             .unwrap();
     };
 }
+
+#[cfg(test)]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_console_macros() {
+        let v = 1;
+        info!("info {v}");
+        warn!("warn {}", v);
+        error!("error {v:?}");
+    }
+}
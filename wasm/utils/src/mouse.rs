@@ -1,15 +1,21 @@
 //! マウスイベントを処理してWASM空間で扱いやすい方にする。
 
-use std::{cell::RefCell, rc::Rc};
-
-use crate::{error::Result, util::get_window};
-use futures_channel::mpsc::{UnboundedReceiver, UnboundedSender};
+use std::{
+    cell::{Cell, RefCell},
+    rc::Rc,
+};
+
+use crate::{
+    error::{Error, Result},
+    util::get_window,
+};
+use futures_channel::mpsc::{TryRecvError, UnboundedReceiver, UnboundedSender};
 use fxhash::FxHashMap;
 use wasm_bindgen::prelude::*;
-use web_sys::{AddEventListenerOptions, MouseEvent, WheelEvent};
+use web_sys::{AddEventListenerOptions, MouseEvent, PointerEvent, ResizeObserver, WheelEvent};
 
 /// マウス座標を保持、計算する構造体
-#[derive(Debug, Default, Clone, Copy)]
+#[derive(Debug, Default, Clone, Copy, PartialEq)]
 pub struct Point {
     pub x: f32,
     pub y: f32,
@@ -19,6 +25,26 @@ impl Point {
     pub fn new(x: f32, y: f32) -> Self {
         Self { x, y }
     }
+
+    /// 2点間のユークリッド距離
+    pub fn distance(self, other: Self) -> f32 {
+        (self - other).magnitude()
+    }
+
+    /// 原点からの長さ
+    pub fn magnitude(self) -> f32 {
+        (self.x * self.x + self.y * self.y).sqrt()
+    }
+
+    /// 長さ1に正規化する。ゼロベクトルはそのまま返す
+    pub fn normalize(self) -> Self {
+        let m = self.magnitude();
+        if m == 0.0 {
+            self
+        } else {
+            self / m
+        }
+    }
 }
 
 impl std::ops::Sub for Point {
@@ -56,10 +82,30 @@ pub struct Wheel {
     pub y: f32,
 }
 
+impl Wheel {
+    /// DOM_DELTA_LINEの場合に1行あたりとみなすピクセル数
+    pub const PIXELS_PER_LINE: f32 = 40.0;
+
+    /// deltaModeの違いをピクセル単位に揃えてからscaleを掛ける
+    pub fn normalized(delta_x: f64, delta_y: f64, delta_mode: u32, scale: f32) -> Self {
+        let unit = match delta_mode {
+            WheelEvent::DOM_DELTA_LINE => Self::PIXELS_PER_LINE,
+            _ => 1.0,
+        };
+        Self {
+            x: delta_x as f32 * unit * scale,
+            y: delta_y as f32 * unit * scale,
+        }
+    }
+}
+
 /// モジュール外にマウスとホイールのイベントを通知する
+///
+/// ポインタロック中の移動は`Move`の代わりにGL空間での移動量を`MoveBy`で通知する
 #[derive(Debug, Clone, Copy)]
 pub enum MouseEventMessage {
     Move { pos: Point },
+    MoveBy { delta: Point },
     Wheel { wheel: Wheel },
     Down { pos: Point },
     Up { pos: Point },
@@ -68,6 +114,51 @@ pub enum MouseEventMessage {
     Resize,
 }
 
+// 前回通知した位置から一定距離以内のMoveイベントを間引く
+#[derive(Debug, Default)]
+struct MoveFilter {
+    threshold: Cell<f32>,
+    last: Cell<Option<Point>>,
+}
+
+impl MoveFilter {
+    fn accept(&self, pos: Point) -> bool {
+        if let Some(last) = self.last.get() {
+            if pos.distance(last) < self.threshold.get() {
+                return false;
+            }
+        }
+        self.last.set(Some(pos));
+        true
+    }
+}
+
+// ドラッグ中にCanvas外へ出てもイベントを受け取るためのポインタキャプチャの状態
+#[derive(Debug, Default)]
+struct PointerCapture {
+    enabled: Cell<bool>,
+    // キャプチャを要求中のpointerId
+    requested: Cell<Option<i32>>,
+}
+
+impl PointerCapture {
+    fn down(&self, canvas: &web_sys::HtmlCanvasElement, pointer_id: i32) {
+        if !self.enabled.get() {
+            return;
+        }
+        self.requested.set(Some(pointer_id));
+        // 失敗した場合もmouse*イベントで通知は続くので無視する
+        let _ = canvas.set_pointer_capture(pointer_id);
+    }
+
+    fn up(&self, canvas: &web_sys::HtmlCanvasElement, pointer_id: i32) {
+        if self.requested.get() == Some(pointer_id) {
+            self.requested.set(None);
+            let _ = canvas.release_pointer_capture(pointer_id);
+        }
+    }
+}
+
 #[derive(Debug, Clone, Default)]
 struct PosCnv {
     inner: Rc<RefCell<PositionConverterInner>>,
@@ -87,6 +178,23 @@ impl PosCnv {
     fn pixel_to_gl(&self, p: Point) -> Point {
         self.inner.borrow().pixel_to_gl(p)
     }
+
+    fn pixel_delta_to_gl(&self, d: Point) -> Point {
+        self.inner.borrow().pixel_delta_to_gl(d)
+    }
+
+    // ポインタロック中は絶対座標が変化しないため移動量を通知する
+    fn move_message(&self, page: Point, movement: Point, locked: bool) -> MouseEventMessage {
+        if locked {
+            MouseEventMessage::MoveBy {
+                delta: self.pixel_delta_to_gl(movement),
+            }
+        } else {
+            MouseEventMessage::Move {
+                pos: self.pixel_to_gl(page),
+            }
+        }
+    }
 }
 
 // Canvasで得られるマウスのpx座標をOpenGLの座標に変換する
@@ -133,6 +241,41 @@ impl PositionConverterInner {
         gl_p.y = -gl_p.y;
         gl_p
     }
+
+    fn pixel_delta_to_gl(&self, d: Point) -> Point {
+        let mut gl_d = d / self.area_c * 2.;
+        gl_d.y = -gl_d.y;
+        gl_d
+    }
+}
+
+// ResizeObserverとそのコールバックを保持し、破棄時に監視を止める
+struct ResizeWatcher {
+    observer: ResizeObserver,
+    _closure: Closure<dyn FnMut(JsValue)>,
+}
+
+impl Drop for ResizeWatcher {
+    fn drop(&mut self) {
+        self.observer.disconnect();
+    }
+}
+
+// Canvasの大きさが変わるたびに座標変換の位置と大きさを読み直す
+//
+// visual_viewportのresizeはページのレイアウト変更では発火しないため、要素自体を監視する
+fn observe_resize(canvas: &web_sys::HtmlCanvasElement, cnv: PosCnv) -> Result<ResizeWatcher> {
+    let target = canvas.clone();
+    let closure = Closure::wrap(Box::new(move |_entries: JsValue| {
+        cnv.update_by_canvas(&target);
+    }) as Box<dyn FnMut(JsValue)>);
+    let observer = ResizeObserver::new(closure.as_ref().unchecked_ref())
+        .map_err(|_| Error::js("Failed to create ResizeObserver"))?;
+    observer.observe(canvas);
+    Ok(ResizeWatcher {
+        observer,
+        _closure: closure,
+    })
 }
 
 /// マウスイベントを処理する構造体
@@ -141,11 +284,20 @@ pub struct MouseEventHandler {
     cnv: PosCnv,
     mouse_closures: FxHashMap<String, Closure<dyn FnMut(MouseEvent)>>,
     wheel_closures: FxHashMap<String, Closure<dyn FnMut(WheelEvent)>>,
+    pointer_closures: FxHashMap<String, Closure<dyn FnMut(PointerEvent)>>,
+    pointer_capture: Rc<PointerCapture>,
+    resize_watcher: Option<ResizeWatcher>,
+    wheel_scale: Rc<Cell<f32>>,
+    move_filter: Rc<MoveFilter>,
+    max_per_frame: usize,
     tx: UnboundedSender<MouseEventMessage>,
     rx: UnboundedReceiver<MouseEventMessage>,
 }
 
 impl MouseEventHandler {
+    /// try_recv_allで一度に受け取るイベント数の既定値
+    pub const DEFAULT_MAX_PER_FRAME: usize = 64;
+
     pub fn new(canvas: web_sys::HtmlCanvasElement) -> Self {
         let cnv = PosCnv::from_canvas(&canvas);
         let (tx, rx) = futures_channel::mpsc::unbounded();
@@ -155,11 +307,47 @@ impl MouseEventHandler {
             cnv,
             mouse_closures: FxHashMap::default(),
             wheel_closures: FxHashMap::default(),
+            pointer_closures: FxHashMap::default(),
+            pointer_capture: Rc::new(PointerCapture::default()),
+            resize_watcher: None,
+            wheel_scale: Rc::new(Cell::new(1.0)),
+            move_filter: Rc::new(MoveFilter::default()),
+            max_per_frame: Self::DEFAULT_MAX_PER_FRAME,
             tx,
             rx,
         }
     }
 
+    /// ホイールの移動量に掛ける倍率を設定する。start後に変更しても反映される
+    pub fn set_wheel_scale(&mut self, scale: f32) {
+        self.wheel_scale.set(scale);
+    }
+
+    /// 前回通知した位置からGL空間でこの距離未満の移動はMoveイベントを通知しない
+    pub fn set_drag_threshold(&mut self, gl_units: f32) {
+        self.move_filter.threshold.set(gl_units);
+    }
+
+    /// ボタンを押している間、ポインタをキャプチャしてCanvas外のイベントも受け取る
+    ///
+    /// 素早くドラッグしてCanvas外に出てもmousemoveが途切れなくなる。start後に変更しても反映される
+    pub fn set_capture_pointer(&mut self, enabled: bool) {
+        self.pointer_capture.enabled.set(enabled);
+    }
+
+    /// キャンバスにポインタロックを要求する
+    pub fn request_pointer_lock(&self) -> Result<()> {
+        self.canvas.request_pointer_lock();
+        Ok(())
+    }
+
+    /// ポインタロックを解除する
+    pub fn release_pointer_lock(&self) {
+        if let Some(document) = self.canvas.owner_document() {
+            document.exit_pointer_lock();
+        }
+    }
+
     pub fn start(&mut self) {
         // マウスの上げ下げイベントは位置と状態を更新
         self.build_mouse_closure("mousedown", |(cnv, event)| {
@@ -175,10 +363,21 @@ impl MouseEventHandler {
         });
 
         // マウス移動は移動のみを取得
-        self.build_mouse_closure("mousemove", |(cnv, event)| {
-            let pos = Point::new(event.page_x() as f32, event.page_y() as f32);
-            let pos = cnv.pixel_to_gl(pos);
-            Some(MouseEventMessage::Move { pos })
+        let move_filter = self.move_filter.clone();
+        let canvas = self.canvas.clone();
+        self.build_mouse_closure("mousemove", move |(cnv, event)| {
+            let page = Point::new(event.page_x() as f32, event.page_y() as f32);
+            let movement = Point::new(event.movement_x() as f32, event.movement_y() as f32);
+            let locked = canvas
+                .owner_document()
+                .and_then(|d| d.pointer_lock_element())
+                .is_some_and(|e| e == **canvas);
+            match cnv.move_message(page, movement, locked) {
+                MouseEventMessage::Move { pos } => move_filter
+                    .accept(pos)
+                    .then_some(MouseEventMessage::Move { pos }),
+                msg => Some(msg),
+            }
         });
 
         self.build_mouse_closure("click", |(cnv, event)| {
@@ -194,19 +393,44 @@ impl MouseEventHandler {
         });
 
         // ホイールイベントはホイールの移動量を取得
-        self.build_wheel_closure("wheel", |event| {
+        // ブラウザによって単位が異なるのでピクセル単位に揃える
+        let wheel_scale = self.wheel_scale.clone();
+        self.build_wheel_closure("wheel", move |event| {
             Some(MouseEventMessage::Wheel {
-                wheel: Wheel {
-                    x: event.delta_x() as f32,
-                    y: event.delta_y() as f32,
-                },
+                wheel: Wheel::normalized(
+                    event.delta_x(),
+                    event.delta_y(),
+                    event.delta_mode(),
+                    wheel_scale.get(),
+                ),
             })
         });
 
+        // ポインタキャプチャ中の互換マウスイベントはキャプチャ先に届くので
+        // 位置の通知は引き続きmouse*イベントで行う
+        let capture = self.pointer_capture.clone();
+        let canvas = self.canvas.clone();
+        self.build_pointer_closure("pointerdown", move |event| {
+            capture.down(&canvas, event.pointer_id())
+        });
+        let capture = self.pointer_capture.clone();
+        let canvas = self.canvas.clone();
+        self.build_pointer_closure("pointerup", move |event| {
+            capture.up(&canvas, event.pointer_id())
+        });
+
         // リサイズイベントを取得
         self.build_resize_closure();
     }
 
+    /// Canvas要素の大きさの変化を監視して座標変換を更新する
+    ///
+    /// ページのレイアウト変更でCanvasの位置や大きさが変わる場合に使う
+    pub fn observe_resize(&mut self) -> Result<()> {
+        self.resize_watcher = Some(observe_resize(&self.canvas, self.cnv.clone())?);
+        Ok(())
+    }
+
     fn update_resize(&mut self) {
         self.cnv.update_by_canvas(&self.canvas);
     }
@@ -258,6 +482,16 @@ impl MouseEventHandler {
         self.wheel_closures.insert(event_type.to_string(), clusure);
     }
 
+    // ポインタイベントのクロージャを登録
+    fn build_pointer_closure(&mut self, event_type: &str, f: impl Fn(PointerEvent) + 'static) {
+        let closure = Closure::wrap(Box::new(f) as Box<dyn FnMut(PointerEvent)>);
+        self.canvas
+            .add_event_listener_with_callback(event_type, closure.as_ref().unchecked_ref())
+            .unwrap();
+        self.pointer_closures
+            .insert(event_type.to_string(), closure);
+    }
+
     fn build_resize_closure(&mut self) {
         let mut tx = self.tx.clone();
         let closure =
@@ -282,15 +516,48 @@ impl MouseEventHandler {
                 )
                 .unwrap();
         }
+        for (event_type, closure) in self.pointer_closures.drain() {
+            self.canvas
+                .remove_event_listener_with_callback(
+                    event_type.as_str(),
+                    closure.as_ref().unchecked_ref(),
+                )
+                .unwrap();
+        }
+        self.resize_watcher = None;
     }
 
     pub fn try_recv(&mut self) -> Result<Option<MouseEventMessage>> {
-        match self.rx.try_next()? {
-            Some(msg) => Ok(self.msg_handle(Some(msg))),
-            None => Ok(None),
+        match self.rx.try_recv() {
+            Ok(msg) => Ok(self.msg_handle(Some(msg))),
+            Err(TryRecvError::Closed) => Ok(None),
+            Err(e) => Err(Error::js(&e.to_string())),
         }
     }
 
+    /// try_recv_allで一度に受け取るイベント数の上限を設定する
+    pub fn set_max_per_frame(&mut self, max_per_frame: usize) {
+        self.max_per_frame = max_per_frame;
+    }
+
+    /// 溜まっているイベントをまとめて受信する
+    ///
+    /// フレームの処理が滞らないように最大max_per_frame件まで。残りは次回に受け取る
+    pub fn try_recv_all(&mut self) -> Vec<MouseEventMessage> {
+        let mut messages = Vec::new();
+        while messages.len() < self.max_per_frame {
+            match self.rx.try_recv() {
+                Ok(msg) => {
+                    if let Some(msg) = self.msg_handle(Some(msg)) {
+                        messages.push(msg);
+                    }
+                }
+                Err(_) => break,
+            }
+        }
+        messages
+    }
+
     /// マウスイベントを受信する
     pub async fn recv(&mut self) -> Option<MouseEventMessage> {
         use futures_util::StreamExt;
@@ -315,3 +582,148 @@ impl Drop for MouseEventHandler {
         self.stop();
     }
 }
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_wheel_normalized() {
+        let line = Wheel::normalized(0.0, 3.0, WheelEvent::DOM_DELTA_LINE, 0.5);
+        let pixel = Wheel::normalized(0.0, 120.0, WheelEvent::DOM_DELTA_PIXEL, 0.5);
+        assert_eq!(line.y, pixel.y);
+        assert_eq!(line.y, 60.0);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_move_message_by_lock_state() {
+        let cnv = PosCnv {
+            inner: Rc::new(RefCell::new(PositionConverterInner::new(
+                Point::new(0.0, 0.0),
+                Point::new(100.0, 100.0),
+            ))),
+        };
+        let page = Point::new(75.0, 25.0);
+        let movement = Point::new(10.0, 20.0);
+
+        let MouseEventMessage::Move { pos } = cnv.move_message(page, movement, false) else {
+            panic!("expected Move");
+        };
+        assert_eq!(pos, Point::new(0.5, 0.5));
+
+        let MouseEventMessage::MoveBy { delta } = cnv.move_message(page, movement, true) else {
+            panic!("expected MoveBy");
+        };
+        assert_eq!(delta, Point::new(0.2, -0.4));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_point_magnitude() {
+        assert_eq!(Point::new(3.0, 4.0).magnitude(), 5.0);
+        assert_eq!(Point::new(1.0, 1.0).distance(Point::new(4.0, 5.0)), 5.0);
+        assert_eq!(Point::new(0.0, 0.0).normalize(), Point::new(0.0, 0.0));
+        assert_eq!(Point::new(0.0, 2.0).normalize(), Point::new(0.0, 1.0));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_drag_threshold() {
+        let mut handler = MouseEventHandler::new(create_canvas());
+        handler.set_drag_threshold(0.01);
+
+        let emitted = (0..10)
+            .map(|i| {
+                let theta = i as f32 * std::f32::consts::PI / 5.0;
+                Point::new(0.5 + theta.cos() * 0.001, 0.5 + theta.sin() * 0.001)
+            })
+            .filter(|&pos| handler.move_filter.accept(pos))
+            .count();
+        assert_eq!(emitted, 1);
+
+        assert!(handler.move_filter.accept(Point::new(0.6, 0.5)));
+    }
+
+    fn create_canvas() -> web_sys::HtmlCanvasElement {
+        get_window()
+            .unwrap()
+            .document()
+            .unwrap()
+            .create_element("canvas")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .unwrap()
+    }
+
+    #[wasm_bindgen_test]
+    fn test_try_recv_all() {
+        let mut handler = MouseEventHandler::new(create_canvas());
+        for i in 0..5 {
+            let pos = Point::new(i as f32, 0.0);
+            handler
+                .tx
+                .unbounded_send(MouseEventMessage::Move { pos })
+                .unwrap();
+        }
+
+        let messages = handler.try_recv_all();
+        assert_eq!(messages.len(), 5);
+        for (i, msg) in messages.iter().enumerate() {
+            match msg {
+                MouseEventMessage::Move { pos } => assert_eq!(pos.x, i as f32),
+                _ => panic!("unexpected message {:?}", msg),
+            }
+        }
+        assert!(handler.try_recv_all().is_empty());
+    }
+
+    fn dispatch_pointer(canvas: &web_sys::HtmlCanvasElement, event_type: &str, pointer_id: i32) {
+        let init = web_sys::PointerEventInit::new();
+        init.set_pointer_id(pointer_id);
+        let event = PointerEvent::new_with_event_init_dict(event_type, &init).unwrap();
+        canvas.dispatch_event(&event).unwrap();
+    }
+
+    #[wasm_bindgen_test]
+    fn test_capture_pointer() {
+        let canvas = create_canvas();
+        let mut handler = MouseEventHandler::new(canvas.clone());
+        handler.start();
+
+        // 無効な場合はキャプチャしない
+        dispatch_pointer(&canvas, "pointerdown", 1);
+        assert_eq!(handler.pointer_capture.requested.get(), None);
+        dispatch_pointer(&canvas, "pointerup", 1);
+
+        handler.set_capture_pointer(true);
+        dispatch_pointer(&canvas, "pointerdown", 1);
+        assert_eq!(handler.pointer_capture.requested.get(), Some(1));
+
+        // 別のポインタが離れても解除しない
+        dispatch_pointer(&canvas, "pointerup", 2);
+        assert_eq!(handler.pointer_capture.requested.get(), Some(1));
+        dispatch_pointer(&canvas, "pointerup", 1);
+        assert_eq!(handler.pointer_capture.requested.get(), None);
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_observe_resize() {
+        let canvas = create_canvas();
+        canvas.set_width(100);
+        canvas.set_height(100);
+        let body = get_window().unwrap().document().unwrap().body().unwrap();
+        body.append_child(&canvas).unwrap();
+
+        let mut handler = MouseEventHandler::new(canvas.clone());
+        handler.observe_resize().unwrap();
+        assert_eq!(handler.cnv.inner.borrow().area_c, Point::new(100.0, 100.0));
+
+        canvas.set_width(200);
+        gloo_timers::future::TimeoutFuture::new(50).await;
+        assert_eq!(handler.cnv.inner.borrow().area_c, Point::new(200.0, 100.0));
+
+        drop(handler);
+        canvas.remove();
+    }
+}
@@ -8,3 +8,75 @@ pub fn set_panic_hook() {
     #[cfg(feature = "console_error_panic_hook")]
     console_error_panic_hook::set_once();
 }
+
+/// panic時にメッセージと発生箇所をオブジェクトとしてconsole.errorに出力する
+///
+/// DevToolsで構造化されたデータとして確認できる。
+/// リリースビルドでは[set_panic_hook]と同じ文字列の出力になる
+pub fn install_console_error_hook() {
+    #[cfg(debug_assertions)]
+    std::panic::set_hook(Box::new(|info| {
+        #[cfg(feature = "console_error_panic_hook")]
+        console_error_panic_hook::hook(info);
+        let message = panic_message(info);
+        let (file, line) = info
+            .location()
+            .map(|l| (l.file(), l.line()))
+            .unwrap_or(("<unknown>", 0));
+        web_sys::console::error_1(&panic_object(&message, file, line));
+    }));
+    #[cfg(not(debug_assertions))]
+    set_panic_hook();
+}
+
+#[cfg(debug_assertions)]
+fn panic_message(info: &std::panic::PanicHookInfo) -> String {
+    let payload = info.payload();
+    if let Some(s) = payload.downcast_ref::<&str>() {
+        s.to_string()
+    } else if let Some(s) = payload.downcast_ref::<String>() {
+        s.clone()
+    } else {
+        info.to_string()
+    }
+}
+
+// console.errorに渡すオブジェクトを作る
+#[cfg_attr(not(debug_assertions), allow(dead_code))]
+fn panic_object(message: &str, file: &str, line: u32) -> web_sys::js_sys::Object {
+    use wasm_bindgen::JsValue;
+    use web_sys::js_sys::{Object, Reflect};
+
+    let obj = Object::new();
+    // 新規オブジェクトへのプロパティ設定は失敗しない
+    let _ = Reflect::set(&obj, &"message".into(), &message.into());
+    let _ = Reflect::set(&obj, &"file".into(), &file.into());
+    let _ = Reflect::set(&obj, &"line".into(), &JsValue::from(line));
+    obj
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use wasm_bindgen_test::*;
+    use web_sys::js_sys::Reflect;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_install_console_error_hook() {
+        install_console_error_hook();
+        // 二重に設定しても問題ない
+        install_console_error_hook();
+    }
+
+    #[wasm_bindgen_test]
+    fn test_panic_object() {
+        let obj = panic_object("boom", "src/lib.rs", 42);
+        let get = |key: &str| Reflect::get(&obj, &key.into()).unwrap();
+        assert_eq!(get("message").as_string().unwrap(), "boom");
+        assert_eq!(get("file").as_string().unwrap(), "src/lib.rs");
+        assert_eq!(get("line").as_f64().unwrap(), 42.0);
+    }
+}
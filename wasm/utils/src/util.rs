@@ -1,6 +1,6 @@
 use wasm_bindgen::prelude::*;
 
-use crate::error::Result;
+use crate::error::{Error, Result};
 
 /// エレメント取得のラッパー
 pub fn get_element<T>(id: impl AsRef<str>) -> Result<T>
@@ -9,13 +9,18 @@ where
 {
     let id = id.as_ref();
     web_sys::window()
-        .ok_or(JsError::new("Failed to get window"))?
+        .ok_or(Error::js("Failed to get window"))?
         .document()
-        .ok_or(JsError::new("Failed to get document"))?
+        .ok_or(Error::js("Failed to get document"))?
         .get_element_by_id(id)
-        .ok_or(JsError::new(&format!("Failed to get element: {id}")))?
+        .ok_or(Error::js(&format!("Failed to get element: {id}")))?
         .dyn_into::<T>()
-        .map_err(|_| JsError::new(&format!("Failed to convert Element: {id}")))
+        .map_err(|_| Error::js(&format!("Failed to convert Element: {id}")))
+}
+
+/// キャンバス取得のラッパー
+pub fn get_canvas_by_id(id: &str) -> Result<web_sys::HtmlCanvasElement> {
+    get_element(id)
 }
 
 /// エレメントを作成のラッパー
@@ -24,38 +29,38 @@ where
     T: wasm_bindgen::JsCast,
 {
     web_sys::window()
-        .ok_or(JsError::new("window is None"))?
+        .ok_or(Error::js("window is None"))?
         .document()
-        .ok_or(JsError::new("document is None"))?
+        .ok_or(Error::js("document is None"))?
         .create_element(tag.as_ref())
-        .map_err(|_| JsError::new("cannot create element"))?
+        .map_err(|_| Error::js("cannot create element"))?
         .dyn_into::<T>()
-        .map_err(|_| JsError::new("cannot convert to HtmlElement"))
+        .map_err(|_| Error::js("cannot convert to HtmlElement"))
 }
 
 /// Bodyを取得のラッパー
 pub fn get_body() -> Result<web_sys::HtmlElement> {
     web_sys::window()
-        .ok_or(JsError::new("window is None"))?
+        .ok_or(Error::js("window is None"))?
         .document()
-        .ok_or(JsError::new("document is None"))?
+        .ok_or(Error::js("document is None"))?
         .body()
-        .ok_or(JsError::new("body is None"))?
+        .ok_or(Error::js("body is None"))?
         .dyn_into::<web_sys::HtmlElement>()
-        .map_err(|_| JsError::new("cannot convert to HtmlElement"))
+        .map_err(|_| Error::js("cannot convert to HtmlElement"))
 }
 
 /// ウィンドウを取得のラッパー
 pub fn get_window() -> Result<web_sys::Window> {
-    web_sys::window().ok_or(JsError::new("window is None"))
+    web_sys::window().ok_or(Error::js("window is None"))
 }
 
 /// パフォーマンスを取得のラッパー
 pub fn get_performance() -> Result<web_sys::Performance> {
     web_sys::window()
-        .ok_or(JsError::new("Failed to get window"))?
+        .ok_or(Error::js("Failed to get window"))?
         .performance()
-        .ok_or(JsError::new("Failed to get performance"))
+        .ok_or(Error::js("Failed to get performance"))
 }
 
 /// エレメントリストを取得のラッパー
@@ -65,17 +70,17 @@ where
 {
     let class_name = class_name.as_ref();
     let elements = web_sys::window()
-        .ok_or(JsError::new("Failed to get window"))?
+        .ok_or(Error::js("Failed to get window"))?
         .document()
-        .ok_or(JsError::new("Failed to get document"))?
+        .ok_or(Error::js("Failed to get document"))?
         .get_elements_by_class_name(class_name);
     let mut result = Vec::new();
     for i in 0..elements.length() {
         let element = elements
             .item(i)
-            .ok_or(JsError::new("Failed to get element"))?
+            .ok_or(Error::js("Failed to get element"))?
             .dyn_into::<T>()
-            .map_err(|_| JsError::new("Failed to convert to T"))?;
+            .map_err(|_| Error::js("Failed to convert to T"))?;
         result.push(element);
     }
     Ok(result)
@@ -89,7 +94,7 @@ pub fn add_event_listener(
 ) -> Result<()> {
     element
         .add_event_listener_with_callback(event, callback.unchecked_ref())
-        .map_err(|_| JsError::new("Failed to add event listener"))?;
+        .map_err(|_| Error::js("Failed to add event listener"))?;
     Ok(())
 }
 
@@ -101,6 +106,6 @@ pub fn remove_event_listener(
 ) -> Result<()> {
     element
         .remove_event_listener_with_callback(event, callback.unchecked_ref())
-        .map_err(|_| JsError::new("Failed to remove event listener"))?;
+        .map_err(|_| Error::js("Failed to remove event listener"))?;
     Ok(())
 }
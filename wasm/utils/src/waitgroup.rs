@@ -59,6 +59,38 @@ impl WaitGroup {
         self.count.load(Relaxed)
     }
 
+    /// 全てのワーカーが終了するまで、指定時間を上限に待ちます。
+    ///
+    /// WASMではtokioのタイマーが使えないのでgloo-timersで待ちます。
+    /// 内部のReceiverをpollするため`&mut self`を取ります。
+    pub async fn wait_timeout(
+        &mut self,
+        duration: std::time::Duration,
+    ) -> Result<(), WaitGroupTimeoutError> {
+        // 以前の待ち合わせで残った完了通知を読み捨てる
+        while self.rx.try_recv().is_ok() {}
+        if self.is_finished() {
+            return Ok(());
+        }
+
+        #[cfg(target_arch = "wasm32")]
+        {
+            use futures_util::future::{select, Either};
+            let timeout = gloo_timers::future::TimeoutFuture::new(duration.as_millis() as u32);
+            match select(self.rx.next(), timeout).await {
+                Either::Left(_) => Ok(()),
+                Either::Right(_) => Err(WaitGroupTimeoutError),
+            }
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            tokio::time::timeout(duration, self.rx.next())
+                .await
+                .map(|_| ())
+                .map_err(|_| WaitGroupTimeoutError)
+        }
+    }
+
     /// 全てのワーカーが終了するまで待ちます。
     pub fn wait(self) -> WaitGroupFuture {
         WaitGroupFuture {
@@ -67,6 +99,18 @@ impl WaitGroup {
     }
 }
 
+/// [WaitGroup::wait_timeout]で時間内にワーカーが終了しなかったことを示すエラー
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct WaitGroupTimeoutError;
+
+impl std::fmt::Display for WaitGroupTimeoutError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "WaitGroup timed out")
+    }
+}
+
+impl std::error::Error for WaitGroupTimeoutError {}
+
 /// Futureで待ち合わせを行うための構造体
 pub struct WaitGroupFuture {
     rx: StreamFuture<futures_channel::mpsc::Receiver<()>>,
@@ -88,7 +132,8 @@ pub struct Worker {
 
 impl Drop for Worker {
     fn drop(&mut self) {
-        if self.count.fetch_sub(1, Relaxed) == 0 {
+        // 最後のワーカーが抜けたときに完了を通知する
+        if self.count.fetch_sub(1, Relaxed) == 1 {
             let _ = self.tx.clone().try_send(());
         }
     }
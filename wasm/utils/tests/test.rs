@@ -10,7 +10,11 @@ use wasm_bindgen::prelude::*;
 use wasm_bindgen_futures::spawn_local;
 use wasm_bindgen_test::*;
 
-use wasm_utils::{time::*, util::get_performance};
+use wasm_utils::{
+    animation::{AnimationLoop, AnimationTicker},
+    time::*,
+    util::{get_canvas_by_id, get_performance},
+};
 
 wasm_bindgen_test_configure!(run_in_browser);
 
@@ -44,3 +48,74 @@ async fn test_interval() -> std::result::Result<(), JsValue> {
     assert!(count == 10);
     Ok(())
 }
+
+#[wasm_bindgen_test]
+async fn test_animation_step_once() -> std::result::Result<(), JsValue> {
+    let mut a = AnimationLoop::new(|_| Ok(())).with_step_debugger();
+    let debugger = a.step_debugger();
+    let mut ticker = AnimationTicker::default();
+    assert_eq!(a.frame_count(), 0);
+
+    if !cfg!(debug_assertions) {
+        // リリースビルドではステップ実行にならず、step_onceは何もしない
+        assert!(!debugger.is_enabled());
+        a.step_once();
+        debugger.step_once();
+        ticker.tick().await?;
+        assert_eq!(a.frame_count(), 0);
+        assert!(!a.is_running());
+        return Ok(());
+    }
+
+    assert!(debugger.is_enabled());
+    for expected in 1..=2 {
+        a.step_once();
+        // 停止中は何フレーム待ってもカウンタは1しか進まない
+        for _ in 0..3 {
+            ticker.tick().await?;
+        }
+        assert_eq!(a.frame_count(), expected);
+    }
+
+    // JSへ渡すハンドルからも1フレームずつ進められる
+    debugger.step_once();
+    debugger.step_once();
+    for _ in 0..3 {
+        ticker.tick().await?;
+    }
+    assert_eq!(a.frame_count(), 3);
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+async fn test_animation_on_error() -> std::result::Result<(), JsValue> {
+    use std::{cell::RefCell, rc::Rc};
+    use wasm_utils::error::Error;
+
+    let received = Rc::new(RefCell::new(None));
+    let r = received.clone();
+    let mut a = AnimationLoop::new(|_| Err(Error::js("boom"))).on_error(move |e| {
+        *r.borrow_mut() = Some(e);
+    });
+    let mut ticker = AnimationTicker::default();
+    a.start();
+    for _ in 0..3 {
+        ticker.tick().await?;
+    }
+
+    let message = |e: Option<JsValue>| {
+        e.and_then(|e| e.dyn_into::<web_sys::js_sys::Error>().ok())
+            .map(|e| String::from(e.message()))
+    };
+    assert_eq!(message(received.borrow().clone()), Some("boom".to_string()));
+    assert_eq!(message(a.last_error()), Some("boom".to_string()));
+    // エラー後は停止している
+    assert!(!a.is_running());
+    assert_eq!(a.frame_count(), 0);
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_get_canvas_by_id() {
+    assert!(get_canvas_by_id("nonexistent").is_err());
+}
@@ -65,3 +65,40 @@ async fn test_wait_async() -> std::result::Result<(), JsValue> {
 
     Ok(())
 }
+
+// 終わらないワーカーがいる場合はタイムアウトする
+#[wasm_bindgen_test]
+async fn test_wait_timeout() -> std::result::Result<(), JsValue> {
+    use wasm_utils::waitgroup::WaitGroupTimeoutError;
+
+    let mut wg = WaitGroup::new();
+    let _w = wg.add();
+
+    let performance = web_sys::window().unwrap().performance().unwrap();
+    let start = performance.now();
+    let res = wg.wait_timeout(std::time::Duration::from_millis(50)).await;
+    let elapsed = performance.now() - start;
+
+    assert_eq!(res, Err(WaitGroupTimeoutError));
+    assert!(elapsed < 100.0, "elapsed: {elapsed}");
+
+    Ok(())
+}
+
+// 時間内に全てのワーカーが終了すればOkを返す
+#[wasm_bindgen_test]
+async fn test_wait_timeout_finished() -> std::result::Result<(), JsValue> {
+    let mut wg = WaitGroup::new();
+    let w = wg.add();
+
+    spawn_local(async move {
+        gloo_timers::future::TimeoutFuture::new(10).await;
+        drop(w);
+    });
+
+    let res = wg.wait_timeout(std::time::Duration::from_millis(200)).await;
+    assert_eq!(res, Ok(()));
+    assert!(wg.is_finished());
+
+    Ok(())
+}
@@ -1,5 +1,9 @@
+#[cfg(any(feature = "vertex", feature = "texture"))]
+use std::cell::OnceCell;
 use std::rc::Rc;
 
+#[cfg(any(feature = "vertex", feature = "texture"))]
+use crate::GlEnum;
 use crate::{blend::BlendMode, error::Result, program::Program};
 use wasm_bindgen::*;
 use web_sys::{HtmlCanvasElement, WebGl2RenderingContext as gl};
@@ -23,6 +27,9 @@ struct WebGL2ContextOption {
     depth: bool,
     // 描画バッファに8bitのステンシルバッファが必要であることを示す
     stencil: bool,
+    // アンチエイリアスに使うサンプル数の希望値。ブラウザによっては無視される
+    #[serde(skip_serializing_if = "Option::is_none")]
+    samples: Option<i32>,
 }
 
 impl WebGL2ContextOption {
@@ -35,6 +42,7 @@ impl WebGL2ContextOption {
         antialias: true,
         depth: true,
         stencil: true,
+        samples: None,
     };
 }
 
@@ -44,15 +52,24 @@ impl WebGL2ContextOption {
 pub(crate) struct ContextInner {
     gl: Rc<gl>,
     _canvas: HtmlCanvasElement,
+    // KHR_debug拡張の問い合わせ結果。問い合わせは初回のみ行う
+    #[cfg(any(feature = "vertex", feature = "texture"))]
+    khr_debug: OnceCell<Option<js_sys::Object>>,
+    // デフォルトフレームバッファがマルチサンプルで作成されたか
+    msaa: bool,
     #[cfg(feature = "metrics")]
     metrics: crate::metrics::Metrics,
 }
 
 impl ContextInner {
     fn new(gl: Rc<gl>, canvas: HtmlCanvasElement) -> Self {
+        let msaa = detect_msaa(&gl);
         Self {
             gl,
             _canvas: canvas,
+            #[cfg(any(feature = "vertex", feature = "texture"))]
+            khr_debug: OnceCell::new(),
+            msaa,
             #[cfg(feature = "metrics")]
             metrics: crate::metrics::Metrics::default(),
         }
@@ -67,6 +84,57 @@ impl ContextInner {
         &self.metrics
     }
 
+    /// KHR_debug拡張を取得する。未対応の環境ではNone
+    #[cfg(any(feature = "vertex", feature = "texture"))]
+    fn khr_debug(&self) -> Option<&js_sys::Object> {
+        self.khr_debug
+            .get_or_init(|| self.gl.get_extension("KHR_debug").ok().flatten())
+            .as_ref()
+    }
+
+    /// KHR_debugのobjectLabelKHRでオブジェクトにラベルを付ける
+    /// 拡張が無い場合や呼び出しに失敗した場合は何もしない
+    #[cfg(any(feature = "vertex", feature = "texture"))]
+    fn object_label(&self, identifier: GlEnum, object: &JsValue, label: &str) {
+        let Some(ext) = self.khr_debug() else {
+            return;
+        };
+        let Ok(func) = js_sys::Reflect::get(ext, &JsValue::from_str("objectLabelKHR")) else {
+            return;
+        };
+        if let Ok(func) = func.dyn_into::<js_sys::Function>() {
+            let _ = func.call3(
+                ext,
+                &JsValue::from(identifier),
+                object,
+                &JsValue::from_str(label),
+            );
+        }
+    }
+
+    /// 溜まっているGLエラーをすべて取り出してログに出し、最初のエラーコードを返す
+    #[cfg(debug_assertions)]
+    pub(crate) fn check_errors(&self) -> Option<u32> {
+        let mut first = None;
+        loop {
+            let err = self.gl.get_error();
+            if err == gl::NO_ERROR {
+                break;
+            }
+            web_sys::console::warn_1(&format!("GL error: 0x{err:04X}").into());
+            first.get_or_insert(err);
+        }
+        first
+    }
+
+    /// GLエラーがあればpanicする
+    #[cfg(debug_assertions)]
+    pub(crate) fn check_errors_assert(&self) {
+        if let Some(err) = self.check_errors() {
+            panic!("GL error: 0x{err:04X}");
+        }
+    }
+
     #[cfg(feature = "viewport")]
     pub(crate) fn canvas_size(&self) -> (u32, u32) {
         let width = self._canvas.width();
@@ -91,25 +159,158 @@ impl Context {
         })
     }
 
+    /// マルチサンプルアンチエイリアスを指定してコンテキストを取得する
+    ///
+    /// samplesはWebGLのコンテキスト属性に無いブラウザへのヒントで、現状のブラウザは無視する。
+    /// antialiasは[Self::new]でも有効なので、実際の挙動は[Self::new]と変わらない。
+    /// 有効になったかは[Self::has_msaa]で確認する
+    pub fn with_msaa(canvas: HtmlCanvasElement, color: [f32; 4], samples: i32) -> Result<Self> {
+        let option = WebGL2ContextOption {
+            antialias: true,
+            samples: Some(samples),
+            ..WebGL2ContextOption::DEFAULT
+        };
+        let gl = get_context_with_option(&canvas, color, &option)?;
+        Ok(Self {
+            ctx: Rc::new(ContextInner::new(Rc::new(gl), canvas)),
+        })
+    }
+
     /// 生のWebGL2RenderingContextを取得する
     pub fn gl(&self) -> &Rc<gl> {
         self.ctx.gl()
     }
 
+    /// アンチエイリアスが有効なコンテキストか
+    pub fn has_msaa(&self) -> bool {
+        self.ctx.msaa
+    }
+
+    /// 溜まっているGLエラーをすべてconsoleに出し、最初のエラーコードを返す
+    ///
+    /// デバッグビルドのみ
+    #[cfg(debug_assertions)]
+    pub fn check_errors(&self) -> Option<u32> {
+        self.ctx.check_errors()
+    }
+
+    /// GLエラーがあればエラーコードを付けてpanicする。デバッグビルドのみ
+    #[cfg(debug_assertions)]
+    pub fn check_errors_assert(&self) {
+        self.ctx.check_errors_assert();
+    }
+
     pub fn clear(&self, color: [f32; 4]) {
         gl_clear_color(self.ctx.gl(), color);
     }
 
+    /// 描画範囲を制限する矩形を設定する。px単位で左下原点
+    pub fn set_scissor(&self, x: i32, y: i32, width: i32, height: i32) {
+        self.gl().scissor(x, y, width, height);
+    }
+
+    pub fn enable_scissor(&self) {
+        self.gl().enable(gl::SCISSOR_TEST);
+    }
+
+    pub fn disable_scissor(&self) {
+        self.gl().disable(gl::SCISSOR_TEST);
+    }
+
+    /// `(x, y, width, height)`の範囲に制限してfを実行し、終わったら制限を解除する
+    ///
+    /// HUDなどを3D表示の領域に描画しないようにするためのもの
+    pub fn with_scissor<F: FnOnce()>(&self, rect: (i32, i32, i32, i32), f: F) {
+        let (x, y, width, height) = rect;
+        self.set_scissor(x, y, width, height);
+        self.enable_scissor();
+        f();
+        self.disable_scissor();
+    }
+
     /// プログラムを作成する
     pub fn program(&self, vert: &str, frag: &str) -> Result<Program> {
         Program::new(self.ctx.clone(), vert, frag)
     }
+
+    /// DevToolsなどで識別できるようにバッファにラベルを付ける
+    /// KHR_debug拡張が無い場合は何もしない
+    #[cfg(feature = "vertex")]
+    pub fn set_buffer_label(&self, buffer: &web_sys::WebGlBuffer, label: &str) {
+        self.ctx.object_label(BUFFER_KHR, buffer, label);
+    }
+
+    /// DevToolsなどで識別できるようにテクスチャにラベルを付ける
+    /// KHR_debug拡張が無い場合は何もしない
+    #[cfg(feature = "texture")]
+    pub fn set_texture_label(&self, texture: &web_sys::WebGlTexture, label: &str) {
+        self.ctx.object_label(gl::TEXTURE, texture, label);
+    }
+
+    /// 浮動小数点テクスチャに描画するレンダーターゲットを作成する
+    ///
+    /// 描画先にするためにEXT_color_buffer_floatを有効にする
+    #[cfg(feature = "texture")]
+    pub fn create_render_target(&self, res: crate::texture::Resolution) -> Result<RenderTarget> {
+        self.gl()
+            .get_extension("EXT_color_buffer_float")
+            .map_err(|_| JsError::new("Failed to get EXT_color_buffer_float"))?
+            .ok_or(JsError::new("EXT_color_buffer_float is not supported"))?;
+        let fbo = crate::texture::TextureFBO::new_float_vec4(self, res)?;
+        Ok(RenderTarget(fbo))
+    }
+}
+
+/// テクスチャへの描画に必要なフレームバッファとビューポートの切り替えをまとめたもの
+#[cfg(feature = "texture")]
+pub struct RenderTarget(crate::texture::TextureFBO);
+
+#[cfg(feature = "texture")]
+impl RenderTarget {
+    /// 描画先をこのターゲットにしてビューポートを合わせる
+    pub fn begin(&self) {
+        let res = self.0.resolution();
+        self.0.bind();
+        self.gl().viewport(0, 0, res.x as i32, res.y as i32);
+    }
+
+    /// 描画先をデフォルトのフレームバッファに戻し、ビューポートを描画バッファ全体に戻す
+    pub fn end(&self) {
+        self.0.unbind();
+        let gl = self.gl();
+        gl.viewport(0, 0, gl.drawing_buffer_width(), gl.drawing_buffer_height());
+    }
+
+    /// 描画結果のテクスチャ
+    pub fn texture(&self) -> &crate::texture::Texture {
+        self.0.texture()
+    }
+
+    pub fn fbo(&self) -> &crate::texture::TextureFBO {
+        &self.0
+    }
+
+    fn gl(&self) -> &gl {
+        self.0.gl()
+    }
 }
 
+// KHR_debugで定義されるバッファの識別子
+#[cfg(feature = "vertex")]
+const BUFFER_KHR: GlEnum = 0x82E0;
+
 /// Canvas要素からWebGL2RenderingContextを取得する
 pub fn get_context(canvas: &HtmlCanvasElement, color: [f32; 4]) -> Result<gl> {
+    get_context_with_option(canvas, color, &WebGL2ContextOption::DEFAULT)
+}
+
+fn get_context_with_option(
+    canvas: &HtmlCanvasElement,
+    color: [f32; 4],
+    option: &WebGL2ContextOption,
+) -> Result<gl> {
     use wasm_bindgen::JsCast;
-    let options = serde_wasm_bindgen::to_value(&WebGL2ContextOption::DEFAULT)?;
+    let options = serde_wasm_bindgen::to_value(option)?;
 
     let gl = canvas
         .get_context_with_context_options("webgl2", &options)
@@ -134,6 +335,21 @@ pub fn get_context(canvas: &HtmlCanvasElement, color: [f32; 4]) -> Result<gl> {
     Ok(gl)
 }
 
+// 作成されたコンテキストの属性とサンプル数からアンチエイリアスの有無を調べる
+fn detect_msaa(gl: &gl) -> bool {
+    let antialias = gl
+        .get_context_attributes()
+        .and_then(|attr| js_sys::Reflect::get(&attr, &JsValue::from_str("antialias")).ok())
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let samples = gl
+        .get_parameter(gl::SAMPLES)
+        .ok()
+        .and_then(|v| v.as_f64())
+        .unwrap_or(0.0);
+    antialias && samples > 0.0
+}
+
 #[inline]
 pub fn gl_clear_color(gl: &gl, color: [f32; 4]) {
     gl.clear_color(color[0], color[1], color[2], color[3]);
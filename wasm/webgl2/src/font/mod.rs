@@ -98,6 +98,8 @@ void main() {
         vao.bind();
         gl.draw_arrays(gl::TRIANGLES, 0, vao.vertex_size);
         vao.unbind();
+        #[cfg(debug_assertions)]
+        self.program.check_errors_assert();
     }
 }
 
@@ -117,6 +119,11 @@ impl TextVertex {
     pub fn apply_to_vao(&self, vao: &TextVao) {
         self.vertex.update(vao);
     }
+
+    /// 指定した文字の矩形を`(左下, 右上)`で返す。範囲外はNone
+    pub fn glyph_bounds(&self, char_index: usize) -> Option<(GlPoint2d, GlPoint2d)> {
+        self.vertex.glyph_bounds(char_index)
+    }
 }
 
 /// 画面に対して文字列を表示するための頂点情報
@@ -136,6 +143,17 @@ struct TextVertexInner {
 }
 
 impl TextVertexInner {
+    fn glyph_bounds(&self, char_index: usize) -> Option<(GlPoint2d, GlPoint2d)> {
+        if char_index >= self.len {
+            return None;
+        }
+        // 頂点は(x0,y1), (x0,y0), (x1,y1)...の順に並んでいる
+        let vs = self
+            .positions
+            .get(char_index * FontInner::CHAR_VERTEX_COUNT..)?;
+        Some((*vs.get(1)?, *vs.get(2)?))
+    }
+
     // 数字以外の文字列を描画する場合。文字によって位置が変わるのでpositionも変更する
     //
     // TODO: 数字だけなどならuv更新に限定するなど効率化する余地がある
@@ -222,6 +240,29 @@ impl Font {
         let text = " ".repeat(text_len as usize);
         self.text(&text, align)
     }
+
+    /// 切り出し情報
+    pub fn detail(&self) -> &FontTextureDetail {
+        &self.inner.detail
+    }
+}
+
+/// URLからフォントテクスチャと切り出し情報を読み込んで[Font]を作成する
+#[cfg(feature = "loader")]
+pub struct FontBuilder;
+
+#[cfg(feature = "loader")]
+impl FontBuilder {
+    /// テクスチャ画像と切り出し情報のJSONを並行して読み込む
+    pub async fn load(ctx: &Context, texture_url: &str, detail_url: &str) -> Result<Font> {
+        let image = crate::loader::ImageLoader::new(texture_url)?;
+        let detail = crate::loader::fetch_text(detail_url);
+        let (image, detail) = futures_util::join!(image, detail);
+        let detail: FontTextureDetail = serde_json::from_str(&detail?)?;
+        let filter = crate::texture::TextureFilter::default();
+        let texture = ctx.create_texture_image_element(&filter, &image?)?;
+        Ok(Font::new(texture, detail))
+    }
 }
 
 struct FontInner {
@@ -411,6 +452,10 @@ impl FontTextureDetail {
     pub fn height(&self) -> u32 {
         self.height
     }
+    /// 文字の切り出し情報を持っているか
+    pub fn has_char(&self, c: char) -> bool {
+        self.characters.contains_key(&c)
+    }
 }
 
 /// テキスト描画の整列情報
@@ -4,6 +4,7 @@ use std::{
     task::{Context, Poll},
 };
 use wasm_bindgen::prelude::*;
+use wasm_bindgen_futures::JsFuture;
 use web_sys::HtmlImageElement;
 
 use crate::{error::*, texture::Texture};
@@ -63,3 +64,28 @@ pub async fn load_texture(src: impl AsRef<str>, texture: &Texture) -> Result<()>
     texture.update_texture_image_element(&img);
     Ok(())
 }
+
+/// URLの内容を文字列として取得する
+pub async fn fetch_text(url: impl AsRef<str>) -> Result<String> {
+    let url = url.as_ref();
+    let window = web_sys::window().ok_or(JsError::new("window is None"))?;
+    let resp = JsFuture::from(window.fetch_with_str(url))
+        .await
+        .map_err(|_| JsError::new(&format!("Failed to fetch {url}")))?
+        .dyn_into::<web_sys::Response>()
+        .map_err(|_| JsError::new("Failed to convert to Response"))?;
+    if !resp.ok() {
+        return Err(JsError::new(&format!(
+            "Failed to fetch {url}: status {}",
+            resp.status()
+        )));
+    }
+    let text = resp
+        .text()
+        .map_err(|_| JsError::new("Failed to read response body"))?;
+    JsFuture::from(text)
+        .await
+        .map_err(|_| JsError::new("Failed to read response body"))?
+        .as_string()
+        .ok_or(JsError::new("Response body is not a string"))
+}
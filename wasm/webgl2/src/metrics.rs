@@ -96,12 +96,16 @@ impl std::fmt::Display for VertexCount {
 pub struct TextureCount {
     pub texture_count: AtomicU32,
     pub bytes_count: AtomicU64,
+    // 同時に存在したテクスチャの最大値
+    peak_count: AtomicU32,
+    peak_bytes: AtomicU64,
 }
 
 #[cfg(feature = "texture")]
 impl TextureCount {
     pub fn inc_texture(&self, inc: u32) {
-        self.texture_count.fetch_add(inc, Relaxed);
+        let count = self.texture_count.fetch_add(inc, Relaxed) + inc;
+        self.peak_count.fetch_max(count, Relaxed);
     }
 
     pub fn sub_texture(&self, sub: u32) {
@@ -109,12 +113,31 @@ impl TextureCount {
     }
 
     pub fn inc_bytes(&self, inc: u64) {
-        self.bytes_count.fetch_add(inc, Relaxed);
+        let bytes = self.bytes_count.fetch_add(inc, Relaxed) + inc;
+        self.peak_bytes.fetch_max(bytes, Relaxed);
     }
 
     pub fn sub_bytes(&self, sub: u64) {
         self.bytes_count.fetch_sub(sub, Relaxed);
     }
+
+    /// 同時に確保していたテクスチャメモリの最大値
+    pub fn peak_bytes(&self) -> u64 {
+        self.peak_bytes.load(Relaxed)
+    }
+
+    /// 同時に存在したテクスチャ数の最大値
+    pub fn texture_count_peak(&self) -> u64 {
+        self.peak_count.load(Relaxed) as u64
+    }
+
+    /// 最大値を現在の値に戻す
+    pub fn reset_peak(&self) {
+        self.peak_count
+            .store(self.texture_count.load(Relaxed), Relaxed);
+        self.peak_bytes
+            .store(self.bytes_count.load(Relaxed), Relaxed);
+    }
 }
 
 #[cfg(feature = "texture")]
@@ -122,9 +145,11 @@ impl std::fmt::Display for TextureCount {
     fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
         write!(
             f,
-            "Textures: {}, {} B",
+            "Textures: {}, {} B (peak: {}, {} B)",
             self.texture_count.load(Relaxed),
-            self.bytes_count.load(Relaxed)
+            self.bytes_count.load(Relaxed),
+            self.texture_count_peak(),
+            self.peak_bytes()
         )
     }
 }
@@ -135,3 +160,69 @@ impl Context {
         self.ctx.metrics()
     }
 }
+
+/// テクスチャの利用状況とフレーム時間を画面左上に表示するHUD
+///
+/// カメラ行列の影響を受けないよう、Viewport基準の固定サイズで描画する
+#[cfg(all(feature = "font", feature = "viewport"))]
+pub struct GpuMetricsHud {
+    shader: crate::font::TextShader,
+    text: crate::font::TextVertex,
+    vao: crate::font::TextVao,
+    content: String,
+    // 前回updateを呼び出した時刻(ms)
+    last_update: Option<f64>,
+}
+
+#[cfg(all(feature = "font", feature = "viewport"))]
+impl GpuMetricsHud {
+    // 表示する最大文字数
+    const CAPACITY: u32 = 64;
+    // 表示位置と文字の大きさ(px)
+    const POSITION: (i32, i32) = (8, 8);
+    const POINT: f32 = 16.0;
+
+    pub fn new(ctx: &Context, font: &crate::font::Font) -> crate::error::Result<Self> {
+        let shader = crate::font::TextShader::new(ctx)?;
+        let text = font.text_by_capacity(Self::CAPACITY, crate::font::Align::left_top());
+        let vao = shader.create_vbo(&text)?;
+        Ok(Self {
+            shader,
+            text,
+            vao,
+            content: String::new(),
+            last_update: None,
+        })
+    }
+
+    /// 表示内容を最新のメトリクスに更新する
+    pub fn update(&mut self, ctx: &Context) {
+        let now = js_sys::Date::now();
+        let frame_ms = self.last_update.map(|last| now - last).unwrap_or(0.0);
+        self.last_update = Some(now);
+
+        let texture = &ctx.metrics().texture;
+        self.content = format!(
+            "Textures: {}, {} B, {:.1} ms",
+            texture.texture_count.load(Relaxed),
+            texture.bytes_count.load(Relaxed),
+            frame_ms
+        );
+        self.text.update_text(&self.content);
+        self.text.apply_to_vao(&self.vao);
+
+        let (x, y) = Self::POSITION;
+        let mat = ctx.viewport().font_mat(x, y, Self::POINT);
+        self.shader.local_mat(&mat);
+    }
+
+    /// 現在の表示内容
+    pub fn text(&self) -> &str {
+        &self.content
+    }
+
+    /// 描画後のGLエラーはTextShader::drawの中で確認する
+    pub fn draw(&self) {
+        self.shader.draw(&self.vao);
+    }
+}
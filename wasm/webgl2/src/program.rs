@@ -3,17 +3,44 @@
 #[cfg(feature = "context")]
 use std::rc::Rc;
 
+#[cfg(feature = "context")]
+use crate::GlEnum;
+#[cfg(feature = "context")]
+use wasm_bindgen::prelude::wasm_bindgen;
+
 use web_sys::{WebGlProgram, WebGlShader, WebGlUniformLocation};
 
+use wasm_bindgen::JsValue;
+
 use crate::{error::Result, gl, JsError};
 
 /// 2つのコンパイル済みシェーダーを渡してプログラムを作成する
 pub fn link_program(gl: &gl, vertex: &WebGlShader, fragment: &WebGlShader) -> Result<WebGlProgram> {
+    link_program_with_varyings(gl, vertex, fragment, &[])
+}
+
+/// Transform Feedbackで書き出すvaryingを指定してプログラムを作成する
+///
+/// varyingは`SEPARATE_ATTRIBS`で登録するので、出力先はvaryingの順に
+/// `TRANSFORM_FEEDBACK_BUFFER`の各インデックスへ割り当てる
+pub fn link_program_with_varyings(
+    gl: &gl,
+    vertex: &WebGlShader,
+    fragment: &WebGlShader,
+    varyings: &[&str],
+) -> Result<WebGlProgram> {
     let program = gl
         .create_program()
         .ok_or(JsError::new("Failed to create program object"))?;
     gl.attach_shader(&program, vertex);
     gl.attach_shader(&program, fragment);
+    if !varyings.is_empty() {
+        let names = varyings
+            .iter()
+            .map(|v| JsValue::from_str(v))
+            .collect::<js_sys::Array>();
+        gl.transform_feedback_varyings(&program, &names, gl::SEPARATE_ATTRIBS);
+    }
     gl.link_program(&program);
 
     if gl
@@ -85,6 +112,15 @@ impl ShaderType {
 
 // Shaderのコンパイルする
 fn compile_shader(gl: &gl, shader_script: &str, type_: ShaderType) -> Result<WebGlShader> {
+    compile_shader_log(gl, shader_script, type_)?.map_err(|log| JsError::new(&log))
+}
+
+// Shaderをコンパイルし、失敗した場合はコンパイルログを返す
+fn compile_shader_log(
+    gl: &gl,
+    shader_script: &str,
+    type_: ShaderType,
+) -> Result<std::result::Result<WebGlShader, String>> {
     let shader = gl
         .create_shader(type_.to_glenum())
         .ok_or(JsError::new("Failed to create shader object"))?;
@@ -96,23 +132,60 @@ fn compile_shader(gl: &gl, shader_script: &str, type_: ShaderType) -> Result<Web
         .as_bool()
         .unwrap_or(false)
     {
-        Ok(shader)
+        Ok(Ok(shader))
     } else {
         let log = gl
             .get_shader_info_log(&shader)
             .unwrap_or(String::from("Failed to compile shader"));
         gl.delete_shader(Some(&shader));
-        Err(JsError::new(&log))
+        Ok(Err(log))
     }
 }
 
+// コンパイル成功時にも出力される警告などのログを取得する。空の場合はNone
+#[cfg(feature = "context")]
+fn compile_log(gl: &gl, shader: &WebGlShader) -> Option<String> {
+    let log = gl
+        .get_shader_info_log(shader)
+        .filter(|log| !log.trim().is_empty())?;
+    #[cfg(debug_assertions)]
+    web_sys::console::warn_1(&format!("shader compile log: {log}").into());
+    Some(log)
+}
+
+// プログラムの有効な変数名を列挙する
+#[cfg(feature = "context")]
+fn active_names(
+    gl: &gl,
+    program: &WebGlProgram,
+    pname: u32,
+    info: impl Fn(u32) -> Option<web_sys::WebGlActiveInfo>,
+) -> Vec<String> {
+    let count = gl
+        .get_program_parameter(program, pname)
+        .as_f64()
+        .unwrap_or(0.0) as u32;
+    (0..count)
+        .filter_map(info)
+        .map(|i| i.name())
+        .filter(|name| !name.starts_with("gl_"))
+        .collect()
+}
+
 /// WebGLコンテキストに結びついたシェーダープログラムの構造体
+///
+/// JSからはシェーダーの差し替えなど開発用の操作だけを呼び出せる
 #[cfg(feature = "context")]
+#[wasm_bindgen]
 pub struct Program {
     ctx: Rc<crate::context::ContextInner>,
     program: WebGlProgram,
     vertex: WebGlShader,
     fragment: WebGlShader,
+    vertex_log: Option<String>,
+    fragment_log: Option<String>,
+    // Transform Feedbackで書き出すvarying。再リンク時にも同じものを指定する
+    varyings: Vec<String>,
 }
 
 #[cfg(feature = "context")]
@@ -121,23 +194,60 @@ impl Program {
         ctx: Rc<crate::context::ContextInner>,
         vert: &str,
         frag: &str,
+    ) -> Result<Self> {
+        Self::build(ctx, vert, frag, &[])
+    }
+
+    /// Transform Feedbackで書き出すvaryingを指定してプログラムを作成する
+    ///
+    /// 出力先のバッファは`bind_buffer_base(TRANSFORM_FEEDBACK_BUFFER, index, ..)`で
+    /// varyingの順に割り当てる
+    pub fn with_transform_feedback(
+        ctx: &crate::context::Context,
+        vert: &str,
+        frag: &str,
+        varyings: &[&str],
+    ) -> Result<Self> {
+        Self::build(ctx.ctx.clone(), vert, frag, varyings)
+    }
+
+    fn build(
+        ctx: Rc<crate::context::ContextInner>,
+        vert: &str,
+        frag: &str,
+        varyings: &[&str],
     ) -> Result<Self> {
         let gl = ctx.gl();
         let vertex = compile_vertex(gl, vert)?;
         let fragment = compile_fragment(gl, frag)?;
 
         // Link shaders
-        let program = link_program(gl, &vertex, &fragment)?;
+        let program = link_program_with_varyings(gl, &vertex, &fragment, varyings)?;
         #[cfg(feature = "metrics")]
         ctx.metrics().shader.inc_shader(1);
+        let vertex_log = compile_log(gl, &vertex);
+        let fragment_log = compile_log(gl, &fragment);
         Ok(Self {
             ctx,
             program,
             vertex,
             fragment,
+            vertex_log,
+            fragment_log,
+            varyings: varyings.iter().map(|v| v.to_string()).collect(),
         })
     }
 
+    /// Transform Feedbackを開始する。primitiveはPOINTS, LINES, TRIANGLESのいずれか
+    pub fn begin_transform_feedback(&self, primitive: GlEnum) {
+        self.ctx.gl().begin_transform_feedback(primitive);
+    }
+
+    /// Transform Feedbackを終了する
+    pub fn end_transform_feedback(&self) {
+        self.ctx.gl().end_transform_feedback();
+    }
+
     pub(crate) fn ctx(&self) -> Rc<crate::context::ContextInner> {
         self.ctx.clone()
     }
@@ -147,6 +257,14 @@ impl Program {
         self.ctx.gl()
     }
 
+    /// 描画後にGLエラーが無いことを確認し、あればpanicする。デバッグビルドのみ
+    ///
+    /// 各シェーダーのdrawの最後で呼び出す
+    #[cfg(debug_assertions)]
+    pub fn check_errors_assert(&self) {
+        self.ctx.check_errors_assert();
+    }
+
     /// プログラムを有効にする
     pub fn use_program(&self) {
         self.ctx.gl().use_program(Some(&self.program));
@@ -157,6 +275,20 @@ impl Program {
         &self.program
     }
 
+    /// 頂点シェーダーの最後のコンパイルログを取得する。警告が無ければNone
+    ///
+    /// [`Self::recompile`]でコンパイルに失敗した場合はそのエラーログになる
+    pub fn get_vertex_log(&self) -> Option<String> {
+        self.vertex_log.clone()
+    }
+
+    /// フラグメントシェーダーの最後のコンパイルログを取得する。警告が無ければNone
+    ///
+    /// [`Self::recompile`]でコンパイルに失敗した場合はそのエラーログになる
+    pub fn get_fragment_log(&self) -> Option<String> {
+        self.fragment_log.clone()
+    }
+
     /// uniform変数の位置を取得する
     pub fn uniform_location(&self, name: &str) -> Result<WebGlUniformLocation> {
         self.ctx
@@ -167,6 +299,114 @@ impl Program {
                 name
             )))
     }
+
+    /// 有効なuniform変数の名前一覧を取得する。組み込み変数は除く
+    pub fn query_uniform_names(&self) -> Vec<String> {
+        let gl = self.ctx.gl();
+        active_names(gl, &self.program, gl::ACTIVE_UNIFORMS, |i| {
+            gl.get_active_uniform(&self.program, i)
+        })
+    }
+
+    /// 有効なattribute変数の名前一覧を取得する。組み込み変数は除く
+    pub fn query_active_attributes(&self) -> Vec<String> {
+        let gl = self.ctx.gl();
+        active_names(gl, &self.program, gl::ACTIVE_ATTRIBUTES, |i| {
+            gl.get_active_attrib(&self.program, i)
+        })
+    }
+
+    /// 有効なuniformブロックに列挙順で0から連番のバインディングを割り当てる
+    ///
+    /// 割り当てたブロックの数を返す
+    pub fn auto_bind_uniform_blocks(&self) -> Result<u32> {
+        let gl = self.ctx.gl();
+        let count = gl
+            .get_program_parameter(&self.program, gl::ACTIVE_UNIFORM_BLOCKS)
+            .as_f64()
+            .unwrap_or(0.0) as u32;
+        for i in 0..count {
+            let name = gl
+                .get_active_uniform_block_name(&self.program, i)
+                .ok_or(JsError::new(&format!(
+                    "Failed to get uniform block name {}",
+                    i
+                )))?;
+            uniform_block_binding(gl, &self.program, &name, i);
+        }
+        Ok(count)
+    }
+
+    /// シェーダーを再コンパイルしてプログラムを差し替える
+    ///
+    /// 失敗した場合は元のプログラムを維持してエラーを返す。
+    /// Transform Feedbackのvaryingは作成時と同じものを引き継ぐ。
+    /// uniformの位置はプログラム毎に異なるので、成功後に取得し直すこと
+    pub fn recompile(&mut self, vert: &str, frag: &str) -> Result<()> {
+        let gl = self.ctx.gl().clone();
+        // 失敗した場合もコンパイルログを残し、JSのエディタなどから参照できるようにする
+        let vertex = match compile_shader_log(&gl, vert, ShaderType::Vertex)? {
+            Ok(vertex) => vertex,
+            Err(log) => {
+                self.vertex_log = Some(log.clone());
+                return Err(JsError::new(&log));
+            }
+        };
+        let fragment = match compile_shader_log(&gl, frag, ShaderType::Fragment)? {
+            Ok(fragment) => fragment,
+            Err(log) => {
+                gl.delete_shader(Some(&vertex));
+                self.fragment_log = Some(log.clone());
+                return Err(JsError::new(&log));
+            }
+        };
+        let varyings = self.varyings.iter().map(String::as_str).collect::<Vec<_>>();
+        let program = match link_program_with_varyings(&gl, &vertex, &fragment, &varyings) {
+            Ok(program) => program,
+            Err(e) => {
+                gl.delete_shader(Some(&vertex));
+                gl.delete_shader(Some(&fragment));
+                return Err(e);
+            }
+        };
+
+        self.vertex_log = compile_log(&gl, &vertex);
+        self.fragment_log = compile_log(&gl, &fragment);
+        let old_program = std::mem::replace(&mut self.program, program);
+        let old_vertex = std::mem::replace(&mut self.vertex, vertex);
+        let old_fragment = std::mem::replace(&mut self.fragment, fragment);
+        gl.delete_program(Some(&old_program));
+        gl.delete_shader(Some(&old_vertex));
+        gl.delete_shader(Some(&old_fragment));
+        Ok(())
+    }
+}
+
+#[cfg(feature = "context")]
+#[wasm_bindgen]
+impl Program {
+    /// JSから受け取った文字列で再コンパイルする
+    ///
+    /// textareaで編集したGLSLをページを再読み込みせずに反映するためのもの
+    pub fn try_recompile(
+        &mut self,
+        vert: JsValue,
+        frag: JsValue,
+    ) -> std::result::Result<(), JsValue> {
+        let vert = vert.as_string().ok_or("vertex shader must be a string")?;
+        let frag = frag.as_string().ok_or("fragment shader must be a string")?;
+        self.recompile(&vert, &frag).map_err(JsValue::from)
+    }
+
+    /// uniform変数の名前一覧をJSの配列で取得する
+    ///
+    /// JS側で汎用のuniformエディタを組み立てるためのもの
+    pub fn uniform_names_array(&self) -> js_sys::Array {
+        self.query_uniform_names()
+            .into_iter()
+            .map(JsValue::from)
+            .collect()
+    }
 }
 
 #[cfg(feature = "context")]
@@ -1,4 +1,10 @@
+#[cfg(feature = "texture")]
+pub mod outline;
 #[cfg(feature = "pointing")]
 pub mod pointing;
 #[cfg(feature = "texture")]
+pub mod postprocess;
+#[cfg(feature = "texture")]
+pub mod skybox;
+#[cfg(feature = "texture")]
 pub mod texture;
@@ -0,0 +1,159 @@
+//! 描画済みの物体の輪郭を描くためのシェーダー
+//!
+//! 物体をテクスチャに描画しておき、その周囲のピクセルを輪郭色で塗る
+
+use web_sys::WebGlUniformLocation;
+
+use crate::{
+    context::Context,
+    error::Result,
+    gl,
+    program::Program,
+    texture::{Texture, TextureFBO},
+    vertex::{Vao, VaoDefine},
+    GlPoint, GlPoint2d,
+};
+
+/// 輪郭を描画するシェーダー
+///
+/// アルファ値が0より大きいピクセルを前景とし、
+/// 背景のピクセルのうち`thickness`以内に前景があるものを輪郭色で塗る
+pub struct OutlineShader {
+    program: Program,
+    color: WebGlUniformLocation,
+    thickness: WebGlUniformLocation,
+    vao: Vao<OutlineVd>,
+    source: Texture,
+}
+
+impl OutlineShader {
+    const VERT: &'static str = r#"#version 300 es
+
+layout(location = 0) in vec2 position;
+
+out vec2 tex_coord;
+
+void main() {
+    gl_Position = vec4(position, 0.0, 1.0);
+    tex_coord = position * 0.5 + 0.5;
+}
+"#;
+
+    const FRAG: &'static str = r#"#version 300 es
+
+precision mediump float;
+uniform sampler2D u_texture;
+uniform vec4 u_color;
+uniform int u_thickness;
+in vec2 tex_coord;
+
+out vec4 fragmentColor;
+
+bool foreground(ivec2 p, ivec2 size) {
+    return texelFetch(u_texture, clamp(p, ivec2(0), size - 1), 0).a > 0.0;
+}
+
+void main() {
+    ivec2 size = textureSize(u_texture, 0);
+    ivec2 center = ivec2(tex_coord * vec2(size));
+    if (foreground(center, size)) {
+        discard;
+    }
+    for (int y = -u_thickness; y <= u_thickness; y++) {
+        for (int x = -u_thickness; x <= u_thickness; x++) {
+            if (foreground(center + ivec2(x, y), size)) {
+                fragmentColor = u_color;
+                return;
+            }
+        }
+    }
+    discard;
+}
+"#;
+
+    // 画面全体を覆う矩形
+    const QUAD: [GlPoint2d; 4] = [
+        GlPoint2d::new(-1.0, -1.0),
+        GlPoint2d::new(1.0, -1.0),
+        GlPoint2d::new(-1.0, 1.0),
+        GlPoint2d::new(1.0, 1.0),
+    ];
+
+    pub fn new(
+        ctx: &Context,
+        source_fbo: &TextureFBO,
+        color: [f32; 4],
+        thickness: u32,
+    ) -> Result<Self> {
+        let program = ctx.program(Self::VERT, Self::FRAG)?;
+        program.use_program();
+        let gl = program.gl();
+        gl.uniform1i(Some(&program.uniform_location("u_texture")?), 0);
+        let color_loc = program.uniform_location("u_color")?;
+        let thickness_loc = program.uniform_location("u_thickness")?;
+        let mut vao = program.create_vao()?;
+        vao.buffer_data(OutlineVd::Position, &Self::QUAD, gl::STATIC_DRAW);
+        vao.unbind();
+
+        let s = Self {
+            program,
+            color: color_loc,
+            thickness: thickness_loc,
+            vao,
+            source: source_fbo.texture().clone(),
+        };
+        s.set_color(color);
+        s.set_thickness(thickness);
+        Ok(s)
+    }
+
+    pub fn set_color(&self, color: [f32; 4]) {
+        self.program.use_program();
+        self.program
+            .gl()
+            .uniform4fv_with_f32_array(Some(&self.color), &color);
+    }
+
+    /// 輪郭の太さをピクセル単位で設定する
+    pub fn set_thickness(&self, thickness: u32) {
+        self.program.use_program();
+        self.program
+            .gl()
+            .uniform1i(Some(&self.thickness), thickness as i32);
+    }
+
+    pub fn draw(&self) {
+        self.program.use_program();
+        let gl = self.program.gl();
+        gl.active_texture(gl::TEXTURE0);
+        self.source.bind();
+        self.vao.bind();
+        gl.draw_arrays(gl::TRIANGLE_STRIP, 0, Self::QUAD.len() as i32);
+        #[cfg(debug_assertions)]
+        self.program.check_errors_assert();
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum OutlineVd {
+    Position,
+}
+
+impl VaoDefine for OutlineVd {
+    fn name(&self) -> &'static str {
+        match self {
+            OutlineVd::Position => "position",
+        }
+    }
+
+    fn iter() -> std::slice::Iter<'static, Self> {
+        static VD: [OutlineVd; 1] = [OutlineVd::Position];
+        VD.iter()
+    }
+
+    fn size_of(&self) -> i32 {
+        match self {
+            OutlineVd::Position => GlPoint2d::size(),
+        }
+    }
+}
@@ -1,6 +1,6 @@
 //! 画面上の任意の点を指し示すポインタを描画するためのシェーダー
 
-use std::rc::Rc;
+use std::{cell::Cell, rc::Rc};
 
 use crate::error::Result;
 use crate::{
@@ -10,7 +10,8 @@ use crate::{
     vertex::{Vao, VaoDefine},
     GlPoint2d,
 };
-use web_sys::WebGlUniformLocation;
+use wasm_bindgen::prelude::*;
+use web_sys::{js_sys, WebGlUniformLocation};
 
 /// 表示切り替えに関する指示
 pub enum PointingRequest {
@@ -18,14 +19,18 @@ pub enum PointingRequest {
     Enable(bool),
     /// 表示位置
     Position(GlPoint2d),
+    /// タッチ位置。最大[PointingShader::MAX_TOUCH_POINTS]点まで円で表示する
+    Positions(Vec<GlPoint2d>),
 }
 
+#[wasm_bindgen]
 pub struct PointingShader {
     prog: Program,
     uniform: PointingUniform,
     params: PointingParams,
     vao: Vao<PointingVd>,
     vertex_count: i32,
+    touch: TouchShader,
 }
 
 impl PointingShader {
@@ -58,7 +63,11 @@ void main() {
         GlPoint2d { x: 0.0, y: 2.0 },
     ];
 
+    /// 同時に表示するタッチ位置の最大数
+    pub const MAX_TOUCH_POINTS: usize = 10;
+
     pub fn new(ctx: &Context) -> Result<Self> {
+        let touch = TouchShader::new(ctx)?;
         let prog = ctx.program(Self::VERT, Self::FRAG)?;
         prog.use_program();
         let uniform = PointingUniform::new(&prog)?;
@@ -74,6 +83,7 @@ void main() {
             params,
             vao,
             vertex_count: Self::CROSS_VERTEX.len() as i32,
+            touch,
         })
     }
 
@@ -91,6 +101,9 @@ void main() {
                 PointingRequest::Position(pos) => {
                     last_pos = Some(*pos);
                 }
+                PointingRequest::Positions(positions) => {
+                    self.touch.set_positions(positions);
+                }
             }
         }
         if self.params.showing {
@@ -112,6 +125,154 @@ void main() {
         gl.line_width(self.params.line_width);
         self.vao.bind();
         gl.draw_arrays(gl::LINES, 0, self.vertex_count);
+        self.touch.draw(self.params.alpha);
+        #[cfg(debug_assertions)]
+        self.prog.check_errors_assert();
+    }
+
+    /// 表示中のタッチ位置の数
+    pub fn touch_count(&self) -> usize {
+        self.touch.count
+    }
+
+    /// ポインタとタッチ位置の円の色を設定する
+    pub fn set_color(&self, color: [f32; 4]) {
+        self.prog.use_program();
+        self.uniform.set_color(color);
+        self.touch.set_color(color);
+    }
+}
+
+#[wasm_bindgen]
+impl PointingShader {
+    /// タッチ位置を`[x0, y0, x1, y1, ...]`の形式で設定する
+    pub fn set_touch_positions(&mut self, positions: js_sys::Float32Array) {
+        let positions = positions
+            .to_vec()
+            .chunks_exact(2)
+            .map(|p| GlPoint2d::new(p[0], p[1]))
+            .collect();
+        self.apply_requests(&[PointingRequest::Positions(positions)]);
+    }
+
+    /// タッチ位置の円の半径をGL空間の単位で設定する。0以下の場合は表示しない
+    pub fn set_radius(&self, radius: f32) {
+        self.touch.set_radius(radius);
+    }
+
+    /// [Self::set_color]をJSから呼ぶためのもの
+    pub fn set_color_rgba(&self, r: f32, g: f32, b: f32, a: f32) {
+        self.set_color([r, g, b, a]);
+    }
+}
+
+/// タッチ位置を円で表示するシェーダー
+///
+/// 円の頂点を共有し、中心をインスタンス毎に与えて描画する
+struct TouchShader {
+    prog: Program,
+    color: WebGlUniformLocation,
+    alpha: WebGlUniformLocation,
+    u_radius: WebGlUniformLocation,
+    radius: Cell<f32>,
+    vao: Vao<TouchVd>,
+    count: usize,
+}
+
+impl TouchShader {
+    const VERT: &'static str = r#"#version 300 es
+layout(location = 0) in vec2 position;
+// 円の中心
+layout(location = 1) in vec2 instance;
+uniform float u_radius;
+
+void main() {
+    vec2 pos = instance + position * u_radius;
+    gl_Position = vec4(pos, 0.0, 1.0);
+}
+"#;
+
+    // 円の分割数
+    const SEGMENTS: usize = 32;
+    // タッチ位置の円の半径の初期値
+    const RADIUS: f32 = 0.05;
+
+    fn new(ctx: &Context) -> Result<Self> {
+        let prog = ctx.program(Self::VERT, PointingShader::FRAG)?;
+        prog.use_program();
+        let color = prog.uniform_location("color")?;
+        let alpha = prog.uniform_location("alpha")?;
+        let u_radius = prog.uniform_location("u_radius")?;
+        let gl = prog.gl();
+        gl.uniform4fv_with_f32_array(Some(&color), &[1.0, 1.0, 1.0, 1.0]);
+        gl.uniform1f(Some(&alpha), 0.0);
+        gl.uniform1f(Some(&u_radius), Self::RADIUS);
+
+        let circle = (0..Self::SEGMENTS)
+            .map(|i| {
+                let t = i as f32 / Self::SEGMENTS as f32 * std::f32::consts::TAU;
+                GlPoint2d::new(t.cos(), t.sin())
+            })
+            .collect::<Vec<_>>();
+        let mut vao = prog.create_vao()?;
+        vao.buffer_data(TouchVd::Position, &circle, gl::STATIC_DRAW);
+        let instances = [GlPoint2d::new(0.0, 0.0); PointingShader::MAX_TOUCH_POINTS];
+        vao.buffer_data(TouchVd::Instance, &instances, gl::DYNAMIC_DRAW);
+
+        Ok(Self {
+            prog,
+            color,
+            alpha,
+            u_radius,
+            radius: Cell::new(Self::RADIUS),
+            vao,
+            count: 0,
+        })
+    }
+
+    fn set_radius(&self, radius: f32) {
+        self.radius.set(radius);
+        self.prog.use_program();
+        self.prog.gl().uniform1f(Some(&self.u_radius), radius);
+    }
+
+    fn set_color(&self, color: [f32; 4]) {
+        self.prog.use_program();
+        self.prog
+            .gl()
+            .uniform4fv_with_f32_array(Some(&self.color), &color);
+    }
+
+    // 半径が0以下の円は点に潰れても描画されないように透明にする
+    fn masked_alpha(&self, alpha: f32) -> f32 {
+        if self.radius.get() > 0.0 {
+            alpha
+        } else {
+            0.0
+        }
+    }
+
+    fn set_positions(&mut self, positions: &[GlPoint2d]) {
+        let instances = positions
+            .iter()
+            .take(PointingShader::MAX_TOUCH_POINTS)
+            .copied()
+            .collect::<Vec<_>>();
+        self.count = instances.len();
+        if !instances.is_empty() {
+            self.vao.buffer_sub_data(TouchVd::Instance, &instances, 0);
+        }
+    }
+
+    fn draw(&self, alpha: f32) {
+        if self.count == 0 {
+            return;
+        }
+        self.prog.use_program();
+        let gl = self.prog.gl();
+        gl.uniform1f(Some(&self.alpha), self.masked_alpha(alpha));
+        self.vao.bind();
+        gl.draw_arrays_instanced(gl::LINE_LOOP, 0, Self::SEGMENTS as i32, self.count as i32);
     }
 }
 
@@ -229,3 +390,87 @@ impl VaoDefine for PointingVd {
         VD.iter()
     }
 }
+
+#[derive(Debug, PartialEq)]
+enum TouchVd {
+    Position,
+    Instance,
+}
+
+impl VaoDefine for TouchVd {
+    fn name(&self) -> &'static str {
+        match self {
+            TouchVd::Position => "position",
+            TouchVd::Instance => "instance",
+        }
+    }
+
+    fn size_of(&self) -> i32 {
+        use crate::GlPoint;
+        match self {
+            TouchVd::Position => GlPoint2d::size(),
+            TouchVd::Instance => GlPoint2d::size(),
+        }
+    }
+
+    // 中心はインスタンス毎に進める
+    fn is_instanced(&self) -> bool {
+        matches!(self, TouchVd::Instance)
+    }
+
+    fn iter() -> std::slice::Iter<'static, Self> {
+        static VD: [TouchVd; 2] = [TouchVd::Position, TouchVd::Instance];
+        VD.iter()
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    fn uniform_f32(ctx: &Context, touch: &TouchShader, loc: &WebGlUniformLocation) -> f32 {
+        ctx.gl()
+            .get_uniform(touch.prog.program(), loc)
+            .as_f64()
+            .unwrap() as f32
+    }
+
+    #[wasm_bindgen_test]
+    fn test_touch_radius() {
+        let canvas = web_sys::window()
+            .unwrap()
+            .document()
+            .unwrap()
+            .create_element("canvas")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .unwrap();
+        let ctx = Context::new(canvas, crate::context::COLOR_BLACK).unwrap();
+        let mut shader = PointingShader::new(&ctx).unwrap();
+        let touch = &shader.touch;
+        assert_eq!(
+            uniform_f32(&ctx, touch, &touch.u_radius),
+            TouchShader::RADIUS
+        );
+
+        shader.set_radius(0.1);
+        let touch = &shader.touch;
+        assert_eq!(uniform_f32(&ctx, touch, &touch.u_radius), 0.1);
+
+        // 半径0では表示中でも透明になる
+        shader.set_radius(0.0);
+        shader.apply_requests(&[
+            PointingRequest::Enable(true),
+            PointingRequest::Positions(vec![GlPoint2d::new(0.0, 0.0)]),
+        ]);
+        shader.update(0.016);
+        shader.draw();
+        let touch = &shader.touch;
+        assert_eq!(uniform_f32(&ctx, touch, &touch.alpha), 0.0);
+    }
+}
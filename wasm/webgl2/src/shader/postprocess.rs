@@ -0,0 +1,120 @@
+//! 画面全体に効果をかけるためのシェーダー
+//!
+//! シーンをテクスチャに描画してから、全画面の矩形でそのテクスチャを読み直して効果をかける
+
+use crate::{
+    context::Context,
+    error::Result,
+    gl,
+    program::Program,
+    texture::Texture,
+    vertex::{Vao, VaoDefine},
+    GlPoint, GlPoint2d,
+};
+
+/// 全画面の矩形にテクスチャを描画するシェーダー
+pub struct PostProcessShader {
+    program: Program,
+    vao: Vao<PostProcessVd>,
+}
+
+impl PostProcessShader {
+    const VERT: &'static str = r#"#version 300 es
+
+layout(location = 0) in vec2 position;
+
+out vec2 tex_coord;
+
+void main() {
+    gl_Position = vec4(position, 0.0, 1.0);
+    tex_coord = position * 0.5 + 0.5;
+}
+"#;
+
+    /// 入力をそのまま出力するフラグメントシェーダー
+    ///
+    /// 独自の効果を作る場合も`u_texture`と`tex_coord`を同じ名前で受け取る
+    pub const PASS_THROUGH_FRAG: &'static str = r#"#version 300 es
+
+precision mediump float;
+uniform sampler2D u_texture;
+in vec2 tex_coord;
+
+out vec4 fragmentColor;
+
+void main() {
+    fragmentColor = texture(u_texture, tex_coord);
+}
+"#;
+
+    // 画面全体を覆う矩形
+    const QUAD: [GlPoint2d; 4] = [
+        GlPoint2d::new(-1.0, -1.0),
+        GlPoint2d::new(1.0, -1.0),
+        GlPoint2d::new(-1.0, 1.0),
+        GlPoint2d::new(1.0, 1.0),
+    ];
+
+    /// 独自のフラグメントシェーダーで効果をかける
+    pub fn with_frag(ctx: &Context, frag_glsl: &str) -> Result<Self> {
+        let program = ctx.program(Self::VERT, frag_glsl)?;
+        program.use_program();
+        let gl = program.gl();
+        // 効果によってはテクスチャを使わないこともある
+        if let Some(texture) = gl.get_uniform_location(program.program(), "u_texture") {
+            gl.uniform1i(Some(&texture), 0);
+        }
+
+        let mut vao = program.create_vao()?;
+        vao.buffer_data(PostProcessVd::Position, &Self::QUAD, gl::STATIC_DRAW);
+        vao.unbind();
+        Ok(Self { program, vao })
+    }
+
+    /// 入力をそのまま描画する
+    pub fn pass_through(ctx: &Context) -> Result<Self> {
+        Self::with_frag(ctx, Self::PASS_THROUGH_FRAG)
+    }
+
+    /// 独自のuniformを設定するためにプログラムを取得する
+    pub fn program(&self) -> &Program {
+        &self.program
+    }
+
+    /// sourceを入力にして現在のフレームバッファ全体に描画する
+    pub fn draw(&self, source: &Texture) {
+        self.program.use_program();
+        let gl = self.program.gl();
+        gl.active_texture(gl::TEXTURE0);
+        gl.bind_texture(gl::TEXTURE_2D, Some(source.texture()));
+        self.vao.bind();
+        gl.draw_arrays(gl::TRIANGLE_STRIP, 0, Self::QUAD.len() as i32);
+        self.vao.unbind();
+        #[cfg(debug_assertions)]
+        self.program.check_errors_assert();
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum PostProcessVd {
+    Position,
+}
+
+impl VaoDefine for PostProcessVd {
+    fn name(&self) -> &'static str {
+        match self {
+            PostProcessVd::Position => "position",
+        }
+    }
+
+    fn iter() -> std::slice::Iter<'static, Self> {
+        static VD: [PostProcessVd; 1] = [PostProcessVd::Position];
+        VD.iter()
+    }
+
+    fn size_of(&self) -> i32 {
+        match self {
+            PostProcessVd::Position => GlPoint2d::size(),
+        }
+    }
+}
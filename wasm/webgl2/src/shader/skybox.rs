@@ -0,0 +1,154 @@
+//! キューブマップを背景として描画するためのシェーダー
+//!
+//! 深度を常に最奥の1.0にするので、他の物体を描画した後に描いても手前を上書きしない
+
+use web_sys::WebGlUniformLocation;
+
+use crate::{
+    context::Context,
+    error::Result,
+    gl,
+    program::Program,
+    texture::TextureCube,
+    vertex::{Vao, VaoDefine},
+    GlPoint, GlPoint3d,
+};
+
+/// スカイボックスを描画するシェーダー
+pub struct SkyboxShader {
+    program: Program,
+    view: WebGlUniformLocation,
+    projection: WebGlUniformLocation,
+    vao: Vao<SkyboxVd>,
+    vertex_len: i32,
+    texture: TextureCube,
+}
+
+impl SkyboxShader {
+    const VERT: &'static str = r#"#version 300 es
+
+layout(location = 0) in vec3 position;
+
+// カメラの回転のみ。移動しても背景が動かないように平行移動は除く
+uniform mat3 u_view;
+uniform mat4 u_projection;
+
+out vec3 tex_coord;
+
+void main() {
+    vec4 pos = u_projection * vec4(u_view * position, 1.0);
+    // z = wとして深度を常に1.0にする
+    gl_Position = pos.xyww;
+    tex_coord = position;
+}
+"#;
+
+    const FRAG: &'static str = r#"#version 300 es
+
+precision mediump float;
+uniform samplerCube u_texture;
+in vec3 tex_coord;
+
+out vec4 fragmentColor;
+
+void main() {
+    fragmentColor = texture(u_texture, tex_coord);
+}
+"#;
+
+    // 一辺2の立方体の頂点
+    const CORNERS: [GlPoint3d; 8] = [
+        GlPoint3d::new(-1.0, -1.0, -1.0),
+        GlPoint3d::new(1.0, -1.0, -1.0),
+        GlPoint3d::new(-1.0, 1.0, -1.0),
+        GlPoint3d::new(1.0, 1.0, -1.0),
+        GlPoint3d::new(-1.0, -1.0, 1.0),
+        GlPoint3d::new(1.0, -1.0, 1.0),
+        GlPoint3d::new(-1.0, 1.0, 1.0),
+        GlPoint3d::new(1.0, 1.0, 1.0),
+    ];
+
+    // 6面を2つの三角形に分割したCORNERSのインデックス
+    const FACES: [usize; 36] = [
+        1, 5, 3, 3, 5, 7, // +X
+        4, 0, 6, 6, 0, 2, // -X
+        2, 3, 6, 6, 3, 7, // +Y
+        4, 5, 0, 0, 5, 1, // -Y
+        5, 4, 7, 7, 4, 6, // +Z
+        0, 1, 2, 2, 1, 3, // -Z
+    ];
+
+    pub fn new(ctx: &Context, cube_texture: TextureCube) -> Result<Self> {
+        let program = ctx.program(Self::VERT, Self::FRAG)?;
+        program.use_program();
+        let gl = program.gl();
+        gl.uniform1i(Some(&program.uniform_location("u_texture")?), 0);
+        let view = program.uniform_location("u_view")?;
+        let projection = program.uniform_location("u_projection")?;
+
+        let vertex = Self::FACES.map(|i| Self::CORNERS[i]);
+        let mut vao = program.create_vao()?;
+        vao.buffer_data(SkyboxVd::Position, &vertex, gl::STATIC_DRAW);
+        vao.unbind();
+
+        let s = Self {
+            program,
+            view,
+            projection,
+            vao,
+            vertex_len: vertex.len() as i32,
+            texture: cube_texture,
+        };
+        s.set_projection(nalgebra::Matrix4::identity());
+        Ok(s)
+    }
+
+    /// 透視投影の行列を設定する
+    pub fn set_projection(&self, projection: nalgebra::Matrix4<f32>) {
+        self.program.use_program();
+        self.program.gl().uniform_matrix4fv_with_f32_array(
+            Some(&self.projection),
+            false,
+            projection.as_slice(),
+        );
+    }
+
+    /// カメラの行列から回転成分だけを取り出して背景を描画する
+    pub fn draw(&self, camera_mat: nalgebra::Matrix4<f32>) {
+        self.program.use_program();
+        let gl = self.program.gl();
+        let rotation = camera_mat.fixed_view::<3, 3>(0, 0).into_owned();
+        gl.uniform_matrix3fv_with_f32_array(Some(&self.view), false, rotation.as_slice());
+        gl.active_texture(gl::TEXTURE0);
+        self.texture.bind_cube();
+        self.vao.bind();
+        gl.draw_arrays(gl::TRIANGLES, 0, self.vertex_len);
+        self.vao.unbind();
+        #[cfg(debug_assertions)]
+        self.program.check_errors_assert();
+    }
+}
+
+#[derive(Debug, PartialEq)]
+enum SkyboxVd {
+    Position,
+}
+
+impl VaoDefine for SkyboxVd {
+    fn name(&self) -> &'static str {
+        match self {
+            SkyboxVd::Position => "position",
+        }
+    }
+
+    fn iter() -> std::slice::Iter<'static, Self> {
+        static VD: [SkyboxVd; 1] = [SkyboxVd::Position];
+        VD.iter()
+    }
+
+    fn size_of(&self) -> i32 {
+        match self {
+            SkyboxVd::Position => GlPoint3d::size(),
+        }
+    }
+}
@@ -26,12 +26,14 @@ layout(location = 0) in vec2 position;
 layout(location = 1) in vec2 coord;
 
 uniform mat3 local_mat;
+uniform vec2 u_uv_offset;
+uniform vec2 u_uv_scale;
 
 out vec2 tex_coord;
 
 void main() {
     gl_Position = vec4((local_mat * vec3(position, 1.0)).xy, 0.0, 1.0);
-    tex_coord = coord;
+    tex_coord = coord * u_uv_scale + u_uv_offset;
 }
 "#;
 
@@ -59,6 +61,12 @@ void main() {
         &self.uniform
     }
 
+    /// UV座標を`coord * scale + offset`に変換する。タイリングやアトラスの切り出しに使う
+    pub fn set_uv_transform(&self, offset: GlPoint2d, scale: GlPoint2d) {
+        self.program.use_program();
+        self.uniform.set_uv_transform(offset, scale);
+    }
+
     pub fn create_vao(&self, vert: &[GlPoint2d; 4]) -> Result<Vao<TextureVd>> {
         let mut vao = self.program.create_vao()?;
         vao.buffer_data(TextureVd::Position, vert, gl::STATIC_DRAW);
@@ -74,6 +82,8 @@ void main() {
         gl.bind_texture(gl::TEXTURE_2D, Some(texture));
         vao.bind();
         gl.draw_arrays(gl::TRIANGLE_STRIP, 0, 4);
+        #[cfg(debug_assertions)]
+        self.program.check_errors_assert();
     }
 }
 
@@ -81,23 +91,30 @@ pub struct TextureUniform {
     gl: Rc<gl>,
     local_mat: WebGlUniformLocation,
     texture: WebGlUniformLocation,
+    uv_offset: WebGlUniformLocation,
+    uv_scale: WebGlUniformLocation,
 }
 
 impl TextureUniform {
     pub fn new(program: &Program) -> Result<Self> {
         let local_mat = program.uniform_location("local_mat")?;
         let texture = program.uniform_location("u_texture")?;
+        let uv_offset = program.uniform_location("u_uv_offset")?;
+        let uv_scale = program.uniform_location("u_uv_scale")?;
 
         Ok(Self {
             gl: program.gl().clone(),
             local_mat,
             texture,
+            uv_offset,
+            uv_scale,
         })
     }
 
     pub fn init(&self) {
         self.set_mat(nalgebra::Matrix3::identity());
         self.set_texture(0);
+        self.set_uv_transform(GlPoint2d::new(0.0, 0.0), GlPoint2d::new(1.0, 1.0));
     }
 
     pub fn set_mat(&self, mat: nalgebra::Matrix3<f32>) {
@@ -108,6 +125,11 @@ impl TextureUniform {
     pub fn set_texture(&self, texture: i32) {
         self.gl.uniform1i(Some(&self.texture), texture);
     }
+
+    pub fn set_uv_transform(&self, offset: GlPoint2d, scale: GlPoint2d) {
+        self.gl.uniform2f(Some(&self.uv_offset), offset.x, offset.y);
+        self.gl.uniform2f(Some(&self.uv_scale), scale.x, scale.y);
+    }
 }
 
 #[derive(Debug, PartialEq)]
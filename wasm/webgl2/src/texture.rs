@@ -1,11 +1,11 @@
 //! テクスチャの作成と管理を行うモジュールです。
 
-use std::{rc::Rc, sync::atomic::AtomicU64, sync::atomic::Ordering::Relaxed};
+use std::{cell::Cell, rc::Rc, sync::atomic::AtomicU64, sync::atomic::Ordering::Relaxed};
 
 use wasm_bindgen::JsError;
 use web_sys::WebGlTexture;
 
-use crate::{error::Result, gl};
+use crate::{error::Result, gl, GlEnum};
 
 /// テクスチャの設定
 pub struct Texture2dConfig {
@@ -41,6 +41,20 @@ impl Texture2dConfig {
         }
     }
 
+    /// 読み込み済みの画像要素の大きさからRGBAの設定を作成する
+    pub fn from_image_element(element: &web_sys::HtmlImageElement) -> Self {
+        Self::new_rgba(
+            element.natural_width() as i32,
+            element.natural_height() as i32,
+        )
+    }
+
+    /// フィルター設定を差し替える
+    pub fn with_filter(mut self, filter: TextureFilter) -> Self {
+        self.filter = filter;
+        self
+    }
+
     pub fn create_from_byte(&self, gl: &gl, body: Option<&[u8]>) -> Result<WebGlTexture> {
         let texture = create_texture_inner(gl)?;
         gl.bind_texture(gl::TEXTURE_2D, Some(&texture));
@@ -207,12 +221,24 @@ impl crate::context::Context {
     ) -> Result<Texture> {
         Texture::new_from_image_element(self.ctx.clone(), filter, element)
     }
+
+    /// 32bit浮動小数点のRGテクスチャを作成する
+    ///
+    /// dataはテクセル毎に2つの値を並べた`res.x * res.y * 2`個の配列を渡す
+    pub fn create_float_vec2_texture(&self, res: Resolution, data: &[f32]) -> Result<Texture> {
+        Texture::new_float_vec2(self.ctx.clone(), res, data)
+    }
 }
 
 #[cfg(feature = "context")]
 struct TextureInner {
     ctx: Rc<crate::context::ContextInner>,
     texture: Rc<WebGlTexture>,
+    // 部分更新時に渡す画像のフォーマットと型
+    format: GlEnum,
+    type_: GlEnum,
+    // テクスチャの大きさ。部分更新の範囲チェックに使う
+    size: Cell<(i32, i32)>,
     bytes: AtomicU64,
 }
 
@@ -221,6 +247,9 @@ impl TextureInner {
     fn new(
         ctx: Rc<crate::context::ContextInner>,
         texture: WebGlTexture,
+        format: GlEnum,
+        type_: GlEnum,
+        size: (i32, i32),
         bytes: u64,
     ) -> Result<Self> {
         let texture = Rc::new(texture);
@@ -234,6 +263,9 @@ impl TextureInner {
         Ok(Self {
             ctx,
             texture,
+            format,
+            type_,
+            size: Cell::new(size),
             bytes,
         })
     }
@@ -283,7 +315,14 @@ impl Texture {
     ) -> Result<Self> {
         let texture = create_texture(ctx.gl(), config, body)?;
         let bytes = config.bytes();
-        let inner = TextureInner::new(ctx, texture, bytes)?;
+        let inner = TextureInner::new(
+            ctx,
+            texture,
+            config.format,
+            gl::UNSIGNED_BYTE,
+            (config.width, config.height),
+            bytes,
+        )?;
         Ok(Self {
             inner: Rc::new(inner),
         })
@@ -296,7 +335,62 @@ impl Texture {
     ) -> Result<Self> {
         let texture = create_texture_image_element(ctx.gl(), filter, element)?;
         let bytes = predict_bytes_from_element(element);
-        let inner = TextureInner::new(ctx, texture, bytes)?;
+        let inner = TextureInner::new(
+            ctx,
+            texture,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            image_element_size(element),
+            bytes,
+        )?;
+        Ok(Self {
+            inner: Rc::new(inner),
+        })
+    }
+
+    pub(crate) fn new_float_vec2(
+        ctx: Rc<crate::context::ContextInner>,
+        res: Resolution,
+        data: &[f32],
+    ) -> Result<Self> {
+        let expected = res.x as u64 * res.y as u64 * 2;
+        if data.len() as u64 != expected {
+            return Err(JsError::new(&format!(
+                "Float texture data length mismatch. expected={expected}, actual={}",
+                data.len()
+            )));
+        }
+        let gl = ctx.gl();
+        let texture = create_texture_inner(gl)?;
+        gl.bind_texture(gl::TEXTURE_2D, Some(&texture));
+        let view = js_sys::Float32Array::from(data);
+        gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_array_buffer_view(
+            gl::TEXTURE_2D,
+            0,
+            gl::RG32F as i32,
+            res.x as i32,
+            res.y as i32,
+            0,
+            gl::RG,
+            gl::FLOAT,
+            Some(&view),
+        )
+        .map_err(|e| JsError::new(&format!("Failed to tex_image_2d: {:?}", e)))?;
+        // 浮動小数点テクスチャは線形補間できない環境があるのでNEARESTにする
+        gl.tex_parameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        gl.tex_parameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+        gl.tex_parameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+        gl.tex_parameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+        gl.bind_texture(gl::TEXTURE_2D, None);
+        let bytes = expected * std::mem::size_of::<f32>() as u64;
+        let inner = TextureInner::new(
+            ctx,
+            texture,
+            gl::RG,
+            gl::FLOAT,
+            (res.x as i32, res.y as i32),
+            bytes,
+        )?;
         Ok(Self {
             inner: Rc::new(inner),
         })
@@ -315,8 +409,351 @@ impl Texture {
     /// 画像要素からテクスチャを更新する
     pub fn update_texture_image_element(&self, element: &web_sys::HtmlImageElement) {
         update_texture_image_element(self.inner.ctx.gl(), &self.inner.texture, element);
+        self.inner.size.set(image_element_size(element));
         self.inner.update_bytes(predict_bytes_from_element(element));
     }
+
+    /// テクスチャの一部の領域だけを書き換える
+    ///
+    /// dataは作成時と同じフォーマットで`width * height`ピクセル分を渡す。
+    /// 8bit整数のテクスチャのみ対応し、浮動小数点のテクスチャはエラーを返す。
+    /// テクスチャの大きさは変わらないのでバイト数の計測は更新しない
+    pub fn sub_image_update(
+        &self,
+        x: i32,
+        y: i32,
+        data: &[u8],
+        width: i32,
+        height: i32,
+    ) -> Result<()> {
+        let format = self.inner.format;
+        if self.inner.type_ != gl::UNSIGNED_BYTE {
+            return Err(JsError::new(&format!(
+                "sub_image_update supports only UNSIGNED_BYTE textures. type={}",
+                self.inner.type_
+            )));
+        }
+        let (tex_width, tex_height) = self.inner.size.get();
+        // 大きな値を渡されてもオーバーフローしないように加算を確認する
+        let in_bounds = x >= 0
+            && y >= 0
+            && width >= 0
+            && height >= 0
+            && x.checked_add(width).is_some_and(|right| right <= tex_width)
+            && y.checked_add(height)
+                .is_some_and(|bottom| bottom <= tex_height);
+        if !in_bounds {
+            return Err(JsError::new(&format!(
+                "Sub image ({x}, {y}, {width}, {height}) is out of texture size ({tex_width}, {tex_height})"
+            )));
+        }
+        let expected = width as u64 * height as u64 * Texture2dConfig::format_sizeof(format);
+        if data.len() as u64 != expected {
+            return Err(JsError::new(&format!(
+                "Sub image data length mismatch. expected={expected}, actual={}",
+                data.len()
+            )));
+        }
+
+        let gl = self.inner.ctx.gl();
+        self.inner.bind();
+        // 変更したアライメントは他のアップロードに影響しないように戻す
+        let alignment = gl
+            .get_parameter(gl::UNPACK_ALIGNMENT)
+            .ok()
+            .and_then(|v| v.as_f64())
+            .map(|v| v as i32);
+        if Texture2dConfig::format_sizeof(format) != 4 {
+            gl.pixel_storei(gl::UNPACK_ALIGNMENT, 1);
+        }
+        let res = gl.tex_sub_image_2d_with_i32_and_i32_and_u32_and_type_and_opt_u8_array(
+            gl::TEXTURE_2D,
+            0,
+            x,
+            y,
+            width,
+            height,
+            format,
+            gl::UNSIGNED_BYTE,
+            Some(data),
+        );
+        if let Some(alignment) = alignment {
+            gl.pixel_storei(gl::UNPACK_ALIGNMENT, alignment);
+        }
+        res.map_err(|e| {
+            JsError::new(&format!(
+                "Failed to call texSubImage2D: {:?}",
+                e.as_string()
+            ))
+        })
+    }
+}
+
+/// テクスチャやフレームバッファの解像度
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Resolution {
+    pub x: u32,
+    pub y: u32,
+}
+
+impl Resolution {
+    pub const fn new(x: u32, y: u32) -> Self {
+        Self { x, y }
+    }
+}
+
+/// テクスチャを描画先にするフレームバッファ
+#[cfg(feature = "context")]
+pub struct TextureFBO {
+    ctx: Rc<crate::context::ContextInner>,
+    fbo: web_sys::WebGlFramebuffer,
+    texture: Texture,
+    res: Resolution,
+}
+
+#[cfg(feature = "context")]
+impl TextureFBO {
+    /// 8bitのRGBAテクスチャを持つフレームバッファを作る
+    pub fn new_rgba(ctx: &crate::context::Context, res: Resolution) -> Result<Self> {
+        Self::new_inner(ctx, res, gl::RGBA, gl::RGBA, gl::UNSIGNED_BYTE, 4)
+    }
+
+    /// 32bit浮動小数点のRGBAテクスチャを持つフレームバッファを作る
+    ///
+    /// 描画先にするにはEXT_color_buffer_floatが必要
+    pub fn new_float_vec4(ctx: &crate::context::Context, res: Resolution) -> Result<Self> {
+        Self::new_inner(ctx, res, gl::RGBA32F, gl::RGBA, gl::FLOAT, 16)
+    }
+
+    fn new_inner(
+        ctx: &crate::context::Context,
+        res: Resolution,
+        internal_format: GlEnum,
+        src_format: GlEnum,
+        type_: GlEnum,
+        pixel_bytes: u64,
+    ) -> Result<Self> {
+        let ctx = ctx.ctx.clone();
+        let gl = ctx.gl();
+        // フレームバッファにテクスチャ用の領域を確保
+        let texture = create_texture_inner(gl)?;
+        gl.bind_texture(gl::TEXTURE_2D, Some(&texture));
+        gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+            gl::TEXTURE_2D,
+            0,
+            internal_format as i32,
+            res.x as i32,
+            res.y as i32,
+            0,
+            src_format,
+            type_,
+            None,
+        )
+        .map_err(|e| JsError::new(&format!("Failed to tex_image_2d: {:?}", e)))?;
+        gl.tex_parameteri(gl::TEXTURE_2D, gl::TEXTURE_MAG_FILTER, gl::NEAREST as i32);
+        gl.tex_parameteri(gl::TEXTURE_2D, gl::TEXTURE_MIN_FILTER, gl::NEAREST as i32);
+        gl.tex_parameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_S, gl::CLAMP_TO_EDGE as i32);
+        gl.tex_parameteri(gl::TEXTURE_2D, gl::TEXTURE_WRAP_T, gl::CLAMP_TO_EDGE as i32);
+        gl.bind_texture(gl::TEXTURE_2D, None);
+        let bytes = res.x as u64 * res.y as u64 * pixel_bytes;
+        let texture = Texture {
+            inner: Rc::new(TextureInner::new(
+                ctx.clone(),
+                texture,
+                src_format,
+                type_,
+                (res.x as i32, res.y as i32),
+                bytes,
+            )?),
+        };
+
+        let fbo = gl
+            .create_framebuffer()
+            .ok_or(JsError::new("Failed to create framebuffer"))?;
+        gl.bind_framebuffer(gl::FRAMEBUFFER, Some(&fbo));
+        gl.framebuffer_texture_2d(
+            gl::FRAMEBUFFER,
+            gl::COLOR_ATTACHMENT0,
+            gl::TEXTURE_2D,
+            Some(texture.texture()),
+            0,
+        );
+        let status = gl.check_framebuffer_status(gl::FRAMEBUFFER);
+        gl.bind_framebuffer(gl::FRAMEBUFFER, None);
+        if status != gl::FRAMEBUFFER_COMPLETE {
+            gl.delete_framebuffer(Some(&fbo));
+            return Err(JsError::new(&format!(
+                "Framebuffer is not complete. status={status}"
+            )));
+        }
+
+        Ok(Self {
+            ctx,
+            fbo,
+            texture,
+            res,
+        })
+    }
+
+    /// 描画先をこのフレームバッファにする
+    pub fn bind(&self) {
+        self.ctx
+            .gl()
+            .bind_framebuffer(gl::FRAMEBUFFER, Some(&self.fbo));
+    }
+
+    /// 描画先をデフォルトのフレームバッファに戻す
+    pub fn unbind(&self) {
+        self.ctx.gl().bind_framebuffer(gl::FRAMEBUFFER, None);
+    }
+
+    pub fn framebuffer(&self) -> &web_sys::WebGlFramebuffer {
+        &self.fbo
+    }
+
+    /// カラーアタッチメントのテクスチャ
+    pub fn texture(&self) -> &Texture {
+        &self.texture
+    }
+
+    pub fn resolution(&self) -> Resolution {
+        self.res
+    }
+
+    pub(crate) fn gl(&self) -> &gl {
+        self.ctx.gl()
+    }
+}
+
+#[cfg(feature = "context")]
+impl Drop for TextureFBO {
+    fn drop(&mut self) {
+        self.ctx.gl().delete_framebuffer(Some(&self.fbo));
+    }
+}
+
+/// 6面のRGBA画像を持つキューブマップテクスチャ
+///
+/// スカイボックスや環境マッピングに使う
+#[cfg(feature = "context")]
+pub struct TextureCube {
+    ctx: Rc<crate::context::ContextInner>,
+    texture: WebGlTexture,
+    #[cfg(feature = "metrics")]
+    bytes: u64,
+}
+
+#[cfg(feature = "context")]
+impl TextureCube {
+    /// 各面の画像から作成する
+    ///
+    /// facesは+X, -X, +Y, -Y, +Z, -Zの順で、それぞれ`face_size`四方のRGBA画像を渡す
+    pub fn new_from_faces(
+        ctx: &crate::context::Context,
+        faces: [&[u8]; 6],
+        face_size: i32,
+    ) -> Result<Self> {
+        let ctx = ctx.ctx.clone();
+        let gl = ctx.gl();
+        let texture = create_texture_inner(gl)?;
+        gl.bind_texture(gl::TEXTURE_CUBE_MAP, Some(&texture));
+        for (i, face) in faces.iter().enumerate() {
+            gl.tex_image_2d_with_i32_and_i32_and_i32_and_format_and_type_and_opt_u8_array(
+                gl::TEXTURE_CUBE_MAP_POSITIVE_X + i as u32,
+                0,
+                gl::RGBA as i32,
+                face_size,
+                face_size,
+                0,
+                gl::RGBA,
+                gl::UNSIGNED_BYTE,
+                Some(face),
+            )
+            .map_err(|e| {
+                gl.delete_texture(Some(&texture));
+                JsError::new(&format!(
+                    "Failed to call texImage2D for cube face {i}: {:?}",
+                    e.as_string()
+                ))
+            })?;
+        }
+        gl.tex_parameteri(
+            gl::TEXTURE_CUBE_MAP,
+            gl::TEXTURE_MAG_FILTER,
+            gl::LINEAR as i32,
+        );
+        gl.tex_parameteri(
+            gl::TEXTURE_CUBE_MAP,
+            gl::TEXTURE_MIN_FILTER,
+            gl::LINEAR as i32,
+        );
+        gl.tex_parameteri(
+            gl::TEXTURE_CUBE_MAP,
+            gl::TEXTURE_WRAP_S,
+            gl::CLAMP_TO_EDGE as i32,
+        );
+        gl.tex_parameteri(
+            gl::TEXTURE_CUBE_MAP,
+            gl::TEXTURE_WRAP_T,
+            gl::CLAMP_TO_EDGE as i32,
+        );
+        gl.tex_parameteri(
+            gl::TEXTURE_CUBE_MAP,
+            gl::TEXTURE_WRAP_R,
+            gl::CLAMP_TO_EDGE as i32,
+        );
+        gl.bind_texture(gl::TEXTURE_CUBE_MAP, None);
+
+        #[cfg(feature = "metrics")]
+        let bytes = {
+            let bytes =
+                6 * (face_size * face_size) as u64 * Texture2dConfig::format_sizeof(gl::RGBA);
+            let texture = &ctx.metrics().texture;
+            texture.inc_texture(1);
+            texture.inc_bytes(bytes);
+            bytes
+        };
+        Ok(Self {
+            ctx,
+            texture,
+            #[cfg(feature = "metrics")]
+            bytes,
+        })
+    }
+
+    /// 生のWebGLテクスチャを取得する
+    pub fn texture_id(&self) -> &WebGlTexture {
+        &self.texture
+    }
+
+    /// キューブマップとしてバインドする
+    pub fn bind_cube(&self) {
+        self.ctx
+            .gl()
+            .bind_texture(gl::TEXTURE_CUBE_MAP, Some(&self.texture));
+    }
+}
+
+#[cfg(feature = "context")]
+impl Drop for TextureCube {
+    fn drop(&mut self) {
+        self.ctx.gl().delete_texture(Some(&self.texture));
+        #[cfg(feature = "metrics")]
+        {
+            let texture = &self.ctx.metrics().texture;
+            texture.sub_texture(1);
+            texture.sub_bytes(self.bytes);
+        }
+    }
+}
+
+// 画像要素から作ったテクスチャの大きさ
+#[cfg(feature = "context")]
+fn image_element_size(element: &web_sys::HtmlImageElement) -> (i32, i32) {
+    (
+        element.natural_width() as i32,
+        element.natural_height() as i32,
+    )
 }
 
 // 画像要素からテクスチャのバイト数を推定する
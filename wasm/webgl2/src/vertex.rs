@@ -65,6 +65,10 @@ pub trait VaoDefine: 'static + Sized + PartialEq {
     fn has_index_buffer() -> bool {
         false
     }
+    // インスタンス毎に進める属性かどうか。trueの場合はdivisorを1にする
+    fn is_instanced(&self) -> bool {
+        false
+    }
 }
 
 /// Vertex Array Objectを作成する
@@ -105,6 +109,7 @@ where
             gl.bind_buffer(gl::ARRAY_BUFFER, Some(&vbo));
             gl.enable_vertex_attrib_array(loc);
             gl.vertex_attrib_pointer_with_i32(loc, v.size_of(), gl::FLOAT, false, 0, 0);
+            gl.vertex_attrib_divisor(loc, if v.is_instanced() { 1 } else { 0 });
             vbos.push(vbo);
             total_count += 1;
         }
@@ -172,6 +177,47 @@ where
         buffer_subdata(gl, gl::ARRAY_BUFFER, data, offset);
     }
 
+    /// VBO内の範囲をGPU上でコピーする。オフセットと長さはバイト単位
+    ///
+    /// CPUへの読み出しを挟まずにリングバッファの回転などができる。
+    /// コピー元とコピー先の範囲が重なってはならない
+    pub fn copy_buffer_range(&self, vd: T, src_offset: i32, dst_offset: i32, length: i32) {
+        let gl = self.ctx.gl();
+        let vbo = &self.vbos[vd.index()];
+        gl.bind_buffer(gl::COPY_READ_BUFFER, Some(vbo));
+        gl.bind_buffer(gl::COPY_WRITE_BUFFER, Some(vbo));
+        gl.copy_buffer_sub_data_with_i32_and_i32_and_i32(
+            gl::COPY_READ_BUFFER,
+            gl::COPY_WRITE_BUFFER,
+            src_offset,
+            dst_offset,
+            length,
+        );
+        gl.bind_buffer(gl::COPY_READ_BUFFER, None);
+        gl.bind_buffer(gl::COPY_WRITE_BUFFER, None);
+    }
+
+    /// VBOの内容をCPU側に読み出す。テストやデバッグ用
+    ///
+    /// GPUとの同期が発生するので描画ループの中では使わないこと
+    #[cfg(feature = "debug_readback")]
+    pub fn download_buffer_data<P: bytemuck::Pod>(&self, vd: T) -> Vec<P> {
+        let gl = self.ctx.gl();
+        gl.bind_buffer(gl::ARRAY_BUFFER, Some(&self.vbos[vd.index()]));
+        let bytes = gl
+            .get_buffer_parameter(gl::ARRAY_BUFFER, gl::BUFFER_SIZE)
+            .as_f64()
+            .unwrap_or(0.0) as usize;
+        let mut out = vec![<P as bytemuck::Zeroable>::zeroed(); bytes / std::mem::size_of::<P>()];
+        gl.get_buffer_sub_data_with_i32_and_u8_array(
+            gl::ARRAY_BUFFER,
+            0,
+            bytemuck::cast_slice_mut(&mut out),
+        );
+        gl.bind_buffer(gl::ARRAY_BUFFER, None);
+        out
+    }
+
     pub fn index_buffer_data(&mut self, data: &[u16], usage: u32) {
         let gl = self.ctx.gl();
         gl.bind_buffer(gl::ELEMENT_ARRAY_BUFFER, self.index.as_ref());
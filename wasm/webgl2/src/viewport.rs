@@ -137,3 +137,18 @@ impl LocalView {
         self.scissor.scissor(gl);
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalized_unit_mat() {
+        let vp = Viewport::new(0, 0, 200, 100);
+        assert_eq!(vp.aspect(), 2.0);
+
+        let mat = vp.normalized_unit_mat();
+        assert_eq!(mat[(0, 0)], 0.5);
+        assert_eq!(mat[(1, 1)], 1.0);
+    }
+}
@@ -28,3 +28,70 @@ fn test_fontshader() -> std::result::Result<(), JsValue> {
 
     Ok(())
 }
+
+// 1x1の透明PNG
+#[cfg(feature = "loader")]
+const IMAGE_SRC: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAAEAAAABCAQAAAC1HAwCAAAAC0lEQVR42mNkYAAAAAYAAjCB0C8AAAAASUVORK5CYII=";
+
+#[cfg(feature = "loader")]
+#[wasm_bindgen_test]
+async fn test_font_builder_load() -> std::result::Result<(), JsValue> {
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+
+    let detail = r#"{"name":"Test","size":8,"bold":false,"italic":false,"width":1,"height":1,"characters":{"A":{"x":0,"y":0,"width":1,"height":1,"originX":0,"originY":0,"advance":1}}}"#;
+    let detail_url = format!(
+        "data:application/json,{}",
+        js_sys::encode_uri_component(detail)
+    );
+    let font = webgl2::font::FontBuilder::load(&ctx, IMAGE_SRC, &detail_url).await?;
+    assert!(font.detail().has_char('A'));
+    assert!(!font.detail().has_char('B'));
+    assert_eq!(font.detail().width(), 1);
+    Ok(())
+}
+
+/// 隣り合わない文字の矩形は重ならず、左から順に並ぶ
+#[cfg(feature = "loader")]
+#[wasm_bindgen_test]
+async fn test_glyph_bounds() -> std::result::Result<(), JsValue> {
+    use webgl2::font::{Align, FontBuilder};
+
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+
+    let detail = include_str!("../testdata/Ubuntu_Mono_64px.json");
+    let detail_url = format!(
+        "data:application/json,{}",
+        js_sys::encode_uri_component(detail)
+    );
+    let font = FontBuilder::load(&ctx, IMAGE_SRC, &detail_url).await?;
+    let text = font.text("ABC", Align::left_bottom());
+
+    let (bl0, tr0) = text.glyph_bounds(0).unwrap();
+    let (bl2, tr2) = text.glyph_bounds(2).unwrap();
+    assert!(bl0.x < tr0.x && bl0.y < tr0.y);
+    assert!(tr0.x <= bl2.x);
+    // 3文字分の幅は1文字分より広い
+    assert!(tr2.x - bl0.x > tr0.x - bl0.x);
+    assert!(text.glyph_bounds(3).is_none());
+    Ok(())
+}
@@ -0,0 +1,46 @@
+#![cfg(feature = "font-embed")]
+#![cfg(feature = "metrics")]
+#![cfg(feature = "viewport")]
+#![cfg(target_arch = "wasm32")]
+
+extern crate wasm_bindgen_test;
+
+use std::sync::atomic::Ordering::Relaxed;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_test::*;
+use webgl2::metrics::GpuMetricsHud;
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// テクスチャ作成数がHUDの表示に反映される
+#[wasm_bindgen_test]
+fn test_metrics_hud() -> std::result::Result<(), JsValue> {
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let font = webgl2::font::embed::load(&ctx)?;
+    let mut hud = GpuMetricsHud::new(&ctx, &font)?;
+
+    // フォントテクスチャ分を差し引いて数える
+    let base = ctx.metrics().texture.texture_count.load(Relaxed);
+    let mut textures = vec![];
+    for _ in 0..5 {
+        textures.push(ctx.create_blank_texture()?);
+    }
+    hud.update(&ctx);
+
+    assert!(hud.text().contains(&format!("Textures: {}", base + 5)));
+    assert!(hud.text().contains("5"));
+    hud.draw();
+
+    Ok(())
+}
@@ -43,3 +43,70 @@ fn test_metrics_texture() -> std::result::Result<(), JsValue> {
 
     Ok(())
 }
+
+/// 最大値は破棄後も同時に存在した最大の値を保持する
+#[wasm_bindgen_test]
+fn test_metrics_texture_peak() -> std::result::Result<(), JsValue> {
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let metrics = ctx.metrics();
+
+    let mut textures = vec![];
+    for _ in 0..3 {
+        textures.push(ctx.create_blank_texture()?);
+    }
+    textures.truncate(1);
+    textures.push(ctx.create_blank_texture()?);
+
+    assert_eq!(2, metrics.texture.texture_count.load(Relaxed));
+    assert_eq!(3, metrics.texture.texture_count_peak());
+    assert_eq!(12, metrics.texture.peak_bytes());
+
+    drop(textures);
+    assert_eq!(12, metrics.texture.peak_bytes());
+    assert!(metrics.texture.to_string().contains("(peak: 3, 12 B)"));
+
+    metrics.texture.reset_peak();
+    assert_eq!(0, metrics.texture.texture_count_peak());
+    assert_eq!(0, metrics.texture.peak_bytes());
+
+    Ok(())
+}
+
+/// 浮動小数点テクスチャもバイト数を計測し、破棄時に解放する
+#[wasm_bindgen_test]
+fn test_metrics_float_texture() -> std::result::Result<(), JsValue> {
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let metrics = ctx.metrics();
+    let res = webgl2::texture::Resolution::new(4, 2);
+
+    assert!(ctx.create_float_vec2_texture(res, &[0.0; 3]).is_err());
+    let texture = ctx.create_float_vec2_texture(res, &[1.0; 4 * 2 * 2])?;
+    assert_eq!(1, metrics.texture.texture_count.load(Relaxed));
+    assert_eq!(4 * 2 * 8, metrics.texture.bytes_count.load(Relaxed));
+
+    drop(texture);
+    assert_eq!(0, metrics.texture.texture_count.load(Relaxed));
+    assert_eq!(0, metrics.texture.bytes_count.load(Relaxed));
+
+    Ok(())
+}
@@ -0,0 +1,72 @@
+#![cfg(feature = "texture")]
+#![cfg(feature = "shader")]
+#![cfg(target_arch = "wasm32")]
+
+extern crate wasm_bindgen_test;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_test::*;
+use webgl2::{
+    gl,
+    shader::outline::OutlineShader,
+    texture::{Resolution, TextureFBO},
+};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// 4x4の白い領域の周囲1ピクセルが輪郭色になる
+#[wasm_bindgen_test]
+fn test_outline() -> std::result::Result<(), JsValue> {
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let gl = ctx.gl();
+    let res = Resolution::new(8, 8);
+    let source = TextureFBO::new_rgba(&ctx, res)?;
+    let target = TextureFBO::new_rgba(&ctx, res)?;
+    gl.viewport(0, 0, 8, 8);
+
+    // (2, 2)から4x4の領域を白で塗る
+    source.bind();
+    gl.clear_color(0.0, 0.0, 0.0, 0.0);
+    gl.clear(gl::COLOR_BUFFER_BIT);
+    gl.enable(gl::SCISSOR_TEST);
+    gl.scissor(2, 2, 4, 4);
+    gl.clear_color(1.0, 1.0, 1.0, 1.0);
+    gl.clear(gl::COLOR_BUFFER_BIT);
+    gl.disable(gl::SCISSOR_TEST);
+
+    let shader = OutlineShader::new(&ctx, &source, [1.0, 0.0, 0.0, 1.0], 1)?;
+    target.bind();
+    gl.clear_color(0.0, 0.0, 0.0, 0.0);
+    gl.clear(gl::COLOR_BUFFER_BIT);
+    shader.draw();
+
+    let mut pixels = vec![0u8; 8 * 8 * 4];
+    gl.read_pixels_with_opt_u8_array(0, 0, 8, 8, gl::RGBA, gl::UNSIGNED_BYTE, Some(&mut pixels))?;
+    target.unbind();
+
+    for y in 0..8 {
+        for x in 0..8 {
+            let i = (y * 8 + x) * 4;
+            let inside = (2..6).contains(&x) && (2..6).contains(&y);
+            let border = (1..7).contains(&x) && (1..7).contains(&y) && !inside;
+            let expected = if border {
+                [255, 0, 0, 255]
+            } else {
+                [0, 0, 0, 0]
+            };
+            assert_eq!(pixels[i..i + 4], expected, "({x}, {y})");
+        }
+    }
+    assert_eq!(gl.get_error(), gl::NO_ERROR);
+    Ok(())
+}
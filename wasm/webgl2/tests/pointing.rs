@@ -0,0 +1,51 @@
+#![cfg(feature = "pointing")]
+#![cfg(target_arch = "wasm32")]
+
+extern crate wasm_bindgen_test;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_test::*;
+use webgl2::{
+    gl,
+    shader::pointing::{PointingRequest, PointingShader},
+    GlPoint2d,
+};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// タッチ位置は上限まで保持して描画する
+#[wasm_bindgen_test]
+fn test_touch_positions() -> std::result::Result<(), JsValue> {
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let mut shader = PointingShader::new(&ctx)?;
+    shader.apply_requests(&[
+        PointingRequest::Enable(true),
+        PointingRequest::Positions(vec![GlPoint2d::new(0.1, 0.2), GlPoint2d::new(-0.3, 0.4)]),
+    ]);
+    assert_eq!(shader.touch_count(), 2);
+
+    // 上限を超えた分は無視する
+    let positions = (0..12)
+        .flat_map(|i| [i as f32 * 0.1, 0.0])
+        .collect::<Vec<_>>();
+    shader.set_touch_positions(js_sys::Float32Array::from(positions.as_slice()));
+    assert_eq!(shader.touch_count(), PointingShader::MAX_TOUCH_POINTS);
+
+    shader.update(0.016);
+    shader.draw();
+    assert_eq!(ctx.gl().get_error(), gl::NO_ERROR);
+
+    shader.set_touch_positions(js_sys::Float32Array::new_with_length(0));
+    assert_eq!(shader.touch_count(), 0);
+    Ok(())
+}
@@ -0,0 +1,54 @@
+#![cfg(feature = "texture")]
+#![cfg(feature = "shader")]
+#![cfg(target_arch = "wasm32")]
+
+extern crate wasm_bindgen_test;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_test::*;
+use webgl2::{
+    gl,
+    shader::postprocess::PostProcessShader,
+    texture::{Resolution, Texture2dConfig, TextureFBO},
+};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// pass_throughは入力のテクスチャをそのまま出力する
+#[wasm_bindgen_test]
+fn test_pass_through() -> std::result::Result<(), JsValue> {
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let gl = ctx.gl();
+
+    let data = [
+        255, 0, 0, 255, 0, 255, 0, 255, // 下段
+        0, 0, 255, 255, 255, 255, 255, 255, // 上段
+    ];
+    let mut config = Texture2dConfig::new_rgba(2, 2);
+    config.filter.min = gl::NEAREST as i32;
+    config.filter.mag = gl::NEAREST as i32;
+    let source = ctx.create_texture(&config, Some(&data))?;
+
+    let shader = PostProcessShader::pass_through(&ctx)?;
+    let target = TextureFBO::new_rgba(&ctx, Resolution::new(2, 2))?;
+    target.bind();
+    gl.viewport(0, 0, 2, 2);
+    shader.draw(&source);
+
+    let mut pixels = vec![0u8; data.len()];
+    gl.read_pixels_with_opt_u8_array(0, 0, 2, 2, gl::RGBA, gl::UNSIGNED_BYTE, Some(&mut pixels))?;
+    target.unbind();
+
+    assert_eq!(pixels, data);
+    Ok(())
+}
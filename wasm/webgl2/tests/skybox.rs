@@ -0,0 +1,37 @@
+#![cfg(feature = "texture")]
+#![cfg(feature = "shader")]
+#![cfg(target_arch = "wasm32")]
+
+extern crate wasm_bindgen_test;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_test::*;
+use webgl2::{gl, shader::skybox::SkyboxShader, texture::TextureCube};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// 単色のキューブマップで背景を描画してもGLエラーが出ない
+#[wasm_bindgen_test]
+fn test_skybox_draw() -> std::result::Result<(), JsValue> {
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let face = [0u8, 128, 255, 255].repeat(2 * 2);
+    let cube = TextureCube::new_from_faces(&ctx, [&face; 6].map(|f| f.as_slice()), 2)?;
+
+    let shader = SkyboxShader::new(&ctx, cube)?;
+    let camera = nalgebra::Matrix4::new_rotation(nalgebra::Vector3::new(0.0, 0.5, 0.0))
+        .append_translation(&nalgebra::Vector3::new(1.0, 2.0, 3.0));
+    shader.draw(camera);
+
+    assert_eq!(ctx.gl().get_error(), gl::NO_ERROR);
+    Ok(())
+}
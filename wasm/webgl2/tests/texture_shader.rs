@@ -0,0 +1,74 @@
+#![cfg(feature = "texture")]
+#![cfg(feature = "shader")]
+#![cfg(target_arch = "wasm32")]
+
+extern crate wasm_bindgen_test;
+
+use wasm_bindgen::prelude::*;
+use wasm_bindgen_test::*;
+use webgl2::{
+    gl,
+    shader::texture::TextureShader,
+    texture::{Resolution, Texture2dConfig, TextureFBO},
+    GlPoint2d,
+};
+
+wasm_bindgen_test_configure!(run_in_browser);
+
+/// UVのオフセットを0.5にすると横方向に半分ずれた位置を参照する
+#[wasm_bindgen_test]
+fn test_uv_transform() -> std::result::Result<(), JsValue> {
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let gl = ctx.gl();
+
+    // 左が赤、右が緑の2x1テクスチャ
+    let mut config = Texture2dConfig::new_rgba(2, 1);
+    config.filter.min = gl::NEAREST as i32;
+    config.filter.mag = gl::NEAREST as i32;
+    let texture = ctx.create_texture(&config, Some(&[255, 0, 0, 255, 0, 255, 0, 255]))?;
+
+    let shader = TextureShader::new(&ctx)?;
+    let vao = shader.create_vao(&[
+        GlPoint2d::new(-1.0, -1.0),
+        GlPoint2d::new(1.0, -1.0),
+        GlPoint2d::new(-1.0, 1.0),
+        GlPoint2d::new(1.0, 1.0),
+    ])?;
+    let target = TextureFBO::new_rgba(&ctx, Resolution::new(2, 1))?;
+    target.bind();
+    gl.viewport(0, 0, 2, 1);
+
+    let render = || -> std::result::Result<Vec<u8>, JsValue> {
+        shader.draw(&vao, texture.texture());
+        let mut pixels = vec![0u8; 2 * 4];
+        gl.read_pixels_with_opt_u8_array(
+            0,
+            0,
+            2,
+            1,
+            gl::RGBA,
+            gl::UNSIGNED_BYTE,
+            Some(&mut pixels),
+        )?;
+        Ok(pixels)
+    };
+
+    assert_eq!(render()?, vec![255, 0, 0, 255, 0, 255, 0, 255]);
+
+    // 左のピクセルの中心 u=0.25 は 0.75 を参照する
+    shader.set_uv_transform(GlPoint2d::new(0.5, 0.0), GlPoint2d::new(1.0, 1.0));
+    assert_eq!(render()?[..4], [0, 255, 0, 255]);
+
+    target.unbind();
+    Ok(())
+}
@@ -77,3 +77,777 @@ void main() {
 
     Ok(())
 }
+
+/// KHR_debugが無い環境でもラベル付けでパニックしない
+#[cfg(feature = "vertex")]
+#[wasm_bindgen_test]
+fn test_debug_label() -> std::result::Result<(), JsValue> {
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let buffer = webgl2::vertex::create_buffer(ctx.gl())?;
+    ctx.set_buffer_label(&buffer, "test_buffer");
+    // 2回目は問い合わせ結果のキャッシュを使う
+    ctx.set_buffer_label(&buffer, "test_buffer");
+    Ok(())
+}
+
+/// 再コンパイルに成功したら古いプログラムは削除される
+#[wasm_bindgen_test]
+fn test_recompile() -> std::result::Result<(), JsValue> {
+    const VERT: &str = r#"#version 300 es
+layout(location = 0) in vec3 position;
+void main() {
+    gl_Position = vec4(position, 1.0);
+}
+"#;
+    const FRAG: &str = r#"#version 300 es
+precision highp float;
+out vec4 fragmentColor;
+void main() {
+    fragmentColor = vec4(1.0);
+}
+"#;
+
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let mut program = ctx.program(VERT, FRAG)?;
+    let old = program.program().clone();
+
+    // 失敗した場合は元のプログラムが残る
+    assert!(program.recompile(VERT, "invalid").is_err());
+    assert!(ctx.gl().is_program(Some(&old)));
+
+    program.recompile(VERT, FRAG)?;
+    assert!(!ctx.gl().is_program(Some(&old)));
+    assert!(ctx.gl().is_program(Some(program.program())));
+
+    // JSからは文字列以外を渡すとエラーになる
+    let old = program.program().clone();
+    assert!(program.try_recompile(JsValue::NULL, FRAG.into()).is_err());
+    assert!(ctx.gl().is_program(Some(&old)));
+    program.try_recompile(VERT.into(), FRAG.into())?;
+    assert!(!ctx.gl().is_program(Some(&old)));
+    Ok(())
+}
+
+/// コンパイルログは警告が無ければNone、コンパイルに失敗した場合はログがエラーになり再コンパイル時は保持される
+#[wasm_bindgen_test]
+fn test_compile_log() -> std::result::Result<(), JsValue> {
+    const VERT: &str = r#"#version 300 es
+layout(location = 0) in vec3 position;
+void main() {
+    gl_Position = vec4(position, 1.0);
+}
+"#;
+    const FRAG: &str = r#"#version 300 es
+precision mediump float;
+out vec4 fragmentColor;
+void main() {
+    fragmentColor = vec4(1.0);
+}
+"#;
+    // 宣言していない変数を参照するのでコンパイルに失敗する
+    const FRAG_ERROR: &str = r#"#version 300 es
+precision mediump float;
+out vec4 fragmentColor;
+void main() {
+    fragmentColor = vec4(undeclared_value);
+}
+"#;
+
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let mut program = ctx.program(VERT, FRAG)?;
+    assert_eq!(program.get_vertex_log(), None);
+    assert_eq!(program.get_fragment_log(), None);
+
+    let err = match ctx.program(VERT, FRAG_ERROR) {
+        Ok(_) => panic!("compile should fail"),
+        Err(e) => js_sys::Error::from(JsValue::from(e)),
+    };
+    let log = String::from(err.message());
+    assert!(log.contains("undeclared_value"), "{log}");
+
+    // 再コンパイルに失敗した場合は失敗したシェーダーのログが残る
+    assert!(program.recompile(VERT, FRAG_ERROR).is_err());
+    assert_eq!(program.get_vertex_log(), None);
+    let log = program
+        .get_fragment_log()
+        .expect("fragment log should be stored");
+    assert!(log.contains("undeclared_value"), "{log}");
+
+    // 成功すればログは新しいシェーダーのものに置き換わる
+    program.recompile(VERT, FRAG)?;
+    assert_eq!(program.get_fragment_log(), None);
+    Ok(())
+}
+
+/// 有効なuniformとattributeの名前を取得できる
+#[wasm_bindgen_test]
+fn test_query_names() -> std::result::Result<(), JsValue> {
+    const VERT: &str = r#"#version 300 es
+layout(location = 0) in vec3 position;
+uniform mat4 mvp;
+void main() {
+    gl_Position = mvp * vec4(position, 1.0);
+}
+"#;
+    const FRAG: &str = r#"#version 300 es
+precision highp float;
+uniform vec4 color;
+out vec4 fragmentColor;
+void main() {
+    fragmentColor = color;
+}
+"#;
+
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let program = ctx.program(VERT, FRAG)?;
+
+    let mut uniforms = program.query_uniform_names();
+    uniforms.sort();
+    assert_eq!(uniforms, vec!["color", "mvp"]);
+    assert_eq!(program.query_active_attributes(), vec!["position"]);
+    assert_eq!(program.uniform_names_array().length(), 2);
+    Ok(())
+}
+
+/// begin/endの間だけレンダーターゲットのフレームバッファに描画する
+#[cfg(feature = "texture")]
+#[wasm_bindgen_test]
+fn test_render_target() -> std::result::Result<(), JsValue> {
+    use webgl2::{gl, texture::Resolution};
+
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let gl = ctx.gl();
+    // テストに使うブラウザは浮動小数点テクスチャへの描画に対応していること
+    assert!(
+        gl.get_extension("EXT_color_buffer_float")?.is_some(),
+        "EXT_color_buffer_float is not supported in the test browser"
+    );
+    let target = ctx.create_render_target(Resolution::new(16, 8))?;
+
+    assert!(gl.get_parameter(gl::FRAMEBUFFER_BINDING)?.is_null());
+    target.begin();
+    let binding = gl.get_parameter(gl::FRAMEBUFFER_BINDING)?;
+    assert_eq!(
+        &binding,
+        AsRef::<JsValue>::as_ref(target.fbo().framebuffer())
+    );
+    let viewport = js_sys::Int32Array::from(gl.get_parameter(gl::VIEWPORT)?).to_vec();
+    assert_eq!(viewport, vec![0, 0, 16, 8]);
+    target.end();
+    assert!(gl.get_parameter(gl::FRAMEBUFFER_BINDING)?.is_null());
+    Ok(())
+}
+
+/// インスタンス属性はdivisorが1、頂点属性は0になる
+#[cfg(feature = "vertex")]
+#[wasm_bindgen_test]
+fn test_instanced_divisor() -> std::result::Result<(), JsValue> {
+    use webgl2::{gl, vertex::VaoDefine, GlPoint2d};
+
+    #[derive(Debug, PartialEq)]
+    enum InstancedBoidVd {
+        Position,
+        InstanceOffset,
+    }
+
+    impl VaoDefine for InstancedBoidVd {
+        fn iter() -> std::slice::Iter<'static, Self> {
+            static VD: [InstancedBoidVd; 2] =
+                [InstancedBoidVd::Position, InstancedBoidVd::InstanceOffset];
+            VD.iter()
+        }
+
+        fn name(&self) -> &'static str {
+            match self {
+                InstancedBoidVd::Position => "position",
+                InstancedBoidVd::InstanceOffset => "instance_offset",
+            }
+        }
+
+        fn size_of(&self) -> i32 {
+            2
+        }
+
+        fn is_instanced(&self) -> bool {
+            matches!(self, InstancedBoidVd::InstanceOffset)
+        }
+    }
+
+    const VERT: &str = r#"#version 300 es
+layout(location = 0) in vec2 position;
+layout(location = 1) in vec2 instance_offset;
+void main() {
+    gl_Position = vec4(position + instance_offset, 0.0, 1.0);
+}
+"#;
+    const FRAG: &str = r#"#version 300 es
+precision mediump float;
+out vec4 fragmentColor;
+void main() {
+    fragmentColor = vec4(1.0);
+}
+"#;
+
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let gl = ctx.gl();
+    let program = ctx.program(VERT, FRAG)?;
+    let mut vao = program.create_vao::<InstancedBoidVd>()?;
+    let points = [GlPoint2d::new(0.0, 0.0); 3];
+    vao.buffer_data(InstancedBoidVd::Position, &points, gl::STATIC_DRAW);
+    vao.buffer_data(InstancedBoidVd::InstanceOffset, &points, gl::DYNAMIC_DRAW);
+
+    vao.bind();
+    for (vd, expected) in [
+        (InstancedBoidVd::Position, 0.0),
+        (InstancedBoidVd::InstanceOffset, 1.0),
+    ] {
+        let loc = gl.get_attrib_location(program.program(), vd.name()) as u32;
+        let divisor = gl.get_vertex_attrib(loc, gl::VERTEX_ATTRIB_ARRAY_DIVISOR)?;
+        assert_eq!(divisor.as_f64(), Some(expected), "{vd:?}");
+    }
+    vao.unbind();
+    Ok(())
+}
+
+/// VBO内の範囲コピーがコピー先だけを書き換える
+#[cfg(feature = "vertex")]
+#[wasm_bindgen_test]
+fn test_copy_buffer_range() -> std::result::Result<(), JsValue> {
+    use webgl2::{gl, vertex::VaoDefine, GlPoint4d};
+
+    #[derive(Debug, PartialEq)]
+    enum Vd {
+        Position,
+    }
+
+    impl VaoDefine for Vd {
+        fn iter() -> std::slice::Iter<'static, Self> {
+            static VD: [Vd; 1] = [Vd::Position];
+            VD.iter()
+        }
+
+        fn name(&self) -> &'static str {
+            "position"
+        }
+
+        fn size_of(&self) -> i32 {
+            4
+        }
+    }
+
+    const VERT: &str = r#"#version 300 es
+layout(location = 0) in vec4 position;
+void main() {
+    gl_Position = position;
+}
+"#;
+    const FRAG: &str = r#"#version 300 es
+precision mediump float;
+out vec4 fragmentColor;
+void main() {
+    fragmentColor = vec4(1.0);
+}
+"#;
+
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let gl = ctx.gl();
+    let program = ctx.program(VERT, FRAG)?;
+    let mut vao = program.create_vao::<Vd>()?;
+    let data = (0..4)
+        .map(|i| {
+            let i = i as f32 * 4.0;
+            GlPoint4d::new(i, i + 1.0, i + 2.0, i + 3.0)
+        })
+        .collect::<Vec<_>>();
+    vao.buffer_data(Vd::Position, &data, gl::STATIC_DRAW);
+
+    // 先頭4要素を8要素目にコピーする
+    let f32_size = std::mem::size_of::<f32>() as i32;
+    vao.copy_buffer_range(Vd::Position, 0, 8 * f32_size, 4 * f32_size);
+
+    let out = js_sys::Float32Array::new_with_length(16);
+    gl.bind_buffer(gl::ARRAY_BUFFER, Some(vao.vbo(Vd::Position)));
+    gl.get_buffer_sub_data_with_i32_and_array_buffer_view(gl::ARRAY_BUFFER, 0, &out);
+    let expected = [
+        0.0, 1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 0.0, 1.0, 2.0, 3.0, 12.0, 13.0, 14.0, 15.0,
+    ];
+    assert_eq!(out.to_vec(), expected);
+    Ok(())
+}
+
+#[wasm_bindgen_test]
+fn test_transform_feedback() -> std::result::Result<(), JsValue> {
+    use webgl2::gl;
+
+    const VERT: &str = r#"#version 300 es
+layout(location = 0) in vec4 position;
+out vec4 outPosition;
+void main() {
+    outPosition = position + vec4(1.0, 2.0, 3.0, 4.0);
+}
+"#;
+    const VERT_DOUBLE: &str = r#"#version 300 es
+layout(location = 0) in vec4 position;
+out vec4 outPosition;
+void main() {
+    outPosition = position * 2.0;
+}
+"#;
+    const FRAG: &str = r#"#version 300 es
+precision mediump float;
+out vec4 fragmentColor;
+void main() {
+    fragmentColor = vec4(1.0);
+}
+"#;
+
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let gl = ctx.gl();
+    let mut program = Program::with_transform_feedback(&ctx, VERT, FRAG, &["outPosition"])?;
+
+    let input: [f32; 8] = [0.0, 0.0, 0.0, 1.0, 10.0, 20.0, 30.0, 1.0];
+    let src = gl.create_buffer().ok_or("Failed to create buffer")?;
+    gl.bind_buffer(gl::ARRAY_BUFFER, Some(&src));
+    unsafe {
+        let view = js_sys::Float32Array::view(&input);
+        gl.buffer_data_with_array_buffer_view(gl::ARRAY_BUFFER, &view, gl::STATIC_DRAW);
+    }
+    gl.enable_vertex_attrib_array(0);
+    gl.vertex_attrib_pointer_with_i32(0, 4, gl::FLOAT, false, 0, 0);
+
+    let dst = gl.create_buffer().ok_or("Failed to create buffer")?;
+    gl.bind_buffer(gl::TRANSFORM_FEEDBACK_BUFFER, Some(&dst));
+    gl.buffer_data_with_i32(
+        gl::TRANSFORM_FEEDBACK_BUFFER,
+        (input.len() * std::mem::size_of::<f32>()) as i32,
+        gl::STREAM_READ,
+    );
+    gl.bind_buffer(gl::TRANSFORM_FEEDBACK_BUFFER, None);
+
+    // 2点を描画して書き出された値を読み出す
+    let capture = |program: &Program| {
+        program.use_program();
+        gl.bind_buffer_base(gl::TRANSFORM_FEEDBACK_BUFFER, 0, Some(&dst));
+        gl.enable(gl::RASTERIZER_DISCARD);
+        program.begin_transform_feedback(gl::POINTS);
+        gl.draw_arrays(gl::POINTS, 0, 2);
+        program.end_transform_feedback();
+        gl.disable(gl::RASTERIZER_DISCARD);
+        gl.bind_buffer_base(gl::TRANSFORM_FEEDBACK_BUFFER, 0, None);
+
+        let out = js_sys::Float32Array::new_with_length(input.len() as u32);
+        gl.bind_buffer(gl::TRANSFORM_FEEDBACK_BUFFER, Some(&dst));
+        gl.get_buffer_sub_data_with_i32_and_array_buffer_view(
+            gl::TRANSFORM_FEEDBACK_BUFFER,
+            0,
+            &out,
+        );
+        gl.bind_buffer(gl::TRANSFORM_FEEDBACK_BUFFER, None);
+        out.to_vec()
+    };
+    assert_eq!(
+        capture(&program),
+        [1.0, 2.0, 3.0, 5.0, 11.0, 22.0, 33.0, 5.0]
+    );
+
+    // 再コンパイル後もvaryingを引き継いで書き出せる
+    program.recompile(VERT_DOUBLE, FRAG)?;
+    assert_eq!(
+        capture(&program),
+        [0.0, 0.0, 0.0, 2.0, 20.0, 40.0, 60.0, 2.0]
+    );
+    Ok(())
+}
+
+/// 溜まったGLエラーは最初のものが返り、取り出した後は空になる
+#[cfg(debug_assertions)]
+#[wasm_bindgen_test]
+fn test_check_errors() -> std::result::Result<(), JsValue> {
+    use webgl2::gl;
+
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    assert_eq!(ctx.check_errors(), None);
+
+    // 存在しないターゲットへのbindはINVALID_ENUMになる
+    ctx.gl().bind_buffer(0, None);
+    assert_eq!(ctx.check_errors(), Some(gl::INVALID_ENUM));
+    assert_eq!(ctx.check_errors(), None);
+    ctx.check_errors_assert();
+    Ok(())
+}
+
+/// uniformブロックは列挙順に0から連番でバインドされる
+#[wasm_bindgen_test]
+fn test_auto_bind_uniform_blocks() -> std::result::Result<(), JsValue> {
+    use webgl2::gl;
+
+    const VERT: &str = r#"#version 300 es
+
+layout(location = 0) in vec3 position;
+
+uniform matrix {
+    mat4 mvp;
+};
+
+uniform tint {
+    vec4 color;
+};
+
+out vec4 vertexColor;
+
+void main() {
+    vertexColor = color;
+    gl_Position = mvp * vec4(position, 1.0);
+}
+"#;
+
+    const FRAG: &str = r#"#version 300 es
+
+precision highp float;
+
+in vec4 vertexColor;
+out vec4 fragmentColor;
+
+void main() {
+    fragmentColor = vertexColor;
+}
+"#;
+
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let program = ctx.program(VERT, FRAG)?;
+    assert_eq!(program.auto_bind_uniform_blocks()?, 2);
+
+    let gl = program.gl();
+    for i in 0..2 {
+        let binding =
+            gl.get_active_uniform_block_parameter(program.program(), i, gl::UNIFORM_BLOCK_BINDING)?;
+        assert_eq!(binding.as_f64(), Some(i as f64));
+    }
+    Ok(())
+}
+
+/// 書き込んだVBOの内容をそのまま読み出せる
+#[cfg(feature = "debug_readback")]
+#[wasm_bindgen_test]
+fn test_download_buffer_data() -> std::result::Result<(), JsValue> {
+    use webgl2::{gl, vertex::VaoDefine, GlPoint1d};
+
+    #[derive(Debug, PartialEq)]
+    enum Vd {
+        Value,
+    }
+
+    impl VaoDefine for Vd {
+        fn iter() -> std::slice::Iter<'static, Self> {
+            static VD: [Vd; 1] = [Vd::Value];
+            VD.iter()
+        }
+
+        fn name(&self) -> &'static str {
+            "value"
+        }
+
+        fn size_of(&self) -> i32 {
+            1
+        }
+    }
+
+    const VERT: &str = r#"#version 300 es
+layout(location = 0) in float value;
+void main() {
+    gl_Position = vec4(value);
+}
+"#;
+    const FRAG: &str = r#"#version 300 es
+precision mediump float;
+out vec4 fragmentColor;
+void main() {
+    fragmentColor = vec4(1.0);
+}
+"#;
+
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let program = ctx.program(VERT, FRAG)?;
+    let mut vao = program.create_vao::<Vd>()?;
+    let data = [1.0, 2.0, 3.0].map(GlPoint1d::new);
+    vao.buffer_data(Vd::Value, &data, gl::STATIC_DRAW);
+
+    assert_eq!(vao.download_buffer_data::<f32>(Vd::Value), [1.0, 2.0, 3.0]);
+    Ok(())
+}
+
+/// 画像要素の実際の大きさから設定を作る
+#[cfg(all(feature = "texture", feature = "loader"))]
+#[wasm_bindgen_test]
+async fn test_texture_config_from_image_element() -> std::result::Result<(), JsValue> {
+    use webgl2::{gl, loader::ImageLoader, texture::Texture2dConfig};
+
+    // 64x128のグレースケールPNG
+    const IMAGE_SRC: &str = "data:image/png;base64,iVBORw0KGgoAAAANSUhEUgAAAEAAAACACAAAAACe6jC2AAAAH0lEQVR42u3BMQEAAADCoPVPbQhfoAAAAAAAAAAAPgMggAABImXeGgAAAABJRU5ErkJggg==";
+
+    let img = ImageLoader::new(IMAGE_SRC)?.await?;
+    let config = Texture2dConfig::from_image_element(&img);
+    assert_eq!((config.width, config.height), (64, 128));
+    assert_eq!(config.inner_format, gl::RGBA as i32);
+    Ok(())
+}
+
+/// 部分更新は指定した領域だけを書き換える
+#[cfg(feature = "texture")]
+#[wasm_bindgen_test]
+fn test_texture_sub_image_update() -> std::result::Result<(), JsValue> {
+    use webgl2::{
+        gl,
+        texture::{Resolution, TextureFBO},
+    };
+
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let fbo = TextureFBO::new_rgba(&ctx, Resolution::new(4, 4))?;
+    fbo.texture()
+        .sub_image_update(0, 0, &[255; 2 * 2 * 4], 2, 2)?;
+
+    let gl = ctx.gl();
+    let mut pixels = [0u8; 4 * 4 * 4];
+    fbo.bind();
+    gl.read_pixels_with_opt_u8_array(0, 0, 4, 4, gl::RGBA, gl::UNSIGNED_BYTE, Some(&mut pixels))?;
+    fbo.unbind();
+
+    for (i, px) in pixels.chunks_exact(4).enumerate() {
+        let (x, y) = (i % 4, i / 4);
+        let expected = if x < 2 && y < 2 { 255 } else { 0 };
+        assert_eq!(px, [expected; 4], "pixel ({x}, {y})");
+    }
+
+    // 長さの不一致、範囲外、浮動小数点テクスチャはエラーになる
+    let texture = fbo.texture();
+    assert!(texture.sub_image_update(0, 0, &[255; 3], 2, 2).is_err());
+    assert!(texture
+        .sub_image_update(3, 3, &[255; 2 * 2 * 4], 2, 2)
+        .is_err());
+    // x + widthがi32を超える場合もエラーになる
+    assert!(texture.sub_image_update(i32::MAX, 0, &[], 1, 0).is_err());
+    let float_fbo = TextureFBO::new_float_vec4(&ctx, Resolution::new(4, 4));
+    if let Ok(float_fbo) = float_fbo {
+        assert!(float_fbo
+            .texture()
+            .sub_image_update(0, 0, &[255; 2 * 2 * 4], 2, 2)
+            .is_err());
+    }
+    Ok(())
+}
+
+/// 6面の単色画像からキューブマップを作成できる
+#[cfg(feature = "texture")]
+#[wasm_bindgen_test]
+fn test_texture_cube_from_faces() -> std::result::Result<(), JsValue> {
+    use webgl2::{gl, texture::TextureCube};
+
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let colors: [[u8; 4]; 6] = [
+        [255, 0, 0, 255],
+        [0, 255, 0, 255],
+        [0, 0, 255, 255],
+        [255, 255, 0, 255],
+        [0, 255, 255, 255],
+        [255, 0, 255, 255],
+    ];
+    let faces: Vec<Vec<u8>> = colors.iter().map(|c| c.repeat(2 * 2)).collect();
+    let faces: [&[u8]; 6] = std::array::from_fn(|i| faces[i].as_slice());
+    let cube = TextureCube::new_from_faces(&ctx, faces, 2)?;
+
+    cube.bind_cube();
+    let gl = ctx.gl();
+    assert!(gl.is_texture(Some(cube.texture_id())));
+    assert_eq!(gl.get_error(), gl::NO_ERROR);
+    Ok(())
+}
+
+/// has_msaaは作成されたコンテキストの属性でアンチエイリアスが有効かを返す
+#[wasm_bindgen_test]
+fn test_context_with_msaa() -> std::result::Result<(), JsValue> {
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::with_msaa(canvas, webgl2::context::COLOR_BLACK, 4)?;
+    let gl = ctx.gl();
+    let antialias = gl
+        .get_context_attributes()
+        .and_then(|attr| js_sys::Reflect::get(&attr, &JsValue::from_str("antialias")).ok())
+        .and_then(|v| v.as_bool())
+        .unwrap_or(false);
+    let samples = gl
+        .get_parameter(webgl2::gl::SAMPLES)?
+        .as_f64()
+        .unwrap_or(0.0);
+    // samplesはヒントなので、結果はコンテキストの属性で決まる
+    assert_eq!(ctx.has_msaa(), antialias && samples > 0.0);
+    Ok(())
+}
+
+/// with_scissorはクロージャの実行中だけシザーテストを有効にする
+#[wasm_bindgen_test]
+fn test_context_with_scissor() -> std::result::Result<(), JsValue> {
+    use webgl2::gl;
+
+    let doc = web_sys::window()
+        .ok_or("Failed to get Window")?
+        .document()
+        .ok_or("Failed to get Document")?;
+
+    let canvas = doc
+        .create_element("canvas")
+        .expect("Could not create testing node");
+    let canvas: web_sys::HtmlCanvasElement = canvas.dyn_into::<web_sys::HtmlCanvasElement>()?;
+
+    let ctx = webgl2::context::Context::new(canvas, webgl2::context::COLOR_BLACK)?;
+    let gl = ctx.gl();
+    assert!(!gl.is_enabled(gl::SCISSOR_TEST));
+
+    let mut called = false;
+    ctx.with_scissor((1, 2, 3, 4), || {
+        called = true;
+        assert!(gl.is_enabled(gl::SCISSOR_TEST));
+        let scissor = js_sys::Int32Array::from(gl.get_parameter(gl::SCISSOR_BOX).unwrap());
+        assert_eq!(scissor.to_vec(), [1, 2, 3, 4]);
+    });
+    assert!(called);
+    assert!(!gl.is_enabled(gl::SCISSOR_TEST));
+    Ok(())
+}
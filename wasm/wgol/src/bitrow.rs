@@ -0,0 +1,137 @@
+//! 1行分のセルを64bitワードに詰めてまとめて更新するための補助関数
+//!
+//! FixedBitSetは行の境界がワードに揃っていないため、行単位でu64の配列に切り出してから計算する
+
+const BLOCK_BITS: usize = usize::BITS as usize;
+
+// 下位nビットが立ったマスク
+fn low_mask(n: usize) -> u64 {
+    if n >= 64 {
+        u64::MAX
+    } else {
+        (1 << n) - 1
+    }
+}
+
+/// blocksのstartビット目からwidthビットを行として切り出す
+pub(crate) fn read_row(blocks: &[usize], start: usize, width: usize) -> Vec<u64> {
+    let mut row = vec![0u64; width.div_ceil(64)];
+    for (i, word) in row.iter_mut().enumerate() {
+        let len = (width - i * 64).min(64);
+        let mut filled = 0;
+        while filled < len {
+            let pos = start + i * 64 + filled;
+            let offset = pos % BLOCK_BITS;
+            let take = (BLOCK_BITS - offset).min(len - filled);
+            let bits = (blocks[pos / BLOCK_BITS] >> offset) as u64 & low_mask(take);
+            *word |= bits << filled;
+            filled += take;
+        }
+    }
+    row
+}
+
+/// blocksのstartビット目から行を書き込む。blocksの該当範囲は0で初期化されていること
+pub(crate) fn write_row(blocks: &mut [usize], start: usize, width: usize, row: &[u64]) {
+    for (i, word) in row.iter().enumerate() {
+        let len = (width - i * 64).min(64);
+        let mut filled = 0;
+        while filled < len {
+            let pos = start + i * 64 + filled;
+            let offset = pos % BLOCK_BITS;
+            let take = (BLOCK_BITS - offset).min(len - filled);
+            let bits = (word >> filled) & low_mask(take);
+            blocks[pos / BLOCK_BITS] |= (bits as usize) << offset;
+            filled += take;
+        }
+    }
+}
+
+// 各セルに左隣(col - 1)の値を入れる。端は反対側とつながる
+fn shift_west(row: &[u64], width: usize) -> Vec<u64> {
+    let last = row.len() - 1;
+    let mut out = (0..row.len())
+        .map(|i| {
+            let carry = if i == 0 { 0 } else { row[i - 1] >> 63 };
+            (row[i] << 1) | carry
+        })
+        .collect::<Vec<_>>();
+    out[last] &= low_mask(width - last * 64);
+    out[0] |= (row[(width - 1) / 64] >> ((width - 1) % 64)) & 1;
+    out
+}
+
+// 各セルに右隣(col + 1)の値を入れる。端は反対側とつながる
+fn shift_east(row: &[u64], width: usize) -> Vec<u64> {
+    let mut out = (0..row.len())
+        .map(|i| {
+            let carry = row.get(i + 1).map_or(0, |w| w << 63);
+            (row[i] >> 1) | carry
+        })
+        .collect::<Vec<_>>();
+    out[(width - 1) / 64] |= (row[0] & 1) << ((width - 1) % 64);
+    out
+}
+
+/// 上下の行と自身の行から次世代の行を計算する
+///
+/// 周囲8セルの生存数をビットスライスの4bitカウンタで数え、規則のビットマスクと照合する
+pub(crate) fn next_row(
+    up: &[u64],
+    mid: &[u64],
+    down: &[u64],
+    width: usize,
+    birth: u16,
+    survival: u16,
+) -> Vec<u64> {
+    let neighbors = [
+        shift_west(up, width),
+        up.to_vec(),
+        shift_east(up, width),
+        shift_west(mid, width),
+        shift_east(mid, width),
+        shift_west(down, width),
+        down.to_vec(),
+        shift_east(down, width),
+    ];
+
+    let last = mid.len() - 1;
+    (0..mid.len())
+        .map(|i| {
+            // 生存数の各ビットを別々のワードで持つ
+            let mut count = [0u64; 4];
+            for n in neighbors.iter() {
+                let mut carry = n[i];
+                for plane in count.iter_mut() {
+                    let next_carry = *plane & carry;
+                    *plane ^= carry;
+                    carry = next_carry;
+                }
+            }
+
+            let mut born = 0;
+            let mut survive = 0;
+            for k in 0..=8 {
+                let eq = count
+                    .iter()
+                    .enumerate()
+                    .fold(u64::MAX, |acc, (bit, plane)| {
+                        acc & if k & (1 << bit) != 0 { *plane } else { !*plane }
+                    });
+                if birth & (1 << k) != 0 {
+                    born |= eq;
+                }
+                if survival & (1 << k) != 0 {
+                    survive |= eq;
+                }
+            }
+
+            let next = (mid[i] & survive) | (!mid[i] & born);
+            if i == last {
+                next & low_mask(width - last * 64)
+            } else {
+                next
+            }
+        })
+        .collect()
+}
@@ -1,4 +1,6 @@
+mod bitrow;
 mod error;
+mod rle;
 mod utils;
 mod webgl;
 
@@ -9,10 +11,10 @@ use gloo_net::{
 };
 use gloo_timers::future::TimeoutFuture;
 use js_sys::Math::random;
-use std::{cell::RefCell, fmt, rc::Rc};
+use std::{cell::RefCell, collections::VecDeque, fmt, rc::Rc};
 use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
 use wasm_bindgen::prelude::*;
-use wasm_utils::animation::AnimationLoop;
+use wasm_utils::animation::{AnimationLoop, FrameStats};
 use web_sys::{CanvasRenderingContext2d, HtmlCanvasElement, WebGl2RenderingContext as gl};
 use webgl::{
     camera::{Camera, ViewMatrix},
@@ -51,7 +53,13 @@ pub struct GolBuilder {
     cell_size: u32,
     canvas: web_sys::HtmlCanvasElement,
     play_button: web_sys::HtmlButtonElement,
-    fps: web_sys::HtmlElement,
+    step_button: Option<web_sys::HtmlButtonElement>,
+    fps: Option<web_sys::HtmlElement>,
+    ticks_per_frame: f32,
+}
+
+impl GolBuilder {
+    const MAX_TICKS_PER_FRAME: f32 = 100.0;
 }
 
 /// 関数をこう飽きする場合はimplにwasm_bindgenをつけてpubにする
@@ -62,7 +70,6 @@ impl GolBuilder {
         height: u32,
         canvas: web_sys::HtmlCanvasElement,
         play_button: web_sys::HtmlButtonElement,
-        fps: web_sys::HtmlElement,
     ) -> GolBuilder {
         GolBuilder {
             width,
@@ -70,10 +77,37 @@ impl GolBuilder {
             cell_size: 5,
             canvas,
             play_button,
-            fps,
+            step_button: None,
+            fps: None,
+            ticks_per_frame: 1.0,
         }
     }
 
+    /// 一時停止中に1世代だけ進めるボタンを設定する
+    pub fn with_step_button(mut self, step_button: web_sys::HtmlButtonElement) -> GolBuilder {
+        self.step_button = Some(step_button);
+        self
+    }
+
+    /// FPSの統計を表示する要素を設定する。設定しない場合は計測しない
+    pub fn with_fps(mut self, fps: web_sys::HtmlElement) -> GolBuilder {
+        self.fps = Some(fps);
+        self
+    }
+
+    /// 1秒あたりの世代数を設定する
+    ///
+    /// 60fpsを前提に1フレームあたりの世代数に換算する。重くなりすぎないように上限を設ける
+    pub fn with_play_speed(mut self, ticks_per_second: f32) -> GolBuilder {
+        self.ticks_per_frame = (ticks_per_second / 60.0).clamp(0.0, Self::MAX_TICKS_PER_FRAME);
+        self
+    }
+
+    // 1フレームで進める世代数
+    fn ticks_per_frame(&self) -> u32 {
+        self.ticks_per_frame.ceil() as u32
+    }
+
     // Universeを生成する
     fn build(&self) -> Universe {
         // set canvas size
@@ -161,6 +195,8 @@ impl From<bool> for Cell {
     }
 }
 
+pub use gol::Rule;
+
 /// ライフゲームの空間を示す
 #[wasm_bindgen]
 #[derive(Debug)]
@@ -168,6 +204,10 @@ pub struct Universe {
     width: u32,
     height: u32,
     cells: FixedBitSet,
+    rule: Rule,
+    fast_tick: bool,
+    generation: u64,
+    on_tick: Option<js_sys::Function>,
 }
 
 /// アトリビュートがなければJS側には公開されない
@@ -198,6 +238,62 @@ impl Universe {
         })
     }
 
+    /// JSで作ったセルのバイト列から新しいインスタンスを生成する
+    ///
+    /// 各セルは1bitでLSBから順に詰める。長さはceil(width * height / 8)であること
+    pub fn from_cells_js(width: u32, height: u32, data: js_sys::Uint8Array) -> Result<Universe> {
+        utils::set_panic_hook();
+        let size = width
+            .checked_mul(height)
+            .ok_or_else(|| JsError::new(&format!("universe too large: {width}x{height}")))?
+            as usize;
+        if data.length() as usize != size.div_ceil(8) {
+            return Err(JsError::new(&format!(
+                "cells length mismatch: expected {}, got {}",
+                size.div_ceil(8),
+                data.length()
+            )));
+        }
+        let mut bytes = data.to_vec();
+        // sizeを超える末尾のビットは落とす
+        if size % 8 != 0 {
+            if let Some(last) = bytes.last_mut() {
+                *last &= (1 << (size % 8)) - 1;
+            }
+        }
+        // FixedBitSetのブロック単位にまとめる
+        let blocks = bytes
+            .chunks(std::mem::size_of::<usize>())
+            .map(|chunk| {
+                let mut bytes = [0; std::mem::size_of::<usize>()];
+                bytes[..chunk.len()].copy_from_slice(chunk);
+                usize::from_le_bytes(bytes)
+            })
+            .collect::<Vec<_>>();
+        Ok(Universe {
+            width,
+            height,
+            cells: FixedBitSet::with_capacity_and_blocks(size, blocks),
+            rule: Rule::default(),
+            fast_tick: false,
+            generation: 0,
+            on_tick: None,
+        })
+    }
+
+    /// セルの状態を[Self::from_cells_js]で読み込めるバイト列で返す
+    pub fn serialize_cells(&self) -> Vec<u8> {
+        let size = self.cells.len();
+        let mut bytes = self
+            .cells
+            .as_slice()
+            .iter()
+            .flat_map(|block| block.to_le_bytes())
+            .collect::<Vec<_>>();
+        bytes.truncate(size.div_ceil(8));
+        bytes
+    }
+
     fn new_inner(width: u32, height: u32, rule: impl Fn(usize) -> Cell) -> Universe {
         let size = (width * height) as usize;
         let mut cells = FixedBitSet::with_capacity(size);
@@ -211,6 +307,10 @@ impl Universe {
             width,
             height,
             cells,
+            rule: Rule::default(),
+            fast_tick: false,
+            generation: 0,
+            on_tick: None,
         }
     }
 
@@ -227,6 +327,11 @@ impl Universe {
         self.cells.as_slice().as_ptr()
     }
 
+    /// 指定セルが生存しているかを返す
+    pub fn get_cell(&self, row: u32, col: u32) -> bool {
+        self.cells[self.get_index(row, col)]
+    }
+
     /// すべてのセルを文字列で表現して返す
     pub fn render(&self) -> String {
         self.to_string()
@@ -235,63 +340,186 @@ impl Universe {
     /// 更新関数
     pub fn tick(&mut self) {
         // let _timer = Timer::new("Universe::tick");
-        let mut next = self.cells.clone();
-        for row in 0..self.height {
-            for col in 0..self.width {
-                let idx = self.get_index(row, col);
-                let cell = self.cells[idx];
-                let live_neighbors = self.live_neighbor_count(row, col);
-
-                next.set(
-                    idx,
-                    match (cell, live_neighbors) {
-                        (true, x) if x < 2 => false,
-                        (true, 2) | (true, 3) => true,
-                        (true, x) if x > 3 => false,
-                        (false, 3) => true,
-                        (otherwise, _) => otherwise,
-                    },
-                );
-            }
+        if self.fast_tick {
+            self.tick_fast();
+            return;
         }
+        self.cells = gol::next_generation(&self.cells, self.width, self.height, self.rule);
+        self.advance_generation();
+    }
 
-        self.cells = next;
+    /// 現在の世代数。生成時は0で、[Self::tick]を呼ぶ度に1増える。JSにはBigIntで渡る
+    pub fn generation(&self) -> u64 {
+        self.generation
     }
 
-    // 特定のセルの状態を取得する
-    fn get_index(&self, row: u32, column: u32) -> usize {
-        (row * self.width + column) as usize
+    /// 世代が進む度に新しい世代数を引数として呼び出す関数を登録する
+    ///
+    /// 世代数は[Self::generation]と同じくBigIntで渡す。
+    /// 呼び出しはtickが戻った後のマイクロタスクで行うので、コールバックからこのUniverseを操作できる
+    pub fn set_on_tick(&mut self, callback: js_sys::Function) {
+        self.on_tick = Some(callback);
     }
 
-    // 指定セル周辺の行き生存セルの数を返す
-    fn live_neighbor_count(&self, row: u32, column: u32) -> u8 {
-        let mut count = 0;
-        for delta_row in [self.height - 1, 0, 1].iter().cloned() {
-            for delta_col in [self.width - 1, 0, 1].iter().cloned() {
-                if delta_row == 0 && delta_col == 0 {
-                    continue;
+    // 世代数を進めてコールバックに通知する
+    //
+    // tick中はJSからの&mut selfの借用が残っているため、通知は借用が解放された後に遅延させる
+    fn advance_generation(&mut self) {
+        self.generation += 1;
+        if let Some(callback) = self.on_tick.clone() {
+            let generation = JsValue::from(self.generation);
+            wasm_bindgen_futures::spawn_local(async move {
+                if let Err(e) = callback.call1(&JsValue::NULL, &generation) {
+                    web_sys::console::error_1(&e);
                 }
+            });
+        }
+    }
 
-                let neighbor_row = (row + delta_row) % self.height;
-                let neighbor_col = (column + delta_col) % self.width;
-                let idx = self.get_index(neighbor_row, neighbor_col);
-                count += self.cells[idx] as u8;
-            }
+    /// [Self::tick]で[Self::tick_fast]を使うかを設定する
+    pub fn set_fast_tick(&mut self, enabled: bool) {
+        self.fast_tick = enabled;
+    }
+
+    /// 64セルずつビット演算で周囲の生存数を数える更新関数
+    ///
+    /// 幅か高さが2未満の場合は端の扱いが異なるため通常の更新を行う
+    pub fn tick_fast(&mut self) {
+        if self.width < 2 || self.height < 2 {
+            let fast_tick = std::mem::replace(&mut self.fast_tick, false);
+            self.tick();
+            self.fast_tick = fast_tick;
+            return;
         }
-        count
+        let width = self.width as usize;
+        let height = self.height as usize;
+        let blocks = self.cells.as_slice();
+        let rows = (0..height)
+            .map(|row| bitrow::read_row(blocks, row * width, width))
+            .collect::<Vec<_>>();
+
+        let mut next = FixedBitSet::with_capacity(width * height);
+        for row in 0..height {
+            let up = &rows[(row + height - 1) % height];
+            let down = &rows[(row + 1) % height];
+            let next_row = bitrow::next_row(
+                up,
+                &rows[row],
+                down,
+                width,
+                self.rule.birth,
+                self.rule.survival,
+            );
+            bitrow::write_row(next.as_mut_slice(), row * width, width, &next_row);
+        }
+
+        self.cells = next;
+        self.advance_generation();
+    }
+
+    // 特定のセルの状態を取得する
+    fn get_index(&self, row: u32, column: u32) -> usize {
+        gol::index(self.width, row, column)
+    }
+
+    // 指定セルがグリッドの範囲内にあるかを返す
+    fn contains(&self, row: u32, column: u32) -> bool {
+        row < self.height && column < self.width
     }
 
     pub fn difference(&self, other: &Universe) -> usize {
         self.cells.difference_count(&other.cells)
     }
 
+    /// 更新規則を誕生と生存のビットマスクで設定する
+    pub fn set_rule(&mut self, birth_bits: u16, survival_bits: u16) {
+        self.rule = Rule {
+            birth: birth_bits,
+            survival: survival_bits,
+        };
+    }
+
     /// 指定セルの状態を反転する
     pub fn toggle_cell(&mut self, row: u32, column: u32) {
         let idx = self.get_index(row, column);
         let cell = *Cell::from(self.cells[idx]).toggle();
         self.cells.set(idx, cell.into());
+        #[cfg(feature = "debug_toggle")]
         log!("toggle_cell: [{}, {}] = {:?}", row, column, cell);
     }
+
+    /// 指定した矩形領域のセルの状態を反転する
+    ///
+    /// ブラシのように連続で呼ばれることを想定し、ログは出力しない。範囲外は無視する
+    pub fn toggle_region(&mut self, row: u32, col: u32, height: u32, width: u32) {
+        let row_end = row.saturating_add(height).min(self.height);
+        let col_end = col.saturating_add(width).min(self.width);
+        for r in row..row_end {
+            for c in col..col_end {
+                let idx = self.get_index(r, c);
+                self.cells.toggle(idx);
+            }
+        }
+    }
+
+    /// 指定セルの状態を設定する
+    ///
+    /// 範囲外は無視する
+    pub fn set_cell_state(&mut self, row: u32, col: u32, alive: bool) {
+        if !self.contains(row, col) {
+            return;
+        }
+        let idx = self.get_index(row, col);
+        self.cells.set(idx, alive);
+    }
+
+    /// 大きさを変更する
+    ///
+    /// 重なる領域のセルは左上を基準に引き継ぎ、新しく増えた領域は死滅状態にする。
+    /// セル数が溢れる大きさはエラーにして状態を変えない
+    pub fn resize(&mut self, new_width: u32, new_height: u32) -> Result<()> {
+        if new_width == self.width && new_height == self.height {
+            return Ok(());
+        }
+        let size = (new_width as usize)
+            .checked_mul(new_height as usize)
+            .ok_or_else(|| {
+                JsError::new(&format!("universe too large: {new_width}x{new_height}"))
+            })?;
+        let mut cells = FixedBitSet::with_capacity(size);
+        for row in 0..self.height.min(new_height) {
+            for col in 0..self.width.min(new_width) {
+                let idx = row as usize * new_width as usize + col as usize;
+                cells.set(idx, self.cells[self.get_index(row, col)]);
+            }
+        }
+        self.width = new_width;
+        self.height = new_height;
+        self.cells = cells;
+        Ok(())
+    }
+
+    /// 行毎の生存セル数を返す
+    pub fn row_density(&self) -> js_sys::Uint32Array {
+        js_sys::Uint32Array::from(self.generation_statistics().row_counts.as_slice())
+    }
+
+    /// 列毎の生存セル数を返す
+    pub fn col_density(&self) -> js_sys::Uint32Array {
+        js_sys::Uint32Array::from(self.generation_statistics().col_counts.as_slice())
+    }
+
+    /// 指定セルとつながった同じ状態の領域を反転する
+    ///
+    /// aliveがtrueなら生存セルの島を消し、falseなら空白の領域を埋める
+    pub fn flood_fill_at(&mut self, row: u32, col: u32, alive: bool) {
+        let (target, replacement) = if alive {
+            (Cell::Alive, Cell::Dead)
+        } else {
+            (Cell::Dead, Cell::Alive)
+        };
+        self.flood_fill(row, col, target, replacement);
+    }
 }
 
 impl fmt::Display for Universe {
@@ -315,6 +543,13 @@ impl fmt::Display for Universe {
 }
 
 impl Universe {
+    /// 更新規則を指定して新しいインスタンスを生成する
+    pub fn new_with_rule(width: u32, height: u32, rule: Rule) -> Universe {
+        let mut uni = Universe::new(width, height);
+        uni.rule = rule;
+        uni
+    }
+
     /// 幅を設定する。(u32, u32)はWASMの制約により使えないのでwasm_bindgenを使わない
     pub fn set_cells(&mut self, cells: &[(u32, u32)]) {
         for (row, col) in cells.iter().cloned() {
@@ -322,6 +557,141 @@ impl Universe {
             self.cells.set(idx, Cell::Alive.bool());
         }
     }
+
+    /// 指定セルから周囲8セルでつながったtargetの状態のセルをreplacementに置き換える
+    ///
+    /// 端は反対側とつながっているものとして扱う
+    pub fn flood_fill(&mut self, row: u32, col: u32, target: Cell, replacement: Cell) {
+        if target == replacement || self.get_cell(row, col) != target.bool() {
+            return;
+        }
+        let mut queue = VecDeque::new();
+        self.cells.set(self.get_index(row, col), replacement.bool());
+        queue.push_back((row, col));
+        while let Some((row, col)) = queue.pop_front() {
+            for (r, c) in self.neighbors(row, col) {
+                let idx = self.get_index(r, c);
+                if self.cells[idx] == target.bool() {
+                    self.cells.set(idx, replacement.bool());
+                    queue.push_back((r, c));
+                }
+            }
+        }
+    }
+
+    /// 生存セルの数と行、列毎の分布を集計する
+    pub fn generation_statistics(&self) -> GenerationStats {
+        let mut stats = GenerationStats {
+            alive: 0,
+            row_counts: vec![0; self.height as usize],
+            col_counts: vec![0; self.width as usize],
+        };
+        for idx in self.cells.ones() {
+            stats.alive += 1;
+            stats.row_counts[idx / self.width as usize] += 1;
+            stats.col_counts[idx % self.width as usize] += 1;
+        }
+        stats
+    }
+
+    /// 上下左右の4セルでつながった生存セルの島の数を返す
+    ///
+    /// 斜めに接しているだけのセルは別の島として数える
+    pub fn count_components(&self) -> u32 {
+        self.component_sizes().len() as u32
+    }
+
+    /// 最も大きい生存セルの島のセル数を返す
+    pub fn largest_component_size(&self) -> u32 {
+        self.component_sizes().into_iter().max().unwrap_or(0)
+    }
+
+    // 生存セルの島毎のセル数を返す
+    fn component_sizes(&self) -> Vec<u32> {
+        let mut uf = UnionFind::new(self.cells.len());
+        for idx in self.cells.ones() {
+            let (row, col) = (idx as u32 / self.width, idx as u32 % self.width);
+            for (r, c) in self.orthogonal_neighbors(row, col) {
+                let n = self.get_index(r, c);
+                if self.cells[n] {
+                    uf.union(idx, n);
+                }
+            }
+        }
+        let mut sizes = vec![0; self.cells.len()];
+        for idx in self.cells.ones() {
+            sizes[uf.find(idx)] += 1;
+        }
+        sizes.into_iter().filter(|s| *s > 0).collect()
+    }
+
+    // 上下左右の4セルの座標を返す
+    fn orthogonal_neighbors(&self, row: u32, column: u32) -> impl Iterator<Item = (u32, u32)> {
+        let (height, width) = (self.height, self.width);
+        [(height - 1, 0), (1, 0), (0, width - 1), (0, 1)]
+            .into_iter()
+            .map(move |(delta_row, delta_col)| {
+                ((row + delta_row) % height, (column + delta_col) % width)
+            })
+    }
+
+    // 周囲8セルの座標を返す
+    fn neighbors(&self, row: u32, column: u32) -> impl Iterator<Item = (u32, u32)> {
+        let (height, width) = (self.height, self.width);
+        [height - 1, 0, 1].into_iter().flat_map(move |delta_row| {
+            [width - 1, 0, 1]
+                .into_iter()
+                .filter(move |delta_col| delta_row != 0 || *delta_col != 0)
+                .map(move |delta_col| ((row + delta_row) % height, (column + delta_col) % width))
+        })
+    }
+}
+
+/// ある世代の生存セルの分布
+#[derive(Debug, Clone, PartialEq)]
+pub struct GenerationStats {
+    pub alive: u32,
+    pub row_counts: Vec<u32>,
+    pub col_counts: Vec<u32>,
+}
+
+/// 連結成分を求めるための素集合データ構造
+struct UnionFind {
+    parent: Vec<usize>,
+    rank: Vec<u8>,
+}
+
+impl UnionFind {
+    fn new(size: usize) -> Self {
+        Self {
+            parent: (0..size).collect(),
+            rank: vec![0; size],
+        }
+    }
+
+    fn find(&mut self, mut x: usize) -> usize {
+        while self.parent[x] != x {
+            // 経路を半分に縮める
+            self.parent[x] = self.parent[self.parent[x]];
+            x = self.parent[x];
+        }
+        x
+    }
+
+    fn union(&mut self, a: usize, b: usize) {
+        let (a, b) = (self.find(a), self.find(b));
+        if a == b {
+            return;
+        }
+        match self.rank[a].cmp(&self.rank[b]) {
+            std::cmp::Ordering::Less => self.parent[a] = b,
+            std::cmp::Ordering::Greater => self.parent[b] = a,
+            std::cmp::Ordering::Equal => {
+                self.parent[b] = a;
+                self.rank[a] += 1;
+            }
+        }
+    }
 }
 
 pub struct Timer<'a> {
@@ -347,27 +717,24 @@ impl Drop for Timer<'_> {
 /// 実行プロセス全体を関数に閉じ込めたほうが取り回ししやすい
 #[wasm_bindgen]
 pub fn golstart(gb: GolBuilder) -> Result<()> {
-    // JS側の指示はchannel経由で受け取る
-    let (sender, mut recv_p, mut recv_c) = Sender::new();
-
     // UniverseをRcでラップして、非同期taskからアクセスできるようにする
     let uni = Rc::new(RefCell::new(gb.build()));
 
+    // JS側の指示はchannel経由で受け取る
+    let (sender, mut recv_p, mut recv_c) = {
+        let uni = uni.borrow();
+        Sender::new(uni.width(), uni.height())
+    };
+
     // アニメーション更新クロージャ
     // 開始停止が難しいので、良いラップ方法を考えたい。非同期タスクとして見るのが良い?
     let closure = Rc::new(RefCell::new(None));
-    // 描画処理
-    let drawer = Drawer::default();
-
-    let context = gb
-        .canvas
-        .get_context("2d")
-        .unwrap()
-        .unwrap()
-        .dyn_into::<CanvasRenderingContext2d>()
-        .unwrap();
+    // 描画処理。差分描画のために直前の状態を共有する
+    let draw = universe_drawer(&gb.canvas, &uni.borrow(), gb.cell_size)?;
     let play_btn = gb.play_button.clone();
-    let mut fps = Fps::new(gb.fps.clone());
+    let step_btn = gb.step_button.clone();
+    let ticks = gb.ticks_per_frame();
+    let mut fps = gb.fps.clone().map(Fps::new);
 
     gb.gol(sender.c_ctrl.clone());
 
@@ -379,21 +746,12 @@ pub fn golstart(gb: GolBuilder) -> Result<()> {
     let p_ctrl = p.clone();
     let cls_ctrl = closure.clone();
     let uni_ctrl = uni.clone();
+    let draw_ctrl = draw.clone();
     wasm_bindgen_futures::spawn_local(async move {
         loop {
             tokio::select! {
                 Some((ctrl, point)) = recv_c.recv() => {
-                    match ctrl {
-                        CellControl::Alive => {
-                            uni_ctrl.borrow_mut().cells.set(uni_ctrl.borrow().get_index(point.y, point.x), Cell::Alive.into());
-                        }
-                        CellControl::Dead => {
-                            uni_ctrl.borrow_mut().cells.set(uni_ctrl.borrow().get_index(point.y, point.x), Cell::Dead.into());
-                        },
-                        CellControl::Toggle => {
-                            uni_ctrl.borrow_mut().toggle_cell(point.y, point.x);
-                        }
-                    }
+                    apply_cell_control(&mut uni_ctrl.borrow_mut(), ctrl, point);
                 }
                 Some(x) = recv_p.recv() => {
                     match x {
@@ -410,6 +768,10 @@ pub fn golstart(gb: GolBuilder) -> Result<()> {
                             }
                             *p_ctrl.borrow_mut() = None;
                         }
+                        PlayControl::Step => {
+                            // 次のフレームは予約せずに1世代だけ進めて描画する
+                            step_universe(&uni_ctrl, &*draw_ctrl);
+                        }
                     }
                 }
 
@@ -423,10 +785,11 @@ pub fn golstart(gb: GolBuilder) -> Result<()> {
     *closure_clone.borrow_mut() = Some(Closure::<
         dyn FnMut(f64) -> std::result::Result<i32, JsValue>,
     >::new(move |_time| {
-        uni.borrow_mut().tick();
-        drawer.draw_cells(&context, &uni.borrow());
-        drawer.draw_grid(&context);
-        fps.render();
+        tick_frame(ticks, || uni.borrow_mut().tick());
+        draw(&uni.borrow());
+        if let Some(fps) = fps.as_mut() {
+            fps.render();
+        }
         let res = request_animation_frame(closure.borrow().as_ref().unwrap());
         match res {
             Ok(handle) => {
@@ -439,10 +802,75 @@ pub fn golstart(gb: GolBuilder) -> Result<()> {
     *p.borrow_mut() =
         Some(request_animation_frame(closure_clone.borrow().as_ref().unwrap()).unwrap());
 
-    play_button_start(play_btn, sender);
+    play_button_start(play_btn, step_btn, sender);
     Ok(())
 }
 
+// Universeを描画する関数
+type DrawFn = Rc<dyn Fn(&Universe)>;
+
+// 描画する関数を作る。webgl featureではWebGL2のインスタンス描画を使う
+fn universe_drawer(canvas: &HtmlCanvasElement, uni: &Universe, cell_size: u32) -> Result<DrawFn> {
+    if cfg!(feature = "webgl") {
+        webgl_drawer(canvas, uni, cell_size)
+    } else {
+        canvas2d_drawer(canvas, cell_size)
+    }
+}
+
+// Canvas2Dで描画する関数を作る
+fn canvas2d_drawer(canvas: &HtmlCanvasElement, cell_size: u32) -> Result<DrawFn> {
+    let context = canvas
+        .get_context("2d")
+        .unwrap()
+        .unwrap()
+        .dyn_into::<CanvasRenderingContext2d>()
+        .unwrap();
+    let drawer = Drawer {
+        cell_size: cell_size as f64,
+        ..Drawer::default()
+    };
+    Ok(Rc::new(move |uni| {
+        drawer.draw_diff(&context, uni);
+        drawer.draw_grid(&context);
+    }))
+}
+
+// WebGL2のインスタンス描画で描画する関数を作る
+fn webgl_drawer(canvas: &HtmlCanvasElement, uni: &Universe, cell_size: u32) -> Result<DrawFn> {
+    use webgl::gol_shader::DrawerWebGl;
+    let ctx = Context::new(canvas.clone(), DrawerWebGl::GRID_COLOR)?;
+    let drawer = DrawerWebGl::new(&ctx, uni)?.with_cell_size(cell_size);
+    Ok(Rc::new(move |uni| drawer.draw(uni)))
+}
+
+// セルの状態変更指示を適用する
+//
+// 範囲外の位置は無視する
+fn apply_cell_control(uni: &mut Universe, ctrl: CellControl, point: Point) {
+    if !uni.contains(point.y, point.x) {
+        return;
+    }
+    match ctrl {
+        CellControl::Alive => uni.set_cell_state(point.y, point.x, Cell::Alive.into()),
+        CellControl::Dead => uni.set_cell_state(point.y, point.x, Cell::Dead.into()),
+        CellControl::Toggle => uni.toggle_cell(point.y, point.x),
+    }
+}
+
+// 1世代だけ進めて描画する
+fn step_universe(uni: &RefCell<Universe>, draw: impl FnOnce(&Universe)) {
+    uni.borrow_mut().tick();
+    draw(&uni.borrow());
+}
+
+// 1フレーム分の世代を進める
+fn tick_frame(ticks: u32, mut tick: impl FnMut()) {
+    for _ in 0..ticks {
+        tick();
+    }
+}
+
 // 次のアニメーションフレームをリクエストする
 fn request_animation_frame(
     closure: &Closure<dyn FnMut(f64) -> std::result::Result<i32, JsValue>>,
@@ -467,8 +895,8 @@ struct Point {
 
 // セルの状態変更指示
 // enumはC-Styleのみサポート
-#[derive(Debug)]
-#[allow(dead_code)]
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
 enum CellControl {
     Alive,
     Dead,
@@ -481,6 +909,8 @@ enum CellControl {
 pub enum PlayControl {
     Play,
     Pause,
+    /// 再生状態を変えずに1世代だけ進める
+    Step,
 }
 
 // JSからの指示を受け取るための構造体
@@ -488,24 +918,70 @@ pub enum PlayControl {
 pub struct Sender {
     p_ctrl: mpsc::UnboundedSender<PlayControl>,
     c_ctrl: mpsc::UnboundedSender<(CellControl, Point)>,
+    // 受け付けるセル位置の範囲
+    width: u32,
+    height: u32,
 }
 
 /// JSからのWasmに指示を飛ばすための構造体
 #[wasm_bindgen]
 impl Sender {
-    fn new() -> (
+    fn new(
+        width: u32,
+        height: u32,
+    ) -> (
         Self,
         mpsc::UnboundedReceiver<PlayControl>,
         mpsc::UnboundedReceiver<(CellControl, Point)>,
     ) {
         let (p_ctrl, recv_p) = mpsc::unbounded_channel();
         let (c_ctrl, recv_c) = mpsc::unbounded_channel();
-        (Sender { p_ctrl, c_ctrl }, recv_p, recv_c)
+        let sender = Sender {
+            p_ctrl,
+            c_ctrl,
+            width,
+            height,
+        };
+        (sender, recv_p, recv_c)
     }
 
     pub fn play(&self, ctrl: PlayControl) {
         self.p_ctrl.send(ctrl).unwrap();
     }
+
+    /// 複数のセル変更指示をまとめて送信する
+    ///
+    /// 各要素は`{ctrl: "toggle"|"alive"|"dead", x: number, y: number}`の形式。
+    /// グリッドの範囲外の位置が含まれる場合は何も送信せずにエラーを返す
+    pub fn apply_cells(&self, mutations: js_sys::Array) -> Result<()> {
+        let mutations = mutations
+            .iter()
+            .map(serde_wasm_bindgen::from_value::<CellMutation>)
+            .collect::<std::result::Result<Vec<_>, _>>()?;
+        if let Some(m) = mutations
+            .iter()
+            .find(|m| m.x >= self.width || m.y >= self.height)
+        {
+            return Err(JsError::new(&format!(
+                "cell ({}, {}) is out of the {}x{} grid",
+                m.x, m.y, self.width, self.height
+            )));
+        }
+        for m in mutations {
+            self.c_ctrl
+                .send((m.ctrl, Point { x: m.x, y: m.y }))
+                .map_err(|_| JsError::new("Failed to send CellControl"))?;
+        }
+        Ok(())
+    }
+}
+
+// JSから受け取るセル変更指示
+#[derive(Debug, serde::Deserialize)]
+struct CellMutation {
+    ctrl: CellControl,
+    x: u32,
+    y: u32,
 }
 
 // CanbasContext2Dで描画する実装
@@ -513,10 +989,51 @@ struct Drawer {
     alive_color: &'static str,
     dead_color: &'static str,
     cell_size: f64,
+    // 前回描画したセルの状態
+    prev: RefCell<Option<FixedBitSet>>,
 }
 
 impl Drawer {
+    /// 前回描画から状態が変わったセルだけを描き直す
+    ///
+    /// 初回や大きさが変わった場合はすべて描画する
+    fn draw_diff(&self, ctx: &CanvasRenderingContext2d, uni: &Universe) {
+        let Some(changed) = self.changed_cells(uni) else {
+            self.draw_cells(ctx, uni);
+            return;
+        };
+
+        for (color, state) in [(self.alive_color, true), (self.dead_color, false)] {
+            ctx.set_fill_style(&color.into());
+            for &idx in changed.iter().filter(|&&idx| uni.cells[idx] == state) {
+                self.fill_cell(ctx, idx as u32 / uni.width, idx as u32 % uni.width);
+            }
+        }
+    }
+
+    // 前回描画時から状態が変わったセルのインデックスを返し、現在の状態を記録する
+    fn changed_cells(&self, uni: &Universe) -> Option<Vec<usize>> {
+        let mut prev = self.prev.borrow_mut();
+        let changed = prev
+            .as_ref()
+            .filter(|p| p.len() == uni.cells.len())
+            .map(|p| p.symmetric_difference(&uni.cells).collect());
+        *prev = Some(uni.cells.clone());
+        changed
+    }
+
+    fn fill_cell(&self, ctx: &CanvasRenderingContext2d, row: u32, col: u32) {
+        let cell_size = self.cell_size;
+        ctx.fill_rect(
+            col as f64 * (cell_size + 1.0) + 1.0,
+            row as f64 * (cell_size + 1.0) + 1.0,
+            cell_size,
+            cell_size,
+        );
+    }
+
     fn draw_cells(&self, ctx: &CanvasRenderingContext2d, uni: &Universe) {
+        *self.prev.borrow_mut() = Some(uni.cells.clone());
         let cell_size = self.cell_size;
         ctx.set_fill_style(&self.alive_color.into());
 
@@ -585,6 +1102,7 @@ impl Default for Drawer {
             alive_color: "#000000",
             dead_color: "#FFFFFF",
             cell_size: 5.0,
+            prev: RefCell::new(None),
         }
     }
 }
@@ -616,13 +1134,18 @@ pub fn webgl_start(canvas: HtmlCanvasElement) -> Result<()> {
     Ok(())
 }
 
-fn play_button_start(btn: web_sys::HtmlButtonElement, sender: Sender) {
+fn play_button_start(
+    btn: web_sys::HtmlButtonElement,
+    step_btn: Option<web_sys::HtmlButtonElement>,
+    sender: Sender,
+) {
     let sender = Rc::new(RefCell::new(sender));
     let ctx = Rc::new(RefCell::new(btn));
     let is_paused = Rc::new(RefCell::new(true));
     let is_paused_clone = Rc::clone(&is_paused);
     let sender_clone = sender.clone();
     let ctx_clone = ctx.clone();
+    let step_btn_clone = step_btn.clone();
     let closure = Closure::wrap(Box::new(move || {
         let is_paused = *is_paused_clone.borrow();
         if is_paused {
@@ -632,6 +1155,10 @@ fn play_button_start(btn: web_sys::HtmlButtonElement, sender: Sender) {
             sender_clone.borrow().play(PlayControl::Pause);
             ctx_clone.borrow().set_text_content(Some("▶"));
         }
+        // ステップ実行は一時停止中のみ表示する
+        if let Some(step_btn) = step_btn_clone.as_ref() {
+            step_btn.set_hidden(is_paused);
+        }
         *is_paused_clone.borrow_mut() = !is_paused;
     }) as Box<dyn FnMut()>);
 
@@ -640,6 +1167,19 @@ fn play_button_start(btn: web_sys::HtmlButtonElement, sender: Sender) {
         .unwrap();
     closure.forget();
 
+    if let Some(step_btn) = step_btn {
+        let sender_clone = sender.clone();
+        let closure = Closure::wrap(Box::new(move || {
+            sender_clone.borrow().play(PlayControl::Step);
+        }) as Box<dyn FnMut()>);
+        step_btn
+            .add_event_listener_with_callback("click", closure.as_ref().unchecked_ref())
+            .unwrap();
+        closure.forget();
+        // 再生状態で開始するので隠しておく
+        step_btn.set_hidden(true);
+    }
+
     // start play
     sender.borrow().play(PlayControl::Play);
     ctx.borrow().set_text_content(Some("⏸"));
@@ -649,7 +1189,8 @@ struct Fps {
     element: web_sys::HtmlElement,
     performance: web_sys::Performance,
     frames: Vec<f64>,
-    last_ts: f64,
+    last_ts: Option<f64>,
+    stats: FrameStats,
 }
 
 impl Fps {
@@ -659,14 +1200,19 @@ impl Fps {
             element: fps,
             performance,
             frames: Vec::new(),
-            last_ts: 0.0,
+            last_ts: None,
+            stats: FrameStats::new(),
         }
     }
     fn render(&mut self) {
         let now = self.performance.now();
-        let delta = now - self.last_ts;
-        self.last_ts = now;
+        // 初回は前回の時刻が無いので記録しない
+        let Some(last_ts) = self.last_ts.replace(now) else {
+            return;
+        };
+        let delta = now - last_ts;
         let fps = 1000.0 / delta;
+        self.stats.record(delta);
         self.frames.push(fps);
         if self.frames.len() > 60 {
             self.frames.remove(0);
@@ -678,12 +1224,19 @@ impl Fps {
             .iter()
             .cloned()
             .fold(f64::NEG_INFINITY, f64::max);
+        let (p50, p95, p99) = (
+            self.stats.p50_ms(),
+            self.stats.p95_ms(),
+            self.stats.p99_ms(),
+        );
         self.element.set_inner_text(&format!(
             r#"Frames per Second:
            latest = {fps:.3}
   avg of last 100 = {avg:.3}
   min of last 100 = {min:.3}
-  max of last 100 = {max:.3}"#
+  max of last 100 = {max:.3}
+Frame time (ms):
+  p50 = {p50:.2}, p95 = {p95:.2}, p99 = {p99:.2}"#
         ));
     }
 }
@@ -778,7 +1331,7 @@ fn start_websocket(url: &str) -> Result<()> {
 pub fn webgl_interaction(
     canvas: HtmlCanvasElement,
     ctrl: ParticleControl,
-) -> std::result::Result<(), JsValue> {
+) -> std::result::Result<ParticleHandle, JsValue> {
     use crate::webgl::interaction::*;
     canvas.set_width(512);
     canvas.set_height(512);
@@ -787,7 +1340,10 @@ pub fn webgl_interaction(
     let gl = ctx.gl().clone();
 
     let res = Resolution::DEFAULT;
-    let mut shader = ParticleShader::new(&ctx, res, ctrl)?;
+    let shader = Rc::new(RefCell::new(ParticleShader::new(&ctx, res, ctrl)?));
+    let handle = ParticleHandle {
+        shader: shader.clone(),
+    };
 
     // mouse event
     let canvas_ctx = Rc::new(RefCell::new(canvas));
@@ -801,6 +1357,7 @@ pub fn webgl_interaction(
         let t = timestamp_msec as f32;
         let color = hsva(t / 30., 1.0, 1.0, 0.5);
         gl.clear(gl::COLOR_BUFFER_BIT);
+        let mut shader = shader.borrow_mut();
         shader.set_color(color);
 
         let event = {
@@ -827,7 +1384,23 @@ pub fn webgl_interaction(
     a.start();
     a.forget();
 
-    Ok(())
+    Ok(handle)
+}
+
+/// CPUで計算するパーティクルをJSから操作するためのハンドル
+#[wasm_bindgen]
+pub struct ParticleHandle {
+    shader: Rc<RefCell<webgl::interaction::ParticleShader>>,
+}
+
+#[wasm_bindgen]
+impl ParticleHandle {
+    /// 全てのパーティクルを(x, y)に集めて静止させる。座標はGL空間
+    pub fn reset(&self, x: f32, y: f32) {
+        self.shader
+            .borrow_mut()
+            .reset(webgl::interaction::Point::new(x, y));
+    }
 }
 
 #[derive(Debug)]
@@ -919,8 +1492,58 @@ impl MouseEventHandler {
     }
 }
 
+/// GPGPUパーティクルの状態をJSから参照するためのハンドル
+#[wasm_bindgen]
+pub struct ParticleGpgpuHandle {
+    ctx: Context,
+    shader: Rc<RefCell<webgl::interaction::ParticleGpgpuShader>>,
+}
+
+#[wasm_bindgen]
+impl ParticleGpgpuHandle {
+    /// 位置と速度をxyzwの順に並べた配列で取得する
+    pub fn snapshot(&self) -> Result<js_sys::Float32Array> {
+        self.shader.borrow().snapshot_array(&self.ctx)
+    }
+
+    /// カールノイズによる流れ場で動かす
+    pub fn set_curl_noise_mode(&self, enabled: bool, frequency: f32, speed: f32) {
+        self.shader
+            .borrow_mut()
+            .set_curl_noise_mode(enabled, frequency, speed);
+    }
+
+    /// 全てのパーティクルを(x, y)に集めて静止させる。座標はGL空間
+    pub fn reset(&self, x: f32, y: f32) {
+        self.shader
+            .borrow_mut()
+            .reset(&self.ctx, webgl::interaction::Point::new(x, y));
+    }
+}
+
 #[wasm_bindgen]
-pub fn webgl_interaction_gpgpu(canvas: HtmlCanvasElement, ctrl: ParticleControl) -> Result<()> {
+pub fn webgl_interaction_gpgpu(
+    canvas: HtmlCanvasElement,
+    ctrl: ParticleControl,
+) -> Result<ParticleGpgpuHandle> {
+    webgl_interaction_gpgpu_inner(canvas, ctrl, None)
+}
+
+/// シードから生成したノイズ場を加えてGPGPUパーティクルを開始する
+#[wasm_bindgen]
+pub fn webgl_interaction_gpgpu_with_noise(
+    canvas: HtmlCanvasElement,
+    ctrl: ParticleControl,
+    seed: u32,
+) -> Result<ParticleGpgpuHandle> {
+    webgl_interaction_gpgpu_inner(canvas, ctrl, Some(seed))
+}
+
+fn webgl_interaction_gpgpu_inner(
+    canvas: HtmlCanvasElement,
+    ctrl: ParticleControl,
+    noise_seed: Option<u32>,
+) -> Result<ParticleGpgpuHandle> {
     use crate::webgl::interaction::*;
     canvas.set_width(512);
     canvas.set_height(512);
@@ -954,11 +1577,21 @@ pub fn webgl_interaction_gpgpu(canvas: HtmlCanvasElement, ctrl: ParticleControl)
     }
 
     let mut shader = ParticleGpgpuShader::new(&ctx, target_res, ctrl)?;
+    if let Some(seed) = noise_seed {
+        shader = shader.with_noise_texture(&ctx, seed)?;
+    }
+    let shader = Rc::new(RefCell::new(shader));
+    let handle = ParticleGpgpuHandle {
+        ctx: ctx.clone(),
+        shader: shader.clone(),
+    };
 
     // test rendering
-    shader.update(Point::new(0., 0.), true, [1.0, 0.0, 0.0, 1.0]);
+    shader
+        .borrow_mut()
+        .update(Point::new(0., 0.), true, [1.0, 0.0, 0.0, 1.0]);
     // shader.draw_index(&gl, &target_res);
-    shader.draw(&target_res);
+    shader.borrow_mut().draw(&target_res);
 
     // mouse event
     let canvas_ctx = Rc::new(RefCell::new(canvas));
@@ -990,6 +1623,7 @@ pub fn webgl_interaction_gpgpu(canvas: HtmlCanvasElement, ctrl: ParticleControl)
             None => {}
         }
 
+        let mut shader = shader.borrow_mut();
         shader.update(*mouse_pos.borrow(), *mouse_down_flag.borrow(), color);
         shader.draw(&target_res);
         Ok(())
@@ -998,7 +1632,19 @@ pub fn webgl_interaction_gpgpu(canvas: HtmlCanvasElement, ctrl: ParticleControl)
     a.start();
     a.forget();
 
-    Ok(())
+    Ok(handle)
+}
+
+/// HSVA(色相は度数、それ以外は0.0-1.0)をRGBAに変換する
+#[wasm_bindgen]
+pub fn hsva_to_rgba(h: f32, s: f32, v: f32, a: f32) -> js_sys::Float32Array {
+    js_sys::Float32Array::from(hsva(h, s, v, a).as_slice())
+}
+
+/// RGBA(0.0-1.0)をHSVA(色相は度数、それ以外は0.0-1.0)に変換する
+#[wasm_bindgen]
+pub fn rgba_to_hsva(r: f32, g: f32, b: f32, a: f32) -> js_sys::Float32Array {
+    js_sys::Float32Array::from(rgba(r, g, b, a).as_slice())
 }
 
 fn hsva(h: f32, s: f32, v: f32, a: f32) -> [f32; 4] {
@@ -1021,3 +1667,386 @@ fn hsva(h: f32, s: f32, v: f32, a: f32) -> [f32; 4] {
         [r[i], g[i], b[i], a]
     }
 }
+
+// hsvaの逆変換
+fn rgba(r: f32, g: f32, b: f32, a: f32) -> [f32; 4] {
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let d = max - min;
+    let h = if d == 0. {
+        0.
+    } else if max == r {
+        60. * ((g - b) / d)
+    } else if max == g {
+        60. * ((b - r) / d + 2.)
+    } else {
+        60. * ((r - g) / d + 4.)
+    };
+    let h = if h < 0. { h + 360. } else { h };
+    let s = if max == 0. { 0. } else { d / max };
+    [h, s, max, a]
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use std::cell::RefCell;
+
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_step_universe() {
+        let uni = RefCell::new(Universe::new(16, 16));
+        let mut expected = Universe::new(16, 16);
+        expected.tick();
+
+        let mut draw_count = 0;
+        step_universe(&uni, |_| draw_count += 1);
+
+        // 1世代だけ進み、描画は1回
+        assert_eq!(uni.borrow().difference(&expected), 0);
+        assert_eq!(draw_count, 1);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_tick_fast() {
+        // ワード境界をまたぐ幅と、ちょうど揃う幅の両方を確認する
+        for (width, height) in [(2, 2), (5, 3), (64, 8), (70, 33), (130, 17)] {
+            let mut expected = Universe::with_random(width, height);
+            let mut fast = Universe::from_cells_js(
+                width,
+                height,
+                js_sys::Uint8Array::from(expected.serialize_cells().as_slice()),
+            )
+            .unwrap();
+            fast.set_fast_tick(true);
+            for _ in 0..8 {
+                expected.tick();
+                fast.tick();
+                assert_eq!(fast.difference(&expected), 0, "{}x{}", width, height);
+                assert_eq!(expected.difference(&fast), 0, "{}x{}", width, height);
+            }
+        }
+
+        // 規則の変更にも追従する
+        let mut expected = Universe::new(40, 40);
+        let mut fast = Universe::new(40, 40);
+        expected.set_rule(0b1001000, 0b1100);
+        fast.set_rule(0b1001000, 0b1100);
+        for _ in 0..8 {
+            expected.tick();
+            fast.tick_fast();
+            assert_eq!(fast.difference(&expected), 0);
+            assert_eq!(expected.difference(&fast), 0);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_resize() {
+        let mut uni = Universe::new(8, 6);
+        let orig = uni.render();
+        let glider = [(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)];
+
+        // 同じ大きさなら何もしない
+        uni.resize(8, 6).unwrap();
+        assert_eq!(uni.render(), orig);
+
+        // 縮小すると右下が切り捨てられる
+        uni.resize(5, 4).unwrap();
+        assert_eq!((uni.width(), uni.height()), (5, 4));
+        let expected = orig
+            .lines()
+            .take(4)
+            .map(|line| line.chars().take(5).collect::<String>() + "\n")
+            .collect::<String>();
+        assert_eq!(uni.render(), expected);
+
+        // 拡大すると増えた領域は死滅状態になる
+        let base = Universe::new(4, 4);
+        let mut grown = Universe::new(4, 4);
+        grown.resize(10, 12).unwrap();
+        assert_eq!((grown.width(), grown.height()), (10, 12));
+        for row in 0..12 {
+            for col in 0..10 {
+                let expected = row < 4 && col < 4 && base.get_cell(row, col);
+                assert_eq!(grown.get_cell(row, col), expected, "[{}, {}]", row, col);
+            }
+        }
+
+        // 空にしてから拡大しても更新できる
+        let mut cleared = Universe::new(5, 5);
+        cleared.resize(0, 0).unwrap();
+        cleared.resize(6, 6).unwrap();
+        cleared.set_cells(&glider);
+        cleared.tick();
+        assert_eq!(cleared.generation_statistics().alive, 5);
+
+        // セル数が溢れる大きさは受け付けず、元の大きさのまま
+        assert!(cleared.resize(u32::MAX, u32::MAX).is_err());
+        assert_eq!((cleared.width(), cleared.height()), (6, 6));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_rle_round_trip() {
+        for (width, height) in [(1, 1), (8, 8), (100, 37)] {
+            let mut uni = Universe::with_random(width, height);
+            uni.set_rule(0b1001000, 0b1100);
+            let rle = uni.to_rle();
+            assert!(rle.lines().all(|line| line.len() <= 70), "{}", rle);
+
+            let decoded = Universe::from_rle(&rle).unwrap();
+            assert_eq!((decoded.width(), decoded.height()), (width, height));
+            assert_eq!(decoded.rule, uni.rule);
+            assert_eq!(decoded.difference(&uni), 0);
+            assert_eq!(uni.difference(&decoded), 0);
+        }
+
+        // 生存セルが無い場合はパターン部分が空になる
+        let empty = Universe::new_inner(4, 3, |_| Cell::Dead);
+        assert_eq!(empty.to_rle(), "x = 4, y = 3, rule = B3/S23\n!\n");
+        let decoded = Universe::from_rle(&empty.to_rle()).unwrap();
+        assert_eq!((decoded.width(), decoded.height()), (4, 3));
+        assert_eq!(decoded.generation_statistics().alive, 0);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_from_rle() {
+        let rle = r#"
+            #N Glider
+            #C コメントと空白は読み飛ばす
+            x = 5, y = 4, rule = B3/S23
+            bo$2b
+            o$3o
+            !
+        "#;
+        let uni = Universe::from_rle(rle).unwrap();
+        assert_eq!((uni.width(), uni.height()), (5, 4));
+        let mut expected = Universe::new_inner(5, 4, |_| Cell::Dead);
+        expected.set_cells(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+        assert_eq!(uni.difference(&expected), 0);
+        assert_eq!(expected.difference(&uni), 0);
+        assert_eq!(uni.to_rle(), "x = 5, y = 4, rule = B3/S23\nbo$2bo$3o!\n");
+
+        assert!(Universe::from_rle("bo$2bo$3o!").is_err());
+        assert!(Universe::from_rle("x = 2, y = 2\n3o!").is_err());
+        assert!(Universe::from_rle("x = 100000, y = 100000\n!").is_err());
+        // 幅だけが大きい空間も書き出したものを読み込める
+        let wide = Universe::new(8192, 2);
+        let restored = Universe::from_rle(&wide.to_rle()).unwrap();
+        assert_eq!(wide.difference(&restored), 0);
+        assert_eq!(restored.difference(&wide), 0);
+        assert!(Universe::from_rle("x = 2, y = 2\n99999999999b!").is_err());
+        assert!(Universe::from_rle("x = 2, y = 2\n4294967295bo!").is_err());
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_generation() {
+        let received = Rc::new(std::cell::Cell::new(0));
+        let received_clone = received.clone();
+        let callback = Closure::<dyn FnMut(JsValue)>::new(move |generation| {
+            received_clone.set(u64::try_from(generation).unwrap());
+        });
+
+        let mut uni = Universe::new(16, 16);
+        assert_eq!(uni.generation(), 0);
+        uni.tick();
+        assert_eq!(uni.generation(), 1);
+        // コールバックは登録後の更新から呼ばれる
+        assert_eq!(received.get(), 0);
+
+        uni.set_on_tick(
+            callback
+                .as_ref()
+                .unchecked_ref::<js_sys::Function>()
+                .clone(),
+        );
+        uni.tick();
+        // 通知はtickが戻った後に行われる
+        assert_eq!(received.get(), 0);
+        gloo_timers::future::TimeoutFuture::new(0).await;
+        assert_eq!(received.get(), 2);
+        uni.set_fast_tick(true);
+        uni.tick();
+        assert_eq!(uni.generation(), 3);
+        gloo_timers::future::TimeoutFuture::new(0).await;
+        assert_eq!(received.get(), 3);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_from_cells_js() {
+        let mut uni = Universe::new_inner(10, 7, |_| Cell::Dead);
+        // グライダー
+        uni.set_cells(&[(0, 1), (1, 2), (2, 0), (2, 1), (2, 2)]);
+
+        let bytes = uni.serialize_cells();
+        assert_eq!(bytes.len(), 9);
+        let data = js_sys::Uint8Array::from(bytes.as_slice());
+        let restored = Universe::from_cells_js(10, 7, data).unwrap();
+        assert_eq!(restored.difference(&uni), 0);
+        assert_eq!(restored.to_string(), uni.to_string());
+
+        let short = js_sys::Uint8Array::from(&bytes[..8]);
+        assert!(Universe::from_cells_js(10, 7, short).is_err());
+
+        // 範囲外のビットが立っていても無視する
+        let mut padded = bytes.clone();
+        padded[8] |= 0xfc;
+        let data = js_sys::Uint8Array::from(padded.as_slice());
+        let restored = Universe::from_cells_js(10, 7, data).unwrap();
+        assert_eq!(restored.cells, uni.cells);
+        let empty = js_sys::Uint8Array::new_with_length(0);
+        assert!(Universe::from_cells_js(u32::MAX, 2, empty).is_err());
+    }
+
+    #[wasm_bindgen_test]
+    fn test_generation_statistics() {
+        let mut uni = Universe::new_inner(10, 6, |_| Cell::Dead);
+        for col in 0..10 {
+            uni.set_cell_state(3, col, true);
+        }
+        uni.set_cell_state(5, 2, true);
+
+        let stats = uni.generation_statistics();
+        assert_eq!(stats.alive, 11);
+        assert_eq!(stats.row_counts[3], uni.width());
+        assert_eq!(stats.row_counts, vec![0, 0, 0, 10, 0, 1]);
+        assert_eq!(stats.col_counts[2], 2);
+        assert_eq!(uni.col_density().to_vec(), stats.col_counts);
+        assert_eq!(uni.row_density().to_vec(), stats.row_counts);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_highlife_rule() {
+        let highlife = Rule {
+            birth: (1 << 3) | (1 << 6),
+            survival: (1 << 2) | (1 << 3),
+        };
+        let mut uni = Universe::new_inner(8, 8, |_| Cell::Dead);
+        uni.rule = highlife;
+        // (3, 3)の周囲に6個の生存セルを置く
+        uni.set_cells(&[(2, 2), (2, 3), (2, 4), (4, 2), (4, 3), (4, 4)]);
+
+        let mut conway = Universe::new(8, 8);
+        conway.cells = uni.cells.clone();
+        conway.tick();
+        uni.tick();
+
+        // B6によりHighLifeだけ中央が誕生する
+        assert!(uni.get_cell(3, 3));
+        assert!(!conway.get_cell(3, 3));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_changed_cells_blinker() {
+        let mut uni = Universe::new(5, 5);
+        for row in 0..5 {
+            for col in 0..5 {
+                uni.set_cell_state(row, col, false);
+            }
+        }
+        for col in 1..4 {
+            uni.set_cell_state(2, col, true);
+        }
+
+        let drawer = Drawer::default();
+        // 初回は全描画
+        assert!(drawer.changed_cells(&uni).is_none());
+        for _ in 0..3 {
+            uni.tick();
+            // 中央のセルは生存したままで、両端の2セルが消えて新たに2セルが生まれる
+            let changed = drawer.changed_cells(&uni).unwrap();
+            assert_eq!(changed.len(), 4);
+        }
+    }
+
+    #[wasm_bindgen_test]
+    async fn test_golstart_without_fps() {
+        let document = web_sys::window().unwrap().document().unwrap();
+        let create = |tag: &str| document.create_element(tag).unwrap();
+        let gb = GolBuilder::new(
+            8,
+            8,
+            create("canvas").dyn_into().unwrap(),
+            create("button").dyn_into().unwrap(),
+        );
+        assert!(gb.fps.is_none());
+        golstart(gb).unwrap();
+
+        // 数フレーム描画してもパニックしない
+        gloo_timers::future::TimeoutFuture::new(50).await;
+    }
+
+    #[wasm_bindgen_test]
+    fn test_play_speed() {
+        let document = web_sys::window().unwrap().document().unwrap();
+        let create = |tag: &str| document.create_element(tag).unwrap();
+        let gb = GolBuilder::new(
+            8,
+            8,
+            create("canvas").dyn_into().unwrap(),
+            create("button").dyn_into().unwrap(),
+        );
+        assert_eq!(gb.ticks_per_frame(), 1);
+
+        let gb = gb.with_play_speed(150.0);
+        assert_eq!(gb.ticks_per_frame, 2.5);
+        assert_eq!(gb.ticks_per_frame(), 3);
+
+        let mut count = 0;
+        tick_frame(gb.ticks_per_frame(), || count += 1);
+        assert_eq!(count, 3);
+
+        let gb = gb.with_play_speed(1e9);
+        assert_eq!(gb.ticks_per_frame(), 100);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_apply_cells() {
+        let (sender, _recv_p, mut recv_c) = Sender::new(16, 16);
+        let mut uni = Universe::new(16, 16);
+        for row in 0..uni.height() {
+            for col in 0..uni.width() {
+                uni.set_cell_state(row, col, false);
+            }
+        }
+
+        let mutations = js_sys::Array::new();
+        for i in 0..10 {
+            let m = js_sys::Object::new();
+            let ctrl = if i % 2 == 0 { "alive" } else { "toggle" };
+            js_sys::Reflect::set(&m, &"ctrl".into(), &ctrl.into()).unwrap();
+            js_sys::Reflect::set(&m, &"x".into(), &i.into()).unwrap();
+            js_sys::Reflect::set(&m, &"y".into(), &1.into()).unwrap();
+            mutations.push(&m);
+        }
+        sender.apply_cells(mutations).unwrap();
+
+        let mut count = 0;
+        while let Ok((ctrl, point)) = recv_c.try_recv() {
+            apply_cell_control(&mut uni, ctrl, point);
+            count += 1;
+        }
+        assert_eq!(count, 10);
+        for x in 0..10 {
+            assert!(uni.get_cell(1, x));
+        }
+        assert_eq!(uni.render().matches('◼').count(), 10);
+
+        // 範囲外の位置を含む場合は1件も送信しない
+        let mutation = |x: u32, y: u32| {
+            let m = js_sys::Object::new();
+            js_sys::Reflect::set(&m, &"ctrl".into(), &"alive".into()).unwrap();
+            js_sys::Reflect::set(&m, &"x".into(), &x.into()).unwrap();
+            js_sys::Reflect::set(&m, &"y".into(), &y.into()).unwrap();
+            m
+        };
+        for (x, y) in [(16, 0), (0, 16)] {
+            let mutations = js_sys::Array::of2(&mutation(0, 0), &mutation(x, y));
+            assert!(sender.apply_cells(mutations).is_err());
+        }
+        assert!(recv_c.try_recv().is_err());
+    }
+}
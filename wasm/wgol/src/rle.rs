@@ -0,0 +1,170 @@
+//! パターン共有に使われるRLE(Run Length Encoded)形式の読み書き
+//!
+//! <https://conwaylife.com/wiki/Run_Length_Encoded>
+
+use fixedbitset::FixedBitSet;
+use wasm_bindgen::prelude::*;
+
+use crate::{error::Result, Rule, Universe};
+
+// 1行の最大文字数
+const LINE_WIDTH: usize = 70;
+// 読み込めるセル数の上限。確保するメモリを抑えるため幅と高さの積で制限する
+const MAX_CELLS: u64 = 4096 * 4096;
+
+// ランの長さと記号を追記する
+fn push_run(items: &mut Vec<String>, count: u32, tag: char) {
+    if count == 0 {
+        return;
+    }
+    items.push(if count == 1 {
+        tag.to_string()
+    } else {
+        format!("{count}{tag}")
+    });
+}
+
+#[wasm_bindgen]
+impl Universe {
+    /// RLE形式の文字列に変換する
+    pub fn to_rle(&self) -> String {
+        let mut items = Vec::new();
+        // 空行はまとめて数える
+        let mut pending_rows = 0;
+        for row in 0..self.height {
+            if row > 0 {
+                pending_rows += 1;
+            }
+            let mut runs = Vec::new();
+            let mut run = (false, 0);
+            for col in 0..self.width {
+                let alive = self.cells[self.get_index(row, col)];
+                if alive != run.0 && run.1 > 0 {
+                    runs.push(run);
+                    run.1 = 0;
+                }
+                run = (alive, run.1 + 1);
+            }
+            // 行末の死滅セルは省略する
+            if run.0 {
+                runs.push(run);
+            }
+            if runs.is_empty() {
+                continue;
+            }
+            push_run(&mut items, pending_rows, '$');
+            pending_rows = 0;
+            for (alive, count) in runs {
+                push_run(&mut items, count, if alive { 'o' } else { 'b' });
+            }
+        }
+        items.push("!".to_string());
+
+        let mut rle = format!(
+            "x = {}, y = {}, rule = {}\n",
+            self.width,
+            self.height,
+            self.rule.to_rle()
+        );
+        let mut line_len = 0;
+        for item in items {
+            if line_len + item.len() > LINE_WIDTH {
+                rle.push('\n');
+                line_len = 0;
+            }
+            line_len += item.len();
+            rle.push_str(&item);
+        }
+        rle.push('\n');
+        rle
+    }
+
+    /// RLE形式の文字列から新しいインスタンスを生成する
+    ///
+    /// `#`で始まる行はコメントとして読み飛ばす。ruleが無い場合はB3/S23とする
+    pub fn from_rle(s: &str) -> Result<Universe> {
+        let mut lines = s
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty() && !line.starts_with('#'));
+        let header = lines
+            .next()
+            .ok_or_else(|| JsError::new("RLE header not found"))?;
+
+        let (mut width, mut height, mut rule) = (None, None, Rule::default());
+        for field in header.split(',') {
+            let (key, value) = field
+                .split_once('=')
+                .ok_or_else(|| JsError::new(&format!("invalid RLE header: {header}")))?;
+            let value = value.trim();
+            match key.trim() {
+                "x" => width = value.parse::<u32>().ok(),
+                "y" => height = value.parse::<u32>().ok(),
+                "rule" => {
+                    rule = Rule::from_rle(value)
+                        .ok_or_else(|| JsError::new(&format!("invalid RLE rule: {value}")))?
+                }
+                _ => {}
+            }
+        }
+        let (Some(width), Some(height)) = (width, height) else {
+            return Err(JsError::new(&format!("invalid RLE header: {header}")));
+        };
+        if width as u64 * height as u64 > MAX_CELLS {
+            return Err(JsError::new(&format!(
+                "RLE size too large: {width}x{height} (max {MAX_CELLS} cells)"
+            )));
+        }
+        let overflow = || JsError::new("RLE run count overflow");
+
+        let mut cells = FixedBitSet::with_capacity(width as usize * height as usize);
+        let (mut row, mut col) = (0u32, 0u32);
+        let mut count: Option<u32> = None;
+        'pattern: for line in lines {
+            for c in line.chars().filter(|c| !c.is_whitespace()) {
+                if let Some(d) = c.to_digit(10) {
+                    count = Some(
+                        count
+                            .unwrap_or(0)
+                            .checked_mul(10)
+                            .and_then(|n| n.checked_add(d))
+                            .ok_or_else(overflow)?,
+                    );
+                    continue;
+                }
+                let n = count.take().unwrap_or(1);
+                match c {
+                    'b' => col = col.checked_add(n).ok_or_else(overflow)?,
+                    'o' => {
+                        let end = col.checked_add(n).ok_or_else(overflow)?;
+                        if row >= height || end > width {
+                            return Err(JsError::new(&format!(
+                                "RLE cell out of range: [{row}, {col}]"
+                            )));
+                        }
+                        for _ in 0..n {
+                            cells.insert((row * width + col) as usize);
+                            col += 1;
+                        }
+                    }
+                    '$' => {
+                        row = row.checked_add(n).ok_or_else(overflow)?;
+                        col = 0;
+                    }
+                    '!' => break 'pattern,
+                    _ => return Err(JsError::new(&format!("invalid RLE tag: {c}"))),
+                }
+            }
+        }
+
+        Ok(Universe {
+            width,
+            height,
+            cells,
+            rule,
+            fast_tick: false,
+            generation: 0,
+            on_tick: None,
+        })
+    }
+}
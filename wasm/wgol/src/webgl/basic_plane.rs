@@ -80,6 +80,8 @@ void main() {
         let gl = self.program.gl();
         gl.bind_vertex_array(Some(&self.vao.vao));
         gl.draw_elements_with_i32(gl::TRIANGLES, self.vao.index_count, gl::UNSIGNED_SHORT, 0);
+        #[cfg(debug_assertions)]
+        self.program.check_errors_assert();
     }
 }
 
@@ -0,0 +1,279 @@
+use std::cell::RefCell;
+
+use fixedbitset::FixedBitSet;
+use web_sys::WebGlUniformLocation;
+
+use webgl2::{
+    context::Context,
+    gl,
+    program::Program,
+    vertex::{Vao, VaoDefine},
+    GlPoint1d, GlPoint2d,
+};
+
+use crate::{error::Result, Universe};
+
+/// ライフゲームのセルをインスタンス描画するDrawer
+///
+/// 1つの四角形を全セル分インスタンス化し、状態が変わったセルだけVBOを書き換える
+pub struct DrawerWebGl {
+    program: Program,
+    resolution: WebGlUniformLocation,
+    cell_size: WebGlUniformLocation,
+    vao: RefCell<Vao<GolVd>>,
+    // 前回描画したセルの状態と大きさ
+    prev: RefCell<Option<(u32, FixedBitSet)>>,
+}
+
+impl DrawerWebGl {
+    const VERT: &'static str = r#"#version 300 es
+layout(location = 0) in vec2 position;
+// セルの列と行
+layout(location = 1) in vec2 offset;
+layout(location = 2) in float alive;
+// キャンバスの大きさ(px)
+uniform vec2 resolution;
+uniform float cell_size;
+
+out float v_alive;
+
+void main() {
+    // Canvas2Dと同じく1pxのグリッド線を挟んで並べる
+    vec2 px = offset * (cell_size + 1.0) + 1.0 + position * cell_size;
+    vec2 pos = px / resolution;
+    v_alive = alive;
+    gl_Position = vec4(pos.x * 2.0 - 1.0, 1.0 - pos.y * 2.0, 0.0, 1.0);
+}
+"#;
+
+    const FRAG: &'static str = r#"#version 300 es
+precision mediump float;
+
+in float v_alive;
+out vec4 fragmentColor;
+
+const vec4 alive_color = vec4(0.0, 0.0, 0.0, 1.0);
+const vec4 dead_color = vec4(1.0, 1.0, 1.0, 1.0);
+
+void main() {
+    fragmentColor = mix(dead_color, alive_color, v_alive);
+}
+"#;
+
+    // グリッド線の色。Canvas2Dの実装に合わせる
+    pub const GRID_COLOR: [f32; 4] = [0.8, 0.8, 0.8, 1.0];
+    // セルの大きさ(px)の既定値。Canvas2Dの実装に合わせる
+    const DEFAULT_CELL_SIZE: u32 = 5;
+
+    pub fn new(ctx: &Context, uni: &Universe) -> Result<Self> {
+        let program = ctx.program(Self::VERT, Self::FRAG)?;
+        let resolution = program.uniform_location("resolution")?;
+        let cell_size = program.uniform_location("cell_size")?;
+
+        let quad = [
+            GlPoint2d::new(0.0, 0.0),
+            GlPoint2d::new(1.0, 0.0),
+            GlPoint2d::new(0.0, 1.0),
+            GlPoint2d::new(1.0, 1.0),
+        ];
+        let mut vao = program.create_vao()?;
+        vao.buffer_data(GolVd::Position, &quad, gl::STATIC_DRAW);
+
+        let drawer = Self {
+            program,
+            resolution,
+            cell_size,
+            vao: RefCell::new(vao),
+            prev: RefCell::new(None),
+        };
+        drawer.upload_all(uni);
+        Ok(drawer.with_cell_size(Self::DEFAULT_CELL_SIZE))
+    }
+
+    /// セルの大きさ(px)を設定する
+    ///
+    /// クリック位置の換算と合わせるため[crate::GolBuilder]の値を渡す
+    pub fn with_cell_size(self, cell_size: u32) -> Self {
+        self.program.use_program();
+        self.program
+            .gl()
+            .uniform1f(Some(&self.cell_size), cell_size as f32);
+        self
+    }
+
+    /// 前回から状態が変わったセルだけVBOを更新して描画する
+    ///
+    /// 大きさが変わった場合はインスタンスのVBOを作り直す
+    pub fn draw(&self, uni: &Universe) {
+        let changed = self
+            .prev
+            .borrow()
+            .as_ref()
+            .filter(|(width, prev)| *width == uni.width && prev.len() == uni.cells.len())
+            .map(|(_, prev)| prev.symmetric_difference(&uni.cells).collect::<Vec<_>>());
+        match changed {
+            Some(mut changed) => {
+                changed.sort_unstable();
+                self.upload_changed(uni, &changed);
+            }
+            None => self.upload_all(uni),
+        }
+
+        let gl = self.program.gl();
+        gl.clear_color(
+            Self::GRID_COLOR[0],
+            Self::GRID_COLOR[1],
+            Self::GRID_COLOR[2],
+            Self::GRID_COLOR[3],
+        );
+        gl.clear(gl::COLOR_BUFFER_BIT);
+        self.program.use_program();
+        gl.uniform2f(
+            Some(&self.resolution),
+            gl.drawing_buffer_width() as f32,
+            gl.drawing_buffer_height() as f32,
+        );
+        self.vao.borrow().bind();
+        gl.draw_arrays_instanced(gl::TRIANGLE_STRIP, 0, 4, uni.cells.len() as i32);
+        #[cfg(debug_assertions)]
+        self.program.check_errors_assert();
+    }
+
+    // 全セルのオフセットと状態を書き込む
+    fn upload_all(&self, uni: &Universe) {
+        let offsets = (0..uni.height)
+            .flat_map(|row| (0..uni.width).map(move |col| GlPoint2d::new(col as f32, row as f32)))
+            .collect::<Vec<_>>();
+        let states = (0..uni.cells.len())
+            .map(|idx| alive(&uni.cells, idx))
+            .collect::<Vec<_>>();
+        let mut vao = self.vao.borrow_mut();
+        vao.buffer_data(GolVd::Offset, &offsets, gl::STATIC_DRAW);
+        vao.buffer_data(GolVd::Alive, &states, gl::DYNAMIC_DRAW);
+        *self.prev.borrow_mut() = Some((uni.width, uni.cells.clone()));
+    }
+
+    // 変わったセルの状態だけを書き込む。changedは昇順で、連続するセルはまとめて送る
+    fn upload_changed(&self, uni: &Universe, changed: &[usize]) {
+        let vao = self.vao.borrow();
+        let mut start = 0;
+        while start < changed.len() {
+            let mut end = start + 1;
+            while end < changed.len() && changed[end] == changed[end - 1] + 1 {
+                end += 1;
+            }
+            let states = changed[start..end]
+                .iter()
+                .map(|&idx| alive(&uni.cells, idx))
+                .collect::<Vec<_>>();
+            vao.buffer_sub_data(GolVd::Alive, &states, changed[start] as i32);
+            start = end;
+        }
+        if let Some((_, prev)) = self.prev.borrow_mut().as_mut() {
+            prev.clone_from(&uni.cells);
+        }
+    }
+}
+
+fn alive(cells: &FixedBitSet, idx: usize) -> GlPoint1d {
+    GlPoint1d::new(if cells[idx] { 1.0 } else { 0.0 })
+}
+
+#[derive(Debug, PartialEq)]
+pub enum GolVd {
+    Position,
+    Offset,
+    Alive,
+}
+
+impl VaoDefine for GolVd {
+    fn iter() -> std::slice::Iter<'static, Self> {
+        [GolVd::Position, GolVd::Offset, GolVd::Alive].iter()
+    }
+
+    fn name(&self) -> &'static str {
+        match self {
+            GolVd::Position => "position",
+            GolVd::Offset => "offset",
+            GolVd::Alive => "alive",
+        }
+    }
+
+    fn size_of(&self) -> i32 {
+        use webgl2::GlPoint;
+        match self {
+            GolVd::Position => GlPoint2d::size(),
+            GolVd::Offset => GlPoint2d::size(),
+            GolVd::Alive => GlPoint1d::size(),
+        }
+    }
+
+    // セル毎の位置と状態はインスタンス毎に進める
+    fn is_instanced(&self) -> bool {
+        matches!(self, GolVd::Offset | GolVd::Alive)
+    }
+}
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use wasm_bindgen::JsCast;
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    // 左上から(x, y)pxの位置の色を読む
+    fn read_pixel(ctx: &Context, x: i32, y: i32) -> [u8; 4] {
+        let gl = ctx.gl();
+        let mut pixel = [0u8; 4];
+        let y = gl.drawing_buffer_height() - 1 - y;
+        gl.read_pixels_with_opt_u8_array(x, y, 1, 1, gl::RGBA, gl::UNSIGNED_BYTE, Some(&mut pixel))
+            .unwrap();
+        pixel
+    }
+
+    // 左上のセルの中心の色を読む
+    fn read_first_cell(ctx: &Context) -> [u8; 4] {
+        read_pixel(ctx, 3, 3)
+    }
+
+    #[wasm_bindgen_test]
+    fn test_draw_changed_cells() {
+        let canvas = web_sys::window()
+            .unwrap()
+            .document()
+            .unwrap()
+            .create_element("canvas")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .unwrap();
+        let ctx = Context::new(canvas, DrawerWebGl::GRID_COLOR).unwrap();
+        let mut uni = Universe::new(8, 8);
+        uni.set_cell_state(0, 0, false);
+        let drawer = DrawerWebGl::new(&ctx, &uni).unwrap();
+
+        drawer.draw(&uni);
+        assert_eq!(read_first_cell(&ctx), [255, 255, 255, 255]);
+
+        // 変わったセルだけ更新しても反映される
+        uni.set_cell_state(0, 0, true);
+        drawer.draw(&uni);
+        assert_eq!(read_first_cell(&ctx), [0, 0, 0, 255]);
+        assert_eq!(drawer.prev.borrow().as_ref().unwrap().1, uni.cells);
+
+        // 大きさが変わったら作り直す
+        uni.resize(4, 4).unwrap();
+        drawer.draw(&uni);
+        assert_eq!(drawer.prev.borrow().as_ref().unwrap().0, 4);
+
+        // 既定の5pxでは(8, 3)は2番目のセルだが、10pxにすると左上のセルに含まれる
+        uni.set_cell_state(0, 0, true);
+        uni.set_cell_state(0, 1, false);
+        drawer.draw(&uni);
+        assert_eq!(read_pixel(&ctx, 8, 3), [255, 255, 255, 255]);
+        let drawer = drawer.with_cell_size(10);
+        drawer.draw(&uni);
+        assert_eq!(read_pixel(&ctx, 8, 3), [0, 0, 0, 255]);
+    }
+}
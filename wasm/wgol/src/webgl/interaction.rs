@@ -8,7 +8,7 @@ use webgl2::{
     gl,
     program::Program,
     vertex::{Vao, VaoDefine},
-    GlEnum, GlPoint2d, GlPoint3d,
+    GlEnum, GlPoint2d, GlPoint3d, GlPoint4d,
 };
 
 use crate::error::Result;
@@ -98,12 +98,22 @@ void main() {
         self.uniform.set_size(self.particle.current_size);
     }
 
+    /// 全てのパーティクルをtargetに集めて静止させる
+    pub fn reset(&mut self, target: Point) {
+        self.particle.reset(target);
+        self.vao
+            .buffer_sub_data(ParticleVd::Position, &self.particle.position, 0);
+        self.uniform.set_size(self.particle.current_size);
+    }
+
     pub fn draw(&self) {
         self.program.use_program();
         self.vao.bind();
         self.program
             .gl()
             .draw_arrays(gl::POINTS, 0, self.vertex_len);
+        #[cfg(debug_assertions)]
+        self.program.check_errors_assert();
     }
 }
 
@@ -198,7 +208,7 @@ impl std::ops::Mul<f32> for Point {
 
 /// パーティクルに関する操作
 #[wasm_bindgen(inspectable)]
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq, serde::Serialize, serde::Deserialize)]
 pub struct ParticleControl {
     // 追従移動速度の係数。小さいと実際の移動量が小さくなり、速度も遅いがマウスに追従しやすくなる
     // 大きくするとオーバーシュートが増える
@@ -216,6 +226,8 @@ pub struct ParticleControl {
     // ベクトル更新レートの逆数
     // これが大きいほど、パーティクルの方向転換が遅くなる = オーバーシュートしやすくなる
     pub handle_rate: f32,
+    // クリックした瞬間にパーティクルをターゲット位置に集めてから拡散させる
+    pub burst_on_click: bool,
 }
 
 impl ParticleControl {
@@ -226,6 +238,7 @@ impl ParticleControl {
         max_velocity: 2.0,
         max_size: 4.0,
         handle_rate: 1.0 / 5.0,
+        burst_on_click: false,
     };
 }
 
@@ -234,6 +247,19 @@ impl ParticleControl {
     pub fn default() -> Self {
         Self::DEFAULT
     }
+
+    /// 設定を保存・共有するためにJSのオブジェクトに変換する
+    ///
+    /// 呼び出し後もJS側のオブジェクトを使えるよう参照で受ける
+    #[allow(clippy::wrong_self_convention)]
+    pub fn to_json(&self) -> std::result::Result<JsValue, JsValue> {
+        Ok(serde_wasm_bindgen::to_value(self)?)
+    }
+
+    /// `to_json`で出力したオブジェクトから設定を復元する
+    pub fn from_json(val: JsValue) -> std::result::Result<ParticleControl, JsValue> {
+        Ok(serde_wasm_bindgen::from_value(val)?)
+    }
 }
 
 pub struct Particle {
@@ -243,6 +269,8 @@ pub struct Particle {
     current_velocity: f32,
     current_size: f32,
     ctrl: ParticleControl,
+    // 前回のupdateでベクトル更新を行ったか
+    prev_vector_update: bool,
 }
 
 impl Particle {
@@ -267,6 +295,7 @@ impl Particle {
             current_velocity: 0.0,
             current_size: 1.0,
             ctrl,
+            prev_vector_update: false,
         }
     }
 
@@ -293,6 +322,8 @@ impl Particle {
 
     // 目標点に向かって移動
     pub fn update(&mut self, target: Point, vector_update: bool) {
+        let burst = self.ctrl.burst_on_click && vector_update && !self.prev_vector_update;
+        self.prev_vector_update = vector_update;
         match vector_update {
             true => {
                 self.current_velocity = self.ctrl.max_velocity;
@@ -303,6 +334,12 @@ impl Particle {
                 self.current_size *= self.ctrl.size_decay;
             }
         }
+        if burst {
+            // 押した瞬間は全てターゲット位置に集め、次のフレームから拡散させる
+            let pos = GlPoint2d::from(target);
+            self.position.iter_mut().for_each(|p| *p = pos);
+            return;
+        }
         for x in 0..self.res.x {
             for y in 0..self.res.y {
                 let i = self.index(x, y);
@@ -313,6 +350,17 @@ impl Particle {
             }
         }
     }
+
+    /// 全てのパーティクルをtargetに移動し、速度と大きさを0にする
+    pub fn reset(&mut self, target: Point) {
+        let pos = GlPoint2d::from(target);
+        self.position.iter_mut().for_each(|p| *p = pos);
+        self.vector
+            .iter_mut()
+            .for_each(|v| *v = GlPoint2d::new(0.0, 0.0));
+        self.current_velocity = 0.0;
+        self.current_size = 0.0;
+    }
 }
 
 #[derive(Debug, PartialEq)]
@@ -351,6 +399,8 @@ pub struct ParticleGpgpuShader {
     fbos: [TextureFBO; 2],
     fbo_prev_index: usize,
     state: ParticleGpgpuState,
+    // 速度に加えるノイズ場
+    noise: Option<webgl2::texture::Texture>,
 }
 
 impl ParticleGpgpuShader {
@@ -394,12 +444,34 @@ uniform bool vectorUpdate;
 uniform float velocity;
 uniform float speed;
 uniform float handleRate;
+uniform bool u_use_curl_noise;
+uniform float u_curl_frequency;
+uniform float u_curl_speed;
+uniform float u_time;
+uniform bool u_use_noise;
+uniform sampler2D u_noise;
+
+// ポテンシャル sin(x)cos(y) の回転成分。発散が無いので渦を巻くような流れになる
+vec2 curl(vec2 q){
+    return vec2(-sin(q.x) * sin(q.y), -cos(q.x) * cos(q.y));
+}
 
 out vec4 fragmentColor;
 void main(){
     vec2 p = gl_FragCoord.xy / resolution;
     vec4 t = texture(u_texture, p);
+    if(u_use_curl_noise){
+        // 入力に関わらずノイズ場に沿って流れ続ける
+        vec2 c = curl(t.xy * u_curl_frequency + u_time * u_curl_speed) * handleRate;
+        vec2 w = normalize(c + t.zw + vec2(1e-6));
+        fragmentColor = vec4(t.xy + w * speed, w);
+        return;
+    }
     vec2 v = normalize(target - t.xy) * handleRate;
+    if(u_use_noise){
+        // 事前に生成したノイズ場の向きを加える
+        v += texture(u_noise, p).xy * handleRate;
+    }
     vec2 w = normalize(v + t.zw);
     vec4 destColor = vec4(t.xy + w * speed * velocity, w);
     if(!vectorUpdate){destColor.zw = t.zw;}
@@ -485,12 +557,36 @@ void main(){
             fbos,
             fbo_prev_index: 0,
             state,
+            noise: None,
         };
         s.draw_init();
 
         Ok(s)
     }
 
+    // ノイズ場を作る際の空間周波数。解像度全体でおよそ4周期
+    const NOISE_PERIODS: f64 = 4.0;
+
+    /// CPUで生成したノイズ場を速度の計算に加える
+    ///
+    /// 各テクセルはシードから決まる向きの単位ベクトル
+    pub fn with_noise_texture(mut self, ctx: &Context, seed: u32) -> Result<Self> {
+        let field = noise_field(self.res, seed, Self::NOISE_PERIODS)
+            .iter()
+            .flat_map(|p| [p.x, p.y])
+            .collect::<Vec<_>>();
+        let res = webgl2::texture::Resolution::new(self.res.x, self.res.y);
+        let texture = ctx.create_float_vec2_texture(res, &field)?;
+
+        self.velocity.use_program();
+        self.u_velocity.set_noise(true, Self::NOISE_TEXTURE_UNIT);
+        self.noise = Some(texture);
+        Ok(self)
+    }
+
+    // ノイズ場は位置と速度のテクスチャと別のユニットを使う
+    const NOISE_TEXTURE_UNIT: u32 = 1;
+
     // 取り出すテクスチャ座標の位置
     fn point_vert(x: u32, y: u32) -> Vec<GlPoint2d> {
         let (ix, iy) = (1. / x as f32, 1. / y as f32);
@@ -520,6 +616,8 @@ void main(){
         self.index_vao.bind();
         gl.draw_arrays(gl::TRIANGLE_STRIP, 0, 4);
         self.fbos[0].unbind();
+        #[cfg(debug_assertions)]
+        self.index.check_errors_assert();
     }
 
     /// 画面全体にインデックスを描画。インデックス確認用
@@ -534,6 +632,8 @@ void main(){
         self.index.use_program();
         self.index_vao.bind();
         gl.draw_arrays(gl::TRIANGLE_STRIP, 0, 4);
+        #[cfg(debug_assertions)]
+        self.index.check_errors_assert();
     }
 
     pub fn update(&mut self, target: Point, vector_update: bool, color: [f32; 4]) {
@@ -545,6 +645,7 @@ void main(){
         self.u_velocity.set_target(self.state.target);
         self.u_velocity.set_velocity(self.state.velocity);
         self.u_velocity.set_vector_update(self.state.vector_update);
+        self.u_velocity.set_time(self.state.time);
 
         // 描画uniformを更新
         self.point.use_program();
@@ -569,6 +670,10 @@ void main(){
         gl.clear(gl::COLOR_BUFFER_BIT);
 
         self.velocity.use_program();
+        if let Some(noise) = self.noise.as_ref() {
+            gl.active_texture(gl::TEXTURE0 + Self::NOISE_TEXTURE_UNIT);
+            noise.bind();
+        }
         gl.active_texture(gl::TEXTURE0);
         // 前のFBOの状態をテクスチャの仕組みで取得
         gl.bind_texture(gl::TEXTURE_2D, Some(&fbos[0].texture));
@@ -594,10 +699,74 @@ void main(){
         self.point_vao.unbind();
 
         gl.flush();
+        #[cfg(debug_assertions)]
+        self.point.check_errors_assert();
 
         // 次のフレームのためにインデックスを更新
         self.fbo_prev_index = next;
     }
+
+    /// 全てのパーティクルをtargetに集めて静止させる
+    ///
+    /// 現在のFBOを位置=target、速度=0の値でクリアする
+    pub fn reset(&mut self, ctx: &Context, target: Point) {
+        let gl = ctx.gl();
+        let fbo = &self.fbos[self.fbo_prev_index];
+        fbo.bind();
+        gl.viewport(0, 0, self.res.x as i32, self.res.y as i32);
+        gl.clear_color(target.x, target.y, 0.0, 0.0);
+        gl.clear(gl::COLOR_BUFFER_BIT);
+        fbo.unbind();
+        self.state.reset(target);
+    }
+
+    /// 速度の計算をカールノイズによる流れ場に切り替える
+    ///
+    /// 有効な場合はマウスの位置を追従せず、frequencyで空間的な細かさ、speedで時間変化の速さを指定する
+    pub fn set_curl_noise_mode(&mut self, enabled: bool, frequency: f32, speed: f32) {
+        self.velocity.use_program();
+        self.u_velocity.set_curl_noise(enabled, frequency, speed);
+    }
+
+    /// 最新の位置と速度のテクスチャをCPU側に読み出す
+    ///
+    /// xyが位置、zwが速度
+    pub fn snapshot(&self, ctx: &Context) -> Result<Vec<GlPoint4d>> {
+        let data = self.read_state(ctx)?;
+        Ok(data
+            .chunks_exact(4)
+            .map(|c| GlPoint4d::new(c[0], c[1], c[2], c[3]))
+            .collect())
+    }
+
+    /// [Self::snapshot]の内容をJSに渡せる形で取得する
+    pub fn snapshot_array(&self, ctx: &Context) -> Result<js_sys::Float32Array> {
+        let data = self
+            .snapshot(ctx)?
+            .iter()
+            .flat_map(|p| [p.x, p.y, p.z, p.w])
+            .collect::<Vec<_>>();
+        Ok(js_sys::Float32Array::from(data.as_slice()))
+    }
+
+    fn read_state(&self, ctx: &Context) -> Result<Vec<f32>> {
+        let gl = ctx.gl();
+        let len = (self.res.x * self.res.y * 4) as usize;
+        let buf = js_sys::Float32Array::new_with_length(len as u32);
+        self.fbos[self.fbo_prev_index].bind();
+        let res = gl.read_pixels_with_opt_array_buffer_view(
+            0,
+            0,
+            self.res.x as i32,
+            self.res.y as i32,
+            gl::RGBA,
+            gl::FLOAT,
+            Some(&buf),
+        );
+        self.fbos[self.fbo_prev_index].unbind();
+        res.map_err(|e| JsError::new(&format!("Failed to read_pixels: {:?}", e)))?;
+        Ok(buf.to_vec())
+    }
 }
 
 struct ParticleGpgpuState {
@@ -607,6 +776,8 @@ struct ParticleGpgpuState {
     vector_update: bool,
     ambient: [f32; 4],
     target: Point,
+    // 経過時間(秒)。updateを1フレームとして進める
+    time: f32,
 }
 
 impl ParticleGpgpuState {
@@ -618,10 +789,21 @@ impl ParticleGpgpuState {
             vector_update: false,
             ambient: [1.0, 1.0, 1.0, 1.0],
             target: Point::new(0.0, 0.0),
+            time: 0.0,
         }
     }
 
+    const FRAME_SEC: f32 = 1.0 / 60.0;
+
+    fn reset(&mut self, target: Point) {
+        self.target = target;
+        self.vector_update = false;
+        self.velocity = 0.0;
+        self.size = 0.0;
+    }
+
     fn update(&mut self, target: Point, vector_update: bool) {
+        self.time += Self::FRAME_SEC;
         self.vector_update = vector_update;
         self.target = target;
         match vector_update {
@@ -637,6 +819,25 @@ impl ParticleGpgpuState {
     }
 }
 
+// 解像度分のノイズ場を生成する。各要素は単位ベクトル
+//
+// 2つの独立したシンプレックスノイズの比から角度を求めることで、向きの偏りを抑える
+fn noise_field(res: Resolution, seed: u32, periods: f64) -> Vec<GlPoint2d> {
+    use noise::{NoiseFn, Simplex};
+    let nx = Simplex::new(seed);
+    let ny = Simplex::new(seed.wrapping_add(1));
+    let scale = periods / res.x.max(res.y) as f64;
+    let mut field = Vec::with_capacity((res.x * res.y) as usize);
+    for y in 0..res.y {
+        for x in 0..res.x {
+            let p = [x as f64 * scale, y as f64 * scale];
+            let angle = ny.get(p).atan2(nx.get(p)) as f32;
+            field.push(GlPoint2d::new(angle.cos(), angle.sin()));
+        }
+    }
+    field
+}
+
 struct ParticleGpgpuPointUniform {
     gl: Rc<gl>,
     point_size: WebGlUniformLocation,
@@ -686,6 +887,12 @@ struct ParticleGpgpuVelocityUniform {
     velocity: WebGlUniformLocation,
     speed: WebGlUniformLocation,
     handle_rate: WebGlUniformLocation,
+    use_curl_noise: WebGlUniformLocation,
+    curl_frequency: WebGlUniformLocation,
+    curl_speed: WebGlUniformLocation,
+    time: WebGlUniformLocation,
+    use_noise: WebGlUniformLocation,
+    noise: WebGlUniformLocation,
 }
 
 impl ParticleGpgpuVelocityUniform {
@@ -697,6 +904,12 @@ impl ParticleGpgpuVelocityUniform {
         let velocity = program.uniform_location("velocity")?;
         let speed = program.uniform_location("speed")?;
         let handle_rate = program.uniform_location("handleRate")?;
+        let use_curl_noise = program.uniform_location("u_use_curl_noise")?;
+        let curl_frequency = program.uniform_location("u_curl_frequency")?;
+        let curl_speed = program.uniform_location("u_curl_speed")?;
+        let time = program.uniform_location("u_time")?;
+        let use_noise = program.uniform_location("u_use_noise")?;
+        let noise = program.uniform_location("u_noise")?;
         let gl = program.gl().clone();
         Ok(Self {
             gl,
@@ -707,6 +920,12 @@ impl ParticleGpgpuVelocityUniform {
             velocity,
             speed,
             handle_rate,
+            use_curl_noise,
+            curl_frequency,
+            curl_speed,
+            time,
+            use_noise,
+            noise,
         })
     }
 
@@ -717,6 +936,9 @@ impl ParticleGpgpuVelocityUniform {
         self.set_velocity(state.velocity);
         self.set_speed(state.ctrl.speed);
         self.set_handle_rate(state.ctrl.handle_rate);
+        self.set_time(state.time);
+        self.set_curl_noise(false, 1.0, 1.0);
+        self.set_noise(false, 0);
     }
 
     #[allow(dead_code)]
@@ -748,6 +970,22 @@ impl ParticleGpgpuVelocityUniform {
     pub fn set_handle_rate(&self, rate: f32) {
         self.gl.uniform1f(Some(&self.handle_rate), rate);
     }
+
+    pub fn set_curl_noise(&self, enabled: bool, frequency: f32, speed: f32) {
+        self.gl
+            .uniform1i(Some(&self.use_curl_noise), enabled as i32);
+        self.gl.uniform1f(Some(&self.curl_frequency), frequency);
+        self.gl.uniform1f(Some(&self.curl_speed), speed);
+    }
+
+    pub fn set_time(&self, time: f32) {
+        self.gl.uniform1f(Some(&self.time), time);
+    }
+
+    pub fn set_noise(&self, enabled: bool, texture_unit: u32) {
+        self.gl.uniform1i(Some(&self.use_noise), enabled as i32);
+        self.gl.uniform1i(Some(&self.noise), texture_unit as i32);
+    }
 }
 
 struct ParticleGpgpuIndexUniform {
@@ -869,3 +1107,154 @@ impl TextureFBO {
         self.gl.bind_framebuffer(gl::FRAMEBUFFER, None);
     }
 }
+
+#[cfg(all(test, target_arch = "wasm32"))]
+mod tests {
+    use wasm_bindgen_test::*;
+
+    use super::*;
+
+    wasm_bindgen_test_configure!(run_in_browser);
+
+    #[wasm_bindgen_test]
+    fn test_noise_field() {
+        let res = Resolution::new(64, 64);
+        let field = noise_field(res, 42, 4.0);
+        assert_eq!(field.len(), 64 * 64);
+
+        // 向きごとに8分割して偏りが無いことを確認
+        let mut bins = [0; 8];
+        for p in field.iter() {
+            assert!((p.norm() - 1.0).abs() < 1e-4);
+            let t = (p.y.atan2(p.x) + std::f32::consts::PI) / std::f32::consts::TAU;
+            bins[((t * 8.0) as usize).min(7)] += 1;
+        }
+        let expected = field.len() / bins.len();
+        for count in bins {
+            assert!(count > expected / 2, "{bins:?}");
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_particle_control_json() {
+        let json = ParticleControl::DEFAULT.to_json().unwrap();
+        let ctrl = ParticleControl::from_json(json).unwrap();
+        assert_eq!(ctrl, ParticleControl::DEFAULT);
+    }
+
+    #[wasm_bindgen_test]
+    fn test_particle_burst() {
+        let ctrl = ParticleControl {
+            burst_on_click: true,
+            ..ParticleControl::DEFAULT
+        };
+        let mut particle = Particle::new(Resolution::new(4, 4), ctrl);
+        let target = Point::new(0.5, -0.25);
+
+        particle.update(target, false);
+        assert_ne!(particle.position[0], GlPoint2d::from(target));
+
+        particle.update(target, true);
+        assert!(particle
+            .position
+            .iter()
+            .all(|p| *p == GlPoint2d::from(target)));
+
+        // 押し続けている間は再度集めない
+        particle.update(Point::new(0.0, 0.0), true);
+        assert_ne!(particle.position[0], GlPoint2d::from(Point::new(0.0, 0.0)));
+    }
+
+    #[wasm_bindgen_test]
+    fn test_particle_reset() {
+        let mut particle = Particle::new(Resolution::new(4, 4), ParticleControl::DEFAULT);
+        particle.update(Point::new(0.5, 0.5), true);
+        particle.update(Point::new(0.5, 0.5), false);
+
+        let target = Point::new(-0.5, 0.25);
+        particle.reset(target);
+        assert!(particle
+            .position
+            .iter()
+            .all(|p| *p == GlPoint2d::from(target)));
+        assert!(particle.vector.iter().all(|v| v.norm() == 0.0));
+        assert_eq!(
+            (particle.current_velocity, particle.current_size),
+            (0.0, 0.0)
+        );
+    }
+
+    #[wasm_bindgen_test]
+    fn test_snapshot_after_reset() {
+        let canvas = web_sys::window()
+            .unwrap()
+            .document()
+            .unwrap()
+            .create_element("canvas")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .unwrap();
+        let ctx = Context::new(canvas, webgl2::context::COLOR_BLACK).unwrap();
+        ctx.gl().get_extension("EXT_color_buffer_float").unwrap();
+
+        let res = Resolution::new(8, 8);
+        let mut shader = ParticleGpgpuShader::new(&ctx, res, ParticleControl::DEFAULT).unwrap();
+        shader.update(Point::new(0.5, 0.5), true, [1.0; 4]);
+        shader.draw(&res);
+
+        let target = Point::new(-0.5, 0.25);
+        shader.reset(&ctx, target);
+        for p in shader.snapshot(&ctx).unwrap() {
+            assert_eq!((p.x, p.y, p.z, p.w), (target.x, target.y, 0.0, 0.0));
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_snapshot_after_init() {
+        let canvas = web_sys::window()
+            .unwrap()
+            .document()
+            .unwrap()
+            .create_element("canvas")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .unwrap();
+        let ctx = Context::new(canvas, webgl2::context::COLOR_BLACK).unwrap();
+        ctx.gl().get_extension("EXT_color_buffer_float").unwrap();
+
+        let res = Resolution::new(8, 8);
+        let shader = ParticleGpgpuShader::new(&ctx, res, ParticleControl::DEFAULT).unwrap();
+        let snapshot = shader.snapshot(&ctx).unwrap();
+
+        assert_eq!(snapshot.len(), 64);
+        for p in snapshot {
+            assert!((-1.0..=1.0).contains(&p.x) && (-1.0..=1.0).contains(&p.y));
+            assert_eq!((p.z, p.w), (0.0, 0.0));
+        }
+    }
+
+    #[wasm_bindgen_test]
+    fn test_curl_noise_mode() {
+        let canvas = web_sys::window()
+            .unwrap()
+            .document()
+            .unwrap()
+            .create_element("canvas")
+            .unwrap()
+            .dyn_into::<web_sys::HtmlCanvasElement>()
+            .unwrap();
+        let ctx = Context::new(canvas, webgl2::context::COLOR_BLACK).unwrap();
+        ctx.gl().get_extension("EXT_color_buffer_float").unwrap();
+
+        let res = Resolution::new(8, 8);
+        let mut shader = ParticleGpgpuShader::new(&ctx, res, ParticleControl::DEFAULT).unwrap();
+        shader.set_curl_noise_mode(true, 3.0, 1.0);
+        // マウス入力なし
+        shader.update(Point::new(0.0, 0.0), false, [1.0; 4]);
+        shader.draw(&res);
+
+        for p in shader.snapshot(&ctx).unwrap() {
+            assert!(p.z != 0.0 || p.w != 0.0);
+        }
+    }
+}
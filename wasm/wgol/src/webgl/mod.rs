@@ -1,3 +1,4 @@
 pub mod basic_plane;
 pub mod camera;
+pub mod gol_shader;
 pub mod interaction;
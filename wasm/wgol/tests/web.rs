@@ -7,7 +7,7 @@ extern crate wasm_bindgen_test;
 use std::assert_eq;
 
 use wasm_bindgen_test::*;
-use wasm_game_of_life::Universe;
+use wasm_game_of_life::{Cell, Universe};
 
 wasm_bindgen_test_configure!(run_in_browser);
 
@@ -38,3 +38,121 @@ pub fn test_tick() {
     input_universe.tick();
     assert!(input_universe.difference(&expected_universe) < 1);
 }
+
+#[wasm_bindgen_test]
+fn test_hsva_rgba() {
+    use wasm_game_of_life::{hsva_to_rgba, rgba_to_hsva};
+
+    assert_eq!(hsva_to_rgba(0., 1., 1., 1.).to_vec(), vec![1., 0., 0., 1.]);
+
+    // 往復変換で元の値に戻る
+    for hsva in [
+        [0., 1., 1., 1.],
+        [120., 0.5, 0.8, 0.5],
+        [300., 0.25, 0.5, 1.],
+    ] {
+        let rgba = hsva_to_rgba(hsva[0], hsva[1], hsva[2], hsva[3]).to_vec();
+        let back = rgba_to_hsva(rgba[0], rgba[1], rgba[2], rgba[3]).to_vec();
+        for (a, b) in hsva.iter().zip(back.iter()) {
+            assert!((a - b).abs() < 1e-4, "{hsva:?} != {back:?}");
+        }
+    }
+}
+
+#[wasm_bindgen_test]
+fn test_toggle_region() {
+    let mut uni = Universe::new(8, 8);
+    for row in 0..uni.height() {
+        for col in 0..uni.width() {
+            uni.set_cell_state(row, col, false);
+        }
+    }
+
+    uni.toggle_region(2, 3, 3, 3);
+    assert_eq!(uni.render().matches('◼').count(), 9);
+
+    // 範囲外の指定は隣の行に回り込まずに無視する
+    uni.set_cell_state(0, 8, true);
+    uni.set_cell_state(8, 0, true);
+    assert_eq!(uni.render().matches('◼').count(), 9);
+    assert!(!uni.get_cell(1, 0));
+}
+
+#[wasm_bindgen_test]
+fn test_get_cell() {
+    use wasm_bindgen::JsCast;
+
+    let uni = Universe::with_random(16, 16);
+    let memory = wasm_bindgen::memory()
+        .dyn_into::<js_sys::WebAssembly::Memory>()
+        .unwrap();
+    let size = uni.width() * uni.height();
+    // セルはビット単位で格納されている
+    let bytes = js_sys::Uint8Array::new_with_byte_offset_and_length(
+        &memory.buffer(),
+        uni.cells() as u32,
+        size.div_ceil(8),
+    )
+    .to_vec();
+
+    for row in 0..uni.height() {
+        for col in 0..uni.width() {
+            let idx = (row * uni.width() + col) as usize;
+            let raw = bytes[idx / 8] & (1 << (idx % 8)) != 0;
+            assert_eq!(uni.get_cell(row, col), raw);
+        }
+    }
+}
+
+#[wasm_bindgen_test]
+fn test_flood_fill() {
+    let mut uni = Universe::new(5, 5);
+    for row in 0..uni.height() {
+        for col in 0..uni.width() {
+            uni.set_cell_state(row, col, false);
+        }
+    }
+    uni.toggle_region(1, 1, 3, 3);
+    assert_eq!(uni.render().matches('◼').count(), 9);
+
+    uni.flood_fill_at(2, 2, true);
+    assert_eq!(uni.render().matches('◼').count(), 0);
+
+    // 空白を埋めると全体が生存セルになる
+    uni.flood_fill(0, 0, Cell::Dead, Cell::Alive);
+    assert_eq!(uni.render().matches('◼').count(), 25);
+}
+
+#[wasm_bindgen_test]
+fn test_count_components() {
+    fn empty(width: u32, height: u32) -> Universe {
+        let mut uni = Universe::new(width, height);
+        for row in 0..height {
+            for col in 0..width {
+                uni.set_cell_state(row, col, false);
+            }
+        }
+        uni
+    }
+
+    let uni = empty(16, 16);
+    assert_eq!(uni.count_components(), 0);
+    assert_eq!(uni.largest_component_size(), 0);
+
+    // 斜めにしか接していないセルは別の島として数える
+    let mut uni = empty(16, 16);
+    uni.set_cells(&[(1, 2), (2, 3), (3, 1), (3, 2), (3, 3)]);
+    assert_eq!(uni.count_components(), 2);
+    assert_eq!(uni.largest_component_size(), 4);
+
+    // 端は反対側とつながっている
+    let mut uni = empty(16, 16);
+    uni.set_cells(&[(0, 5), (15, 5), (8, 0), (8, 15)]);
+    assert_eq!(uni.count_components(), 2);
+    assert_eq!(uni.largest_component_size(), 2);
+
+    let mut uni = empty(16, 16);
+    uni.toggle_region(4, 4, 3, 3);
+    assert_eq!(uni.count_components(), 1);
+    assert_eq!(uni.largest_component_size(), 9);
+}
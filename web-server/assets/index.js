@@ -14,7 +14,7 @@ const width = 64;
 const height = 64;
 const playPauseButton = document.getElementById("play-pause");
 const fps = document.getElementById("fps");
-const golb = GolBuilder.new(width, height, canvas, playPauseButton, fps);
+const golb = GolBuilder.new(width, height, canvas, playPauseButton).with_fps(fps);
 golstart(golb);
 webgl_start(canvas_webgl);
 const ctrl = ParticleControl.default();
@@ -0,0 +1,126 @@
+//! ログ出力の設定
+//!
+//! `LOG_FORMAT=json` の場合はログ集約基盤で扱えるようにJSONで出力する
+
+use std::time::Duration;
+
+use axum::http::{Request, Response};
+use tower_http::{
+    classify::{ServerErrorsAsFailures, SharedClassifier},
+    trace::{DefaultOnRequest, TraceLayer},
+};
+use tracing::Span;
+use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+
+/// ログの出力形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum LogFormat {
+    #[default]
+    Pretty,
+    Json,
+}
+
+impl LogFormat {
+    /// 環境変数`LOG_FORMAT`から出力形式を決める
+    pub fn from_env() -> Self {
+        match std::env::var("LOG_FORMAT").as_deref() {
+            Ok("json") => Self::Json,
+            _ => Self::Pretty,
+        }
+    }
+}
+
+/// グローバルなサブスクライバを初期化する
+pub fn init(format: LogFormat) {
+    let json = format == LogFormat::Json;
+    tracing_subscriber::registry()
+        .with(
+            tracing_subscriber::EnvFilter::try_from_default_env()
+                .unwrap_or_else(|_| "web_server=debug,tower_http=debug".into()),
+        )
+        .with(json.then(|| tracing_subscriber::fmt::layer().json()))
+        .with((!json).then(tracing_subscriber::fmt::layer))
+        .init();
+}
+
+/// リクエスト毎にmethodとpathを持つspanを作り、応答時にstatus_codeとlatency_msを記録する
+#[allow(clippy::type_complexity)]
+pub fn trace_layer<B>() -> TraceLayer<
+    SharedClassifier<ServerErrorsAsFailures>,
+    fn(&Request<B>) -> Span,
+    DefaultOnRequest,
+    fn(&Response<axum::body::Body>, Duration, &Span),
+> {
+    TraceLayer::new_for_http()
+        .make_span_with(make_span as fn(&Request<B>) -> Span)
+        .on_response(on_response as fn(&Response<axum::body::Body>, Duration, &Span))
+}
+
+fn make_span<B>(req: &Request<B>) -> Span {
+    tracing::info_span!(
+        "request",
+        method = %req.method(),
+        path = %req.uri().path(),
+    )
+}
+
+fn on_response<B>(res: &Response<B>, latency: Duration, _span: &Span) {
+    tracing::info!(
+        status_code = res.status().as_u16(),
+        latency_ms = latency.as_secs_f64() * 1000.0,
+        "response"
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::{Arc, Mutex};
+
+    use tracing_subscriber::fmt::MakeWriter;
+
+    use super::*;
+
+    // 出力をメモリに保持するWriter
+    #[derive(Clone, Default)]
+    struct Buffer(Arc<Mutex<Vec<u8>>>);
+
+    impl std::io::Write for Buffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().write(buf)
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    impl<'a> MakeWriter<'a> for Buffer {
+        type Writer = Self;
+        fn make_writer(&'a self) -> Self::Writer {
+            self.clone()
+        }
+    }
+
+    #[test]
+    fn test_json_response_log() {
+        let buf = Buffer::default();
+        let subscriber = tracing_subscriber::registry().with(
+            tracing_subscriber::fmt::layer()
+                .json()
+                .with_writer(buf.clone()),
+        );
+        tracing::subscriber::with_default(subscriber, || {
+            let req = Request::get("/api/hello").body(()).unwrap();
+            let span = make_span(&req);
+            let _enter = span.enter();
+            let res = Response::new(());
+            on_response(&res, Duration::from_millis(12), &span);
+        });
+
+        let out = String::from_utf8(buf.0.lock().unwrap().clone()).unwrap();
+        let line = out.lines().next().unwrap();
+        assert!(line.contains("\"latency_ms\""), "{line}");
+        assert!(line.contains("\"status_code\":200"), "{line}");
+        assert!(line.contains("\"method\":\"GET\""), "{line}");
+        assert!(line.contains("\"path\":\"/api/hello\""), "{line}");
+    }
+}
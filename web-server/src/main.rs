@@ -4,20 +4,18 @@ use axum::{http::StatusCode, response::IntoResponse, routing::get, Json, Router}
 use hex_color::HexColor;
 use image::{ImageBuffer, ImageEncoder, Rgba};
 use rand::Rng;
-use tower_http::{services::ServeDir, trace::TraceLayer};
-use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
+use tower_http::services::ServeDir;
+
+mod logging;
+mod universe;
 
 #[tokio::main]
 async fn main() {
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env()
-                .unwrap_or_else(|_| "example_static_file_server=debug,tower_http=debug".into()),
-        )
-        .with(tracing_subscriber::fmt::layer())
-        .init();
+    logging::init(logging::LogFormat::from_env());
 
     let serve_dir = ServeDir::new("assets").append_index_html_on_directories(true);
+    let shared_universe = universe::SharedUniverse::new();
+    shared_universe.spawn_ticker();
     let router = Router::new()
         .nest(
             "/api",
@@ -26,10 +24,11 @@ async fn main() {
                 .route("/ws/echo", get(echo_ws))
                 .route("/ws/boid/gen_stream", get(gen_boid_ws))
                 .route("/texture/generate/:name", get(gen_texture))
-                .route("/sleep/:msec", get(get_sleep)),
+                .route("/sleep/:msec", get(get_sleep))
+                .nest("/universe", universe::routes(shared_universe)),
         )
         .fallback_service(serve_dir)
-        .layer(TraceLayer::new_for_http());
+        .layer(logging::trace_layer());
 
     let port = 8080;
     let addr = SocketAddr::from(([127, 0, 0, 1], port));
@@ -107,6 +106,8 @@ enum ImageFormat {
     Png,
     Jpeg,
     Webp,
+    /// アニメーションPNG
+    Apng,
 }
 
 /// 画像生成リクエスト
@@ -117,9 +118,20 @@ struct TextureQuery {
     format: Option<ImageFormat>,
     color_front: Option<String>,
     color_back: Option<String>,
+    /// APNGのフレーム数
+    frames: Option<u32>,
+    /// APNGの1フレームの表示時間
+    frame_delay_ms: Option<u32>,
+    /// 市松模様の代わりにパーリンノイズを生成する
+    noise: Option<bool>,
+    /// ノイズの空間周波数。1pxあたりの変化量
+    scale: Option<f32>,
 }
 
 impl TextureQuery {
+    /// APNGのフレーム数の上限
+    const MAX_FRAMES: u32 = 64;
+
     fn width(&self) -> u32 {
         self.width.unwrap_or(128)
     }
@@ -129,6 +141,18 @@ impl TextureQuery {
     fn format(&self) -> ImageFormat {
         self.format.unwrap_or_default()
     }
+    fn frames(&self) -> u32 {
+        self.frames.unwrap_or(4).clamp(1, Self::MAX_FRAMES)
+    }
+    fn frame_delay_ms(&self) -> u32 {
+        self.frame_delay_ms.unwrap_or(100)
+    }
+    fn noise(&self) -> bool {
+        self.noise.unwrap_or(false)
+    }
+    fn scale(&self) -> f32 {
+        self.scale.unwrap_or(0.1)
+    }
     fn color_front(&self) -> [u8; 4] {
         Self::parse_color(self.color_front.as_deref(), [128, 128, 128, 255])
     }
@@ -149,11 +173,16 @@ impl TextureQuery {
     }
 }
 
+/// 画像をエンコードする
+///
+/// APNG以外は先頭のフレームのみを使う
 fn write_image(
-    img: &ImageBuffer<Rgba<u8>, Vec<u8>>,
+    frames: &[ImageBuffer<Rgba<u8>, Vec<u8>>],
     format: ImageFormat,
+    frame_delay_ms: u32,
 ) -> Result<Vec<u8>, image::error::ImageError> {
     use image::ExtendedColorType::Rgba8;
+    let img = &frames[0];
     let mut buf = Vec::new();
     match format {
         ImageFormat::Qoi => {
@@ -176,16 +205,74 @@ fn write_image(
             let encoder = WebPEncoder::new_lossless(&mut buf);
             encoder.write_image(img, img.width(), img.height(), Rgba8)?;
         }
+        ImageFormat::Apng => write_apng(&mut buf, frames, frame_delay_ms).map_err(|e| {
+            image::error::ImageError::Encoding(image::error::EncodingError::new(
+                image::error::ImageFormatHint::Exact(image::ImageFormat::Png),
+                e,
+            ))
+        })?,
     }
     Ok(buf)
 }
 
+/// フレームを順に表示して繰り返すAPNGを書き出す
+fn write_apng(
+    buf: &mut Vec<u8>,
+    frames: &[ImageBuffer<Rgba<u8>, Vec<u8>>],
+    frame_delay_ms: u32,
+) -> Result<(), png::EncodingError> {
+    let (width, height) = frames[0].dimensions();
+    let mut encoder = png::Encoder::new(buf, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder.set_animated(frames.len() as u32, 0)?;
+    encoder.set_frame_delay(frame_delay_ms.min(u16::MAX as u32) as u16, 1000)?;
+    let mut writer = encoder.write_header()?;
+    for frame in frames {
+        writer.write_image_data(frame.as_raw())?;
+    }
+    writer.finish()
+}
+
+/// 4分割の市松模様を生成する
+///
+/// shiftを1増やす毎に模様が1マス分ずれる
+fn checker_image(
+    width: u32,
+    height: u32,
+    front: Rgba<u8>,
+    back: Rgba<u8>,
+    shift: u32,
+) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    let cell_w = (width / 2).max(1);
+    let cell_h = (height / 2).max(1);
+    ImageBuffer::from_fn(width, height, |x, y| {
+        if ((x / cell_w).min(1) + (y / cell_h).min(1) + shift) % 2 == 0 {
+            front
+        } else {
+            back
+        }
+    })
+}
+
+/// グレースケールのパーリンノイズを生成する
+///
+/// `[-1, 1]`の値を`[0, 255]`に割り当てる。shiftを1増やす毎に1px分ずれる
+fn noise_image(width: u32, height: u32, scale: f32, shift: u32) -> ImageBuffer<Rgba<u8>, Vec<u8>> {
+    use noise::{NoiseFn, Perlin};
+    let perlin = Perlin::new(0);
+    let scale = scale as f64;
+    ImageBuffer::from_fn(width, height, |x, y| {
+        let v = perlin.get([(x + shift) as f64 * scale, y as f64 * scale]);
+        let l = ((v.clamp(-1.0, 1.0) + 1.0) * 127.5).round() as u8;
+        Rgba([l, l, l, 255])
+    })
+}
+
 async fn gen_texture(
     axum::extract::Path(_name): axum::extract::Path<String>,
     query: axum::extract::Query<TextureQuery>,
 ) -> impl IntoResponse {
-    use image::{ImageBuffer, Rgba};
-
     // parse query
     let front_color = Rgba(query.color_front());
     let back_color = Rgba(query.color_back());
@@ -193,14 +280,20 @@ async fn gen_texture(
     let height = query.height();
     let format = query.format();
 
+    let frames = match format {
+        ImageFormat::Apng => query.frames(),
+        _ => 1,
+    };
+
     // generage image
-    let img = ImageBuffer::from_fn(width, height, |x, y| match (x, y) {
-        (x, y) if x < width / 2 && y < height / 2 => front_color,
-        (x, y) if x >= width / 2 && y >= height / 2 => front_color,
-        _ => back_color,
-    });
+    let images = (0..frames)
+        .map(|i| match query.noise() {
+            true => noise_image(width, height, query.scale(), i),
+            false => checker_image(width, height, front_color, back_color, i),
+        })
+        .collect::<Vec<_>>();
 
-    match write_image(&img, format) {
+    match write_image(&images, format, query.frame_delay_ms()) {
         Ok(buf) => (
             StatusCode::OK,
             [(axum::http::header::CONTENT_TYPE, "image/png")],
@@ -220,3 +313,64 @@ async fn get_sleep(axum::extract::Path(msec): axum::extract::Path<u64>) -> impl
     tokio::time::sleep(std::time::Duration::from_millis(msec)).await;
     format!("slept {msec} msec").into_response()
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn get_texture(query: TextureQuery) -> Vec<u8> {
+        let res = gen_texture(
+            axum::extract::Path("test".to_string()),
+            axum::extract::Query(query),
+        )
+        .await
+        .into_response();
+        assert_eq!(res.status(), StatusCode::OK);
+        axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap()
+            .to_vec()
+    }
+
+    #[tokio::test]
+    async fn test_gen_texture_noise() {
+        let checker = get_texture(TextureQuery::default()).await;
+        let noise = get_texture(TextureQuery {
+            noise: Some(true),
+            ..Default::default()
+        })
+        .await;
+        assert_ne!(checker, noise);
+
+        // ノイズは0を中心にほぼ対称なので平均輝度は中央付近になる
+        let img = image::load_from_memory(&noise).unwrap().to_luma8();
+        let mean = img.pixels().map(|p| p.0[0] as f64).sum::<f64>() / img.len() as f64;
+        assert!((mean - 128.0).abs() < 24.0, "mean: {mean}");
+    }
+
+    #[test]
+    fn test_texture_query_frames() {
+        let frames = |frames| {
+            TextureQuery {
+                frames,
+                ..Default::default()
+            }
+            .frames()
+        };
+        assert_eq!(frames(None), 4);
+        assert_eq!(frames(Some(0)), 1);
+        assert_eq!(frames(Some(u32::MAX)), TextureQuery::MAX_FRAMES);
+    }
+
+    #[test]
+    fn test_write_apng() {
+        let frames = (0..4)
+            .map(|i| checker_image(8, 8, Rgba([255, 255, 255, 255]), Rgba([0, 0, 0, 255]), i))
+            .collect::<Vec<_>>();
+        let buf = write_image(&frames, ImageFormat::Apng, 100).unwrap();
+        assert_eq!(buf[..4], [137, 80, 78, 71]);
+        assert!(buf.windows(4).any(|w| w == b"acTL"));
+        // フレーム毎にfcTLが書かれる
+        assert_eq!(buf.windows(4).filter(|w| w == b"fcTL").count(), 4);
+    }
+}
@@ -0,0 +1,365 @@
+//! サーバー側で動かすライフゲーム
+//!
+//! WASMを使わないクライアントや自動テストから更新処理を確認するために使う
+
+use std::{convert::Infallible, sync::Arc, time::Duration};
+
+use axum::{
+    extract::{Path, Query, State},
+    http::{header, StatusCode},
+    response::{
+        sse::{Event, Sse},
+        IntoResponse,
+    },
+    routing::{get, post},
+    Json, Router,
+};
+use base64::Engine;
+use fixedbitset::FixedBitSet;
+use futures_util::Stream;
+use gol::Rule;
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use tokio::{sync::RwLock, task::JoinHandle};
+
+/// ライフゲームの空間
+///
+/// 更新処理はwasm-game-of-lifeと共有の[gol]クレートを使う
+#[derive(Debug, Clone)]
+pub struct Universe {
+    width: u32,
+    height: u32,
+    cells: FixedBitSet,
+}
+
+impl Universe {
+    /// シードを指定してランダムな状態で生成する
+    pub fn with_seed(width: u32, height: u32, seed: u64) -> Self {
+        let mut rng = StdRng::seed_from_u64(seed);
+        let size = (width * height) as usize;
+        let mut cells = FixedBitSet::with_capacity(size);
+        for i in 0..size {
+            cells.set(i, rng.gen_bool(0.5));
+        }
+        Self {
+            width,
+            height,
+            cells,
+        }
+    }
+
+    /// すべてのセルが死んだ状態で生成する
+    pub fn empty(width: u32, height: u32) -> Self {
+        Self {
+            width,
+            height,
+            cells: FixedBitSet::with_capacity((width * height) as usize),
+        }
+    }
+
+    /// `[row, col]`で指定したセルを生存させる。範囲外は無視する
+    pub fn set_cells(&mut self, cells: &[[u32; 2]]) {
+        for &[row, col] in cells {
+            if row < self.height && col < self.width {
+                let idx = self.get_index(row, col);
+                self.cells.insert(idx);
+            }
+        }
+    }
+
+    /// セル配列をビット列のまま返す
+    pub fn cells(&self) -> &[usize] {
+        self.cells.as_slice()
+    }
+
+    /// セル配列をリトルエンディアンのバイト列にしてbase64で返す
+    pub fn cells_base64(&self) -> String {
+        let bytes = self
+            .cells()
+            .iter()
+            .flat_map(|b| b.to_le_bytes())
+            .collect::<Vec<_>>();
+        base64::engine::general_purpose::STANDARD.encode(bytes)
+    }
+
+    /// 更新関数
+    pub fn tick(&mut self) {
+        self.cells = gol::next_generation(&self.cells, self.width, self.height, Rule::CONWAY);
+    }
+
+    fn get_index(&self, row: u32, column: u32) -> usize {
+        gol::index(self.width, row, column)
+    }
+}
+
+/// ストリーム生成のリクエスト
+#[derive(Debug, serde::Deserialize)]
+pub struct TickQuery {
+    width: Option<u32>,
+    height: Option<u32>,
+    seed: Option<u64>,
+}
+
+/// ストリームで生成できるUniverseの幅と高さの上限
+const MAX_TICK_SIZE: u32 = 1024;
+
+impl TickQuery {
+    fn universe(&self) -> Result<Universe, (StatusCode, String)> {
+        let width = self.width.unwrap_or(64);
+        let height = self.height.unwrap_or(64);
+        if width == 0 || height == 0 {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("universe must not be empty: {width}x{height}"),
+            ));
+        }
+        if width > MAX_TICK_SIZE || height > MAX_TICK_SIZE {
+            return Err((
+                StatusCode::BAD_REQUEST,
+                format!("universe too large: {width}x{height} (max {MAX_TICK_SIZE})"),
+            ));
+        }
+        // 上限内であれば溢れないが、念のため検査しておく
+        width.checked_mul(height).ok_or((
+            StatusCode::BAD_REQUEST,
+            format!("universe too large: {width}x{height}"),
+        ))?;
+        Ok(Universe::with_seed(width, height, self.seed.unwrap_or(42)))
+    }
+}
+
+/// 名前で取得できるRLE形式のパターン
+const PATTERNS: &[(&str, &str)] = &[
+    ("blinker", "x = 3, y = 1, rule = B3/S23\n3o!\n"),
+    ("glider", "x = 3, y = 3, rule = B3/S23\nbo$2bo$3o!\n"),
+    ("r-pentomino", "x = 3, y = 3, rule = B3/S23\nb2o$2o$bo!\n"),
+    (
+        "gosper-glider-gun",
+        "x = 36, y = 9, rule = B3/S23\n\
+         24bo$22bobo$12b2o6b2o12b2o$11bo3bo4b2o12b2o$2o8bo5bo3b2o$2o8bo3bob2o4b\n\
+         obo$10bo5bo7bo$11bo3bo$12b2o!\n",
+    ),
+];
+
+/// 複数のクライアントから共有するUniverseの大きさ
+const SHARED_SIZE: (u32, u32) = (64, 64);
+
+/// 複数のクライアントで共有するUniverse
+///
+/// [routes]にRouterの状態として渡す
+#[derive(Debug, Clone)]
+pub struct SharedUniverse(Arc<RwLock<Universe>>);
+
+impl SharedUniverse {
+    /// すべてのセルが死んだ状態で生成する
+    pub fn new() -> Self {
+        let (width, height) = SHARED_SIZE;
+        Self(Arc::new(RwLock::new(Universe::empty(width, height))))
+    }
+
+    /// 10Hzで更新するタスクを呼び出し元のランタイムで起動する
+    pub fn spawn_ticker(&self) -> JoinHandle<()> {
+        let ticker = self.0.clone();
+        tokio::spawn(async move {
+            let mut interval = tokio::time::interval(Duration::from_millis(100));
+            loop {
+                interval.tick().await;
+                ticker.write().await.tick();
+            }
+        })
+    }
+}
+
+impl Default for SharedUniverse {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// セル設定のリクエスト
+#[derive(Debug, serde::Deserialize)]
+pub struct SetCellsRequest {
+    /// 生存させるセルの`[row, col]`
+    cells: Vec<[u32; 2]>,
+}
+
+/// `/api/universe` 以下のルート
+pub fn routes(shared: SharedUniverse) -> Router {
+    Router::new()
+        .route("/tick", get(tick_sse))
+        .route("/pattern/:name", get(get_pattern))
+        .route("/set_cells", post(set_cells))
+        .route("/cells", get(get_cells))
+        .with_state(shared)
+}
+
+/// 共有Universeのセルを生存させる
+async fn set_cells(
+    State(shared): State<SharedUniverse>,
+    Json(req): Json<SetCellsRequest>,
+) -> impl IntoResponse {
+    shared.0.write().await.set_cells(&req.cells);
+    StatusCode::NO_CONTENT
+}
+
+/// 共有Universeのセル配列をbase64で返す
+async fn get_cells(State(shared): State<SharedUniverse>) -> impl IntoResponse {
+    let cells = shared.0.read().await.cells_base64();
+    ([(header::CONTENT_TYPE, "text/plain")], cells)
+}
+
+/// 既知のパターンをRLE形式で返す
+async fn get_pattern(Path(name): Path<String>) -> impl IntoResponse {
+    match PATTERNS.iter().find(|(n, _)| *n == name) {
+        Some((_, rle)) => (StatusCode::OK, [(header::CONTENT_TYPE, "text/plain")], *rle),
+        None => (
+            StatusCode::NOT_FOUND,
+            [(header::CONTENT_TYPE, "text/plain")],
+            "unknown pattern",
+        ),
+    }
+}
+
+/// 10Hzで更新したセル配列をSSEで送り続ける
+async fn tick_sse(
+    query: Query<TickQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, Infallible>>>, (StatusCode, String)> {
+    let universe = query.universe()?;
+    let interval = tokio::time::interval(Duration::from_millis(100));
+    let stream = futures_util::stream::unfold(
+        (universe, interval),
+        |(mut universe, mut interval)| async move {
+            interval.tick().await;
+            universe.tick();
+            let event = Event::default().data(universe.cells_base64());
+            Some((Ok(event), (universe, interval)))
+        },
+    );
+    Ok(Sse::new(stream))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+    use futures_util::StreamExt;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    #[test]
+    fn test_tick_blinker() {
+        let mut u = Universe::with_seed(5, 5, 0);
+        u.cells.clear();
+        // 横向きのブリンカーは縦向きになる
+        for col in 1..4 {
+            let idx = u.get_index(2, col);
+            u.cells.insert(idx);
+        }
+        u.tick();
+        let alive = u.cells.ones().collect::<Vec<_>>();
+        assert_eq!(alive, vec![7, 12, 17]);
+    }
+
+    #[tokio::test]
+    async fn test_get_pattern() {
+        let req = Request::get("/pattern/glider").body(Body::empty()).unwrap();
+        let res = routes(SharedUniverse::new()).oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        assert_eq!(res.headers()[header::CONTENT_TYPE], "text/plain");
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(body.starts_with(b"x = 3"));
+
+        let req = Request::get("/pattern/unknown")
+            .body(Body::empty())
+            .unwrap();
+        let res = routes(SharedUniverse::new()).oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_shared_universe_cells() {
+        let shared = SharedUniverse::new();
+        let ticker = shared.spawn_ticker();
+        let app = routes(shared);
+        let req = Request::post("/set_cells")
+            .header(header::CONTENT_TYPE, "application/json")
+            .body(Body::from(
+                r#"{"cells": [[0, 1], [1, 2], [2, 0], [2, 1], [2, 2]]}"#,
+            ))
+            .unwrap();
+        let res = app.clone().oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::NO_CONTENT);
+
+        let req = Request::get("/cells").body(Body::empty()).unwrap();
+        let res = app.oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::OK);
+        let body = axum::body::to_bytes(res.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let bytes = base64::engine::general_purpose::STANDARD
+            .decode(body)
+            .unwrap();
+        assert_eq!(bytes.len(), 64 * 64 / 8);
+        // 空の空間ではグライダーは更新されても5セルのまま
+        let alive = bytes.iter().map(|b| b.count_ones()).sum::<u32>();
+        assert_eq!(alive, 5);
+        ticker.abort();
+    }
+
+    #[tokio::test]
+    async fn test_tick_sse() {
+        let req = Request::get("/tick?width=64&height=64&seed=42")
+            .body(Body::empty())
+            .unwrap();
+        let res = routes(SharedUniverse::new()).oneshot(req).await.unwrap();
+        assert_eq!(
+            res.headers()[axum::http::header::CONTENT_TYPE],
+            "text/event-stream"
+        );
+
+        // チャンクの区切りに依存しないように行単位で取り出す
+        let mut body = res.into_body().into_data_stream();
+        let mut buf = String::new();
+        let mut states = vec![];
+        while states.len() < 5 {
+            let chunk = body.next().await.unwrap().unwrap();
+            buf.push_str(std::str::from_utf8(&chunk).unwrap());
+            while let Some(pos) = buf.find("\n\n") {
+                let event = buf[..pos].to_string();
+                buf.drain(..pos + 2);
+                if let Some(data) = event.strip_prefix("data: ") {
+                    states.push(
+                        base64::engine::general_purpose::STANDARD
+                            .decode(data)
+                            .unwrap(),
+                    );
+                }
+            }
+        }
+        for w in states.windows(2) {
+            assert_eq!(w[0].len(), 64 * 64 / 8);
+            assert_ne!(w[0], w[1]);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_tick_sse_too_large() {
+        let req = Request::get("/tick?width=65536&height=65536")
+            .body(Body::empty())
+            .unwrap();
+        let res = routes(SharedUniverse::new()).oneshot(req).await.unwrap();
+        assert_eq!(res.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_tick_sse_empty() {
+        for query in ["width=0&height=64", "width=64&height=0"] {
+            let req = Request::get(format!("/tick?{query}"))
+                .body(Body::empty())
+                .unwrap();
+            let res = routes(SharedUniverse::new()).oneshot(req).await.unwrap();
+            assert_eq!(res.status(), StatusCode::BAD_REQUEST, "{query}");
+        }
+    }
+}